@@ -0,0 +1,12 @@
+use std::{env, fs, path::PathBuf};
+
+fn main() {
+    if env::var_os("CARGO_FEATURE_RT").is_some() {
+        let out = PathBuf::from(env::var_os("OUT_DIR").unwrap());
+        fs::copy("link.x", out.join("link.x")).unwrap();
+        println!("cargo:rustc-link-search={}", out.display());
+    }
+
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-changed=link.x");
+}
@@ -0,0 +1,74 @@
+//! Encodes a WAV, AIFF or AIFC file to a VADPCM AIFC file, like the SGI
+//! `tabledesign` and `vadpcm_enc` tools together.
+//!
+//! ```text
+//! cargo run --features std --example vadpcm_enc -- [options] input output.aifc
+//!
+//!   --order N             previous samples each prediction uses (2)
+//!   --predictors N        predictors in the codebook (4)
+//!   --loop START END N    loop from START to END N times, or forever with -1
+//! ```
+
+use std::{env, fs, process::ExitCode};
+
+use nintendo64_pac::ai::vadpcm::{
+    aifc::{self, AudioFile},
+    encoder::{self, EncoderOptions},
+    wav,
+};
+
+fn main() -> ExitCode {
+    match run(env::args().skip(1).collect()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(message) => {
+            eprintln!("vadpcm_enc: {message}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(args: Vec<String>) -> Result<(), String> {
+    let mut options = EncoderOptions::new();
+    let mut paths = Vec::new();
+    let mut args = args.into_iter();
+
+    while let Some(arg) = args.next() {
+        let mut number = |name: &str| -> Result<i64, String> {
+            let value = args.next().ok_or(format!("{name} needs a value"))?;
+            value
+                .parse()
+                .map_err(|_| format!("invalid {name}: {value}"))
+        };
+
+        match arg.as_str() {
+            "--order" => options = options.order(number("--order")? as usize),
+            "--predictors" => options = options.predictors(number("--predictors")? as usize),
+            "--loop" => {
+                let start = number("--loop start")? as u32;
+                let end = number("--loop end")? as u32;
+                let count = match number("--loop count")? {
+                    -1 => u32::MAX,
+                    count => count as u32,
+                };
+                options = options.looping(start, end, count);
+            }
+            _ => paths.push(arg),
+        }
+    }
+
+    let [input, output] = <[String; 2]>::try_from(paths)
+        .map_err(|_| "usage: vadpcm_enc [options] input output.aifc".to_string())?;
+
+    let data = fs::read(&input).map_err(|error| format!("{input}: {error}"))?;
+    let pcm = match data.get(0..4) {
+        Some(b"RIFF") => wav::read(&data),
+        _ => aifc::read(&data).and_then(|file| match file {
+            AudioFile::Pcm(pcm) => Ok(pcm),
+            AudioFile::Vadpcm(_) => Err(aifc::FileError::UnsupportedFormat),
+        }),
+    }
+    .map_err(|error| format!("{input}: {error:?}"))?;
+
+    let sound = encoder::encode(&pcm, &options).map_err(|error| format!("{error:?}"))?;
+    fs::write(&output, aifc::write_aifc(&sound)).map_err(|error| format!("{output}: {error}"))
+}
@@ -0,0 +1,67 @@
+/* Linker script template for the `rt` feature of nintendo64-pac.
+ *
+ * IPL3 copies the first megabyte following the ROM header to 0x80000400 and
+ * jumps there, so `_start` must be the very first thing in `.text`. */
+
+ENTRY(_start)
+
+MEMORY
+{
+    RDRAM : ORIGIN = 0x80000400, LENGTH = 0x3FFC00
+}
+
+/* Overridable exception handler; defaults to passing interrupts to the
+ * interrupt handler and showing the crash screen for anything else. */
+PROVIDE(__n64_exception_handler = __n64_default_exception_handler);
+
+/* Overridable interrupt handler; defaults to masking the pending lines. */
+PROVIDE(__n64_interrupt_handler = __n64_default_interrupt_handler);
+
+SECTIONS
+{
+    .text : ALIGN(16)
+    {
+        KEEP(*(.text.entry))
+        *(.text .text.*)
+    } > RDRAM
+
+    .rodata : ALIGN(8)
+    {
+        *(.rodata .rodata.*)
+    } > RDRAM
+
+    .data : ALIGN(8)
+    {
+        *(.data .data.*)
+    } > RDRAM
+
+    . = ALIGN(8);
+    _gp = . + 0x8000;
+
+    .sdata : ALIGN(8)
+    {
+        *(.sdata .sdata.*)
+    } > RDRAM
+
+    .sbss (NOLOAD) : ALIGN(8)
+    {
+        __bss_start = .;
+        *(.sbss .sbss.* .scommon)
+    } > RDRAM
+
+    .bss (NOLOAD) : ALIGN(8)
+    {
+        *(.bss .bss.* COMMON)
+        . = ALIGN(8);
+        __bss_end = .;
+    } > RDRAM
+
+    __heap_start = .;
+
+    /DISCARD/ :
+    {
+        *(.MIPS.abiflags)
+        *(.reginfo)
+        *(.eh_frame .eh_frame_hdr)
+    }
+}
@@ -2,11 +2,32 @@
 
 use proc_bitfield::bitfield;
 
-use crate::{fields, registers};
+use crate::{
+    fields,
+    mi::{Mi, MiVersionReg},
+    registers,
+    vi::mode::VideoStandard,
+};
+
+pub mod mixer;
+pub mod output;
+pub mod vadpcm;
 
 pub const AI_BASE_ADDR: u32 = 0x0450_0000;
 pub const AI_OFFSET: u32 = 0xA000_0000;
 
+/// # Minimum DAC rate
+///
+/// Smallest divider of the video clock the DAC runs at, in clocks per
+/// sample.
+pub const AI_MIN_DAC_RATE: u32 = 132;
+
+/// # Maximum DAC rate
+pub const AI_MAX_DAC_RATE: u32 = 0x4000;
+
+/// # Maximum bitrate
+pub const AI_MAX_BITRATE: u32 = 16;
+
 registers! {
     /// # Audio interface (AI)
     AI_BASE_ADDR => Ai {
@@ -91,8 +112,255 @@ fields! [
     ux::u24 => RdramAddress,
 
     /// # Transfer length (v1.0)
-    ux::u14 => TransferLengthV1,
+    ux::u15 => TransferLengthV1,
 
     /// # Transfer length (v2.0)
     ux::u18 => TransferLengthV2,
 ];
+
+/// # AI DMA alignment
+///
+/// Alignment in bytes of the address and length of every AI DMA transfer.
+pub const AI_DMA_ALIGN: u32 = 8;
+
+/// # AI DMA boundary
+///
+/// The AI fetches a transfer queued after one that ended on a multiple of
+/// this many bytes from this many bytes past its address.
+pub const AI_DMA_BOUNDARY: u32 = 0x2000;
+
+/// Address to queue for a transfer at `address`, given the end of the
+/// transfer queued before it.
+///
+/// Moves the address back by [`AI_DMA_BOUNDARY`] after a transfer that ended
+/// on one, which the AI then misfetches from the right place, as libultra's
+/// `osAiSetNextBuffer` does.
+pub const fn dma_address(address: u32, previous_end: Option<u32>) -> u32 {
+    match previous_end {
+        Some(end) if end.is_multiple_of(AI_DMA_BOUNDARY) => address.wrapping_sub(AI_DMA_BOUNDARY),
+        _ => address,
+    }
+}
+
+/// # AI DMA error
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AiDmaError {
+    /// The address is not a multiple of [`AI_DMA_ALIGN`].
+    MisalignedAddress,
+
+    /// The length is not a multiple of [`AI_DMA_ALIGN`].
+    MisalignedLength,
+
+    /// The length is zero.
+    Empty,
+
+    /// The length exceeds [`AiRevision::max_length`].
+    TooLong,
+
+    /// Both slots of the DMA FIFO are taken.
+    FifoFull,
+}
+
+/// # AI revision
+///
+/// The AI of the first RCP revision has a 15-bit length register; later
+/// revisions widen it to 18 bits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AiRevision {
+    V1,
+    V2,
+}
+
+impl AiRevision {
+    /// Revision of the AI in the RCP reporting `version`.
+    ///
+    /// The first RCP revision reports an IO version of 1.
+    pub fn from_version(version: MiVersionReg) -> Self {
+        match u8::from(version.io()) {
+            0 | 1 => Self::V1,
+            _ => Self::V2,
+        }
+    }
+
+    /// Revision of the AI in this console.
+    pub fn detect(mi: &Mi) -> Self {
+        Self::from_version(unsafe { core::ptr::read_volatile(&mi.mi_version_reg) })
+    }
+
+    /// Largest transfer in bytes, rounded down to [`AI_DMA_ALIGN`].
+    pub const fn max_length(self) -> u32 {
+        match self {
+            Self::V1 => 0x7FF8,
+            Self::V2 => 0x3FFF8,
+        }
+    }
+
+    /// Length register value for a transfer of `len` bytes at `address`.
+    pub fn len_reg(self, address: u32, len: u32) -> Result<AiLenReg, AiDmaError> {
+        if !address.is_multiple_of(AI_DMA_ALIGN) {
+            return Err(AiDmaError::MisalignedAddress);
+        }
+        if !len.is_multiple_of(AI_DMA_ALIGN) {
+            return Err(AiDmaError::MisalignedLength);
+        }
+        if len == 0 {
+            return Err(AiDmaError::Empty);
+        }
+        if len > self.max_length() {
+            return Err(AiDmaError::TooLong);
+        }
+
+        Ok(match self {
+            Self::V1 => AiLenReg(0).with_transfer_length_v1(len.into()),
+            Self::V2 => AiLenReg(0).with_transfer_length_v2(len.into()),
+        })
+    }
+
+    /// Splits `len` bytes at `address` into transfers of at most
+    /// [`AiRevision::max_length`] bytes, as `(address, len)` pairs.
+    pub fn chunks(self, address: u32, len: u32) -> impl Iterator<Item = (u32, u32)> {
+        let max = self.max_length();
+        (0..len.div_ceil(max)).map(move |index| {
+            let offset = index * max;
+            (address + offset, (len - offset).min(max))
+        })
+    }
+}
+
+/// # Frequency error
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrequencyError {
+    /// The frequency needs a DAC rate below [`AI_MIN_DAC_RATE`].
+    TooHigh,
+
+    /// The frequency needs a DAC rate above [`AI_MAX_DAC_RATE`].
+    TooLow,
+}
+
+/// # Audio rate
+///
+/// Dividers of the video clock for a sample rate, computed like libultra's
+/// `osAiSetFrequency`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AudioRate {
+    /// Video clocks per sample.
+    pub dac_rate: u32,
+    /// Video clocks per bit sent to the DAC.
+    pub bitrate: u32,
+    /// Sample rate actually produced, in Hz.
+    pub frequency: u32,
+}
+
+impl AudioRate {
+    /// Computes the dividers closest to `hz` on `standard`'s video clock.
+    pub const fn new(hz: u32, standard: VideoStandard) -> Result<Self, FrequencyError> {
+        let clock = standard.clock_hz();
+        if hz == 0 {
+            return Err(FrequencyError::TooLow);
+        }
+
+        let dac_rate = (clock / hz) + ((clock % hz) * 2 >= hz) as u32;
+        if dac_rate < AI_MIN_DAC_RATE {
+            return Err(FrequencyError::TooHigh);
+        }
+        if dac_rate > AI_MAX_DAC_RATE {
+            return Err(FrequencyError::TooLow);
+        }
+
+        let bitrate = match dac_rate / 66 {
+            bitrate if bitrate > AI_MAX_BITRATE => AI_MAX_BITRATE,
+            bitrate => bitrate,
+        };
+
+        Ok(Self {
+            dac_rate,
+            bitrate,
+            frequency: clock / dac_rate,
+        })
+    }
+
+    /// DAC rate register value.
+    pub fn dacrate_reg(&self) -> AiDacrateReg {
+        AiDacrateReg(0).with_dac_rate((self.dac_rate - 1).into())
+    }
+
+    /// Bitrate register value.
+    pub fn bitrate_reg(&self) -> AiBitrateReg {
+        AiBitrateReg(0).with_bitrate((self.bitrate - 1).into())
+    }
+}
+
+impl Ai {
+    /// Sets the sample rate closest to `hz` and returns the rate achieved.
+    pub fn set_frequency(
+        &mut self,
+        hz: u32,
+        standard: VideoStandard,
+    ) -> Result<u32, FrequencyError> {
+        let rate = AudioRate::new(hz, standard)?;
+        unsafe {
+            core::ptr::write_volatile(&mut self.ai_dacrate_reg, rate.dacrate_reg());
+            core::ptr::write_volatile(&mut self.ai_bitrate_reg, rate.bitrate_reg());
+        }
+        Ok(rate.frequency)
+    }
+
+    /// Number of buffers in the DMA FIFO, including the one playing.
+    pub fn queued(&self) -> usize {
+        let status = unsafe { core::ptr::read_volatile(&self.ai_status_reg) };
+        match (status.ai_full(), status.ai_busy()) {
+            (true, _) => 2,
+            (false, true) => 1,
+            (false, false) => 0,
+        }
+    }
+
+    /// Queues `len` bytes of samples at the physical address `address`.
+    ///
+    /// `address` should already be adjusted with [`dma_address`]. Fails with
+    /// [`AiDmaError::FifoFull`] if two transfers are already queued.
+    ///
+    /// Buffers longer than [`AiRevision::max_length`] are not split here, as
+    /// the rest could only be queued once the FIFO drains. Only
+    /// [`AudioOutput`](output::AudioOutput) splits them automatically;
+    /// otherwise queue each of [`AiRevision::chunks`] as the FIFO has room.
+    pub fn queue(
+        &mut self,
+        revision: AiRevision,
+        address: u32,
+        len: u32,
+    ) -> Result<(), AiDmaError> {
+        let len_reg = revision.len_reg(address, len)?;
+        if self.queued() == 2 {
+            return Err(AiDmaError::FifoFull);
+        }
+        unsafe {
+            core::ptr::write_volatile(
+                &mut self.ai_dram_addr_reg,
+                AiDramAddrReg(0).with_starting_rdram_address(address.into()),
+            );
+            core::ptr::write_volatile(&mut self.ai_len_reg, len_reg);
+        }
+        Ok(())
+    }
+
+    /// Turns DMA to the DAC on or off.
+    pub fn set_dma_enable(&mut self, enable: bool) {
+        unsafe {
+            core::ptr::write_volatile(
+                &mut self.ai_control_reg,
+                AiControlReg(0).with_dma_enable(enable),
+            );
+        }
+    }
+
+    /// Acknowledges the AI interrupt.
+    pub fn acknowledge_interrupt(&mut self) {
+        unsafe {
+            core::ptr::write_volatile(
+                &mut self.ai_status_reg,
+                AiStatusReg(0).with_clear_ai_intr(true),
+            );
+        }
+    }
+}
@@ -0,0 +1,332 @@
+//! # Software mixer
+//!
+//! [`Mixer`] mixes up to `V` voices of mono 16-bit sounds into interleaved
+//! stereo frames for the AI, on the CPU and in fixed point. Each voice has
+//! its own volume, pan and pitch, and plays its sound once or loops it.
+//!
+//! Voices are resampled with linear interpolation from the sound's rate,
+//! scaled by the pitch, to the mixer's rate. Positions and steps are 16.16
+//! fixed point, volumes are 8.8 and the mix is saturated to 16 bits.
+//!
+//! The mixer fills [`AudioOutput`](super::output::AudioOutput) buffers
+//! directly:
+//!
+//! ```ignore
+//! output.fill(|samples| mixer.mix(samples));
+//! ```
+
+/// # Unity volume
+pub const UNITY_VOLUME: u16 = 0x100;
+
+/// # Unity pitch
+pub const UNITY_PITCH: u32 = 0x1_0000;
+
+/// # Sound
+///
+/// Mono signed 16-bit samples at a given rate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Sound<'a> {
+    samples: &'a [i16],
+    rate: u32,
+    loop_start: Option<usize>,
+}
+
+impl<'a> Sound<'a> {
+    /// A sound that plays `samples` at `rate` Hz once.
+    pub const fn new(samples: &'a [i16], rate: u32) -> Self {
+        Self {
+            samples,
+            rate,
+            loop_start: None,
+        }
+    }
+
+    /// Loops back to the sample at `start` on reaching the end.
+    pub const fn looping(mut self, start: usize) -> Self {
+        self.loop_start = Some(start);
+        self
+    }
+
+    /// The samples.
+    pub fn samples(&self) -> &'a [i16] {
+        self.samples
+    }
+
+    /// The sample rate in Hz.
+    pub fn rate(&self) -> u32 {
+        self.rate
+    }
+
+    /// Where the sound loops back to, if it loops.
+    pub fn loop_start(&self) -> Option<usize> {
+        self.loop_start
+    }
+}
+
+/// # Playback parameters
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Playback {
+    /// Volume in 8.8 fixed point, [`UNITY_VOLUME`] plays unchanged.
+    pub volume: u16,
+    /// Stereo position, from -128 for left only to 127 for right only.
+    pub pan: i8,
+    /// Playback speed in 16.16 fixed point, [`UNITY_PITCH`] plays at the
+    /// sound's own rate.
+    ///
+    /// A sound that would advance by less than 1/65536 of a sample per
+    /// output frame, such as one of rate 0 or at pitch 0, ends at once.
+    pub pitch: u32,
+}
+
+impl Playback {
+    /// Unity volume, centred and at the sound's own rate.
+    pub const DEFAULT: Self = Self {
+        volume: UNITY_VOLUME,
+        pan: 0,
+        pitch: UNITY_PITCH,
+    };
+}
+
+impl Default for Playback {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// # Voice handle
+///
+/// Identifies a sound started with [`Mixer::play`]. Handles of sounds that
+/// have ended are ignored, even once their voice is reused.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VoiceId {
+    index: usize,
+    generation: u32,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Voice<'a> {
+    sound: Sound<'a>,
+    playback: Playback,
+    generation: u32,
+    /// Position in the sound in 16.16 fixed point.
+    position: u64,
+    /// Position increment per output frame in 16.16 fixed point.
+    step: u64,
+    left: i32,
+    right: i32,
+}
+
+impl Voice<'_> {
+    /// Recomputes the step and pan gains from the playback parameters.
+    fn configure(&mut self, output_rate: u32) {
+        let rate = u64::from(self.sound.rate) * u64::from(self.playback.pitch);
+        self.step = rate / u64::from(output_rate.max(1));
+
+        // Each side fades out over its own half of the range, so both ends
+        // fully silence the other side.
+        let pan = i32::from(self.playback.pan);
+        self.left = match pan > 0 {
+            true => 256 * (127 - pan) / 127,
+            false => 256,
+        };
+        self.right = match pan < 0 {
+            true => 256 * (128 + pan) / 128,
+            false => 256,
+        };
+    }
+
+    /// Whether the voice advances through its sound at all.
+    fn is_moving(&self) -> bool {
+        self.step > 0
+    }
+
+    /// Interpolated sample at the current position, or `None` past the end.
+    fn sample(&self) -> Option<i32> {
+        let samples = self.sound.samples;
+        let index = (self.position >> 16) as usize;
+        let first = i32::from(*samples.get(index)?);
+
+        let next = match (samples.get(index + 1), self.sound.loop_start) {
+            (Some(&next), _) => i32::from(next),
+            (None, Some(start)) => samples.get(start).map_or(first, |&next| i32::from(next)),
+            (None, None) => first,
+        };
+
+        // 15 bits of fraction keep the product within an `i32`.
+        let fraction = (self.position & 0xFFFF) as i32 >> 1;
+        Some(first + (((next - first) * fraction) >> 15))
+    }
+
+    /// Moves to the next output frame and returns whether the voice still
+    /// plays.
+    fn advance(&mut self) -> bool {
+        self.position += self.step;
+
+        let len = self.sound.samples.len() as u64;
+        match self.sound.loop_start {
+            Some(start) if (start as u64) < len => {
+                let loop_len = (len - start as u64) << 16;
+                while self.position >= len << 16 {
+                    self.position -= loop_len;
+                }
+                true
+            }
+            _ => self.position < len << 16,
+        }
+    }
+}
+
+/// # Mixer
+pub struct Mixer<'a, const V: usize> {
+    rate: u32,
+    voices: [Option<Voice<'a>>; V],
+    generation: u32,
+}
+
+impl<'a, const V: usize> Mixer<'a, V> {
+    /// A mixer producing frames at `rate` Hz, which should be the rate
+    /// returned by [`Ai::set_frequency`](super::Ai::set_frequency).
+    pub const fn new(rate: u32) -> Self {
+        Self {
+            rate,
+            voices: [None; V],
+            generation: 0,
+        }
+    }
+
+    /// The output rate in Hz.
+    pub fn rate(&self) -> u32 {
+        self.rate
+    }
+
+    /// Number of voices playing.
+    pub fn active(&self) -> usize {
+        self.voices.iter().flatten().count()
+    }
+
+    /// Starts playing `sound` on a free voice.
+    ///
+    /// Returns `None` when every voice is busy.
+    pub fn play(&mut self, sound: Sound<'a>, playback: Playback) -> Option<VoiceId> {
+        let index = self.voices.iter().position(Option::is_none)?;
+        self.generation = self.generation.wrapping_add(1);
+
+        let mut voice = Voice {
+            sound,
+            playback,
+            generation: self.generation,
+            position: 0,
+            step: 0,
+            left: 0,
+            right: 0,
+        };
+        voice.configure(self.rate);
+        self.voices[index] = Some(voice).filter(Voice::is_moving);
+
+        Some(VoiceId {
+            index,
+            generation: self.generation,
+        })
+    }
+
+    /// Whether the sound of `id` is still playing.
+    pub fn is_playing(&self, id: VoiceId) -> bool {
+        self.voice(id).is_some()
+    }
+
+    /// Stops the sound of `id`.
+    pub fn stop(&mut self, id: VoiceId) {
+        if self.voice(id).is_some() {
+            self.voices[id.index] = None;
+        }
+    }
+
+    /// Stops every sound.
+    pub fn stop_all(&mut self) {
+        self.voices = [None; V];
+    }
+
+    /// Playback parameters of the sound of `id`, if it is still playing.
+    pub fn playback(&self, id: VoiceId) -> Option<Playback> {
+        self.voice(id).map(|voice| voice.playback)
+    }
+
+    /// Changes the playback parameters of the sound of `id`, if it is still
+    /// playing.
+    pub fn set_playback(&mut self, id: VoiceId, playback: Playback) {
+        let rate = self.rate;
+        if let Some(voice) = self.voice_mut(id) {
+            voice.playback = playback;
+            voice.configure(rate);
+            if !voice.is_moving() {
+                self.voices[id.index] = None;
+            }
+        }
+    }
+
+    /// Mixes the next frames into `out`, as interleaved left and right
+    /// samples.
+    ///
+    /// The samples are native-endian, which on the console is the
+    /// big-endian order the AI reads.
+    pub fn mix(&mut self, out: &mut [i16]) {
+        for frame in out.chunks_exact_mut(2) {
+            let (left, right) = self.next_frame();
+            frame[0] = left;
+            frame[1] = right;
+        }
+    }
+
+    /// Mixes the next frames into `out` as big-endian bytes, four per frame,
+    /// for writing into AI buffers from any host.
+    pub fn mix_bytes(&mut self, out: &mut [u8]) {
+        for frame in out.chunks_exact_mut(4) {
+            let (left, right) = self.next_frame();
+            frame[0..2].copy_from_slice(&left.to_be_bytes());
+            frame[2..4].copy_from_slice(&right.to_be_bytes());
+        }
+    }
+
+    fn next_frame(&mut self) -> (i16, i16) {
+        let (mut left, mut right) = (0i32, 0i32);
+
+        for slot in self.voices.iter_mut() {
+            let Some(voice) = slot else {
+                continue;
+            };
+
+            let Some(sample) = voice.sample() else {
+                *slot = None;
+                continue;
+            };
+
+            let sample = (sample * i32::from(voice.playback.volume)) >> 8;
+            left += (sample * voice.left) >> 8;
+            right += (sample * voice.right) >> 8;
+
+            if !voice.advance() {
+                *slot = None;
+            }
+        }
+
+        (saturate(left), saturate(right))
+    }
+
+    fn voice(&self, id: VoiceId) -> Option<&Voice<'a>> {
+        self.voices
+            .get(id.index)?
+            .as_ref()
+            .filter(|voice| voice.generation == id.generation)
+    }
+
+    fn voice_mut(&mut self, id: VoiceId) -> Option<&mut Voice<'a>> {
+        self.voices
+            .get_mut(id.index)?
+            .as_mut()
+            .filter(|voice| voice.generation == id.generation)
+    }
+}
+
+fn saturate(value: i32) -> i16 {
+    value.clamp(i32::from(i16::MIN), i32::from(i16::MAX)) as i16
+}
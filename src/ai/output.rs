@@ -0,0 +1,415 @@
+//! # Audio output
+//!
+//! [`AudioOutput`] streams samples to the AI from a ring of `N` buffers
+//! carved out of a block of RDRAM. The application fills buffers, either
+//! with [`AudioOutput::fill`] or from a callback run in the interrupt
+//! handler, and filled buffers are queued to the AI whenever its two-deep DMA
+//! FIFO has room.
+//!
+//! The AI interrupt fires each time a transfer finishes and the next one
+//! starts playing. Buffers longer than the AI revision allows are queued as
+//! several transfers. If the FIFO runs dry the DAC repeats its last sample
+//! until more buffers are queued, which is counted as an underrun.
+//!
+//! Samples are interleaved stereo pairs of signed 16-bit values, left first.
+//! On the console, buffers are handed out through KSEG1 so filling bypasses
+//! the data cache and needs no write back before queueing.
+//!
+//! Transfers following one that ends on an
+//! [`AI_DMA_BOUNDARY`](super::AI_DMA_BOUNDARY) are queued
+//! with the address the AI hardware bug expects, see [`dma_address`].
+
+use core::cell::RefCell;
+
+use critical_section::Mutex;
+
+use crate::{
+    memory::{physical, uncached, writeback_invalidate_dcache},
+    mi::{Mi, MiIntrMaskReg},
+};
+
+use super::{dma_address, Ai, AiRevision, AI_DMA_ALIGN};
+
+/// # Audio output error
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AudioOutputError {
+    /// Fewer than two buffers were requested.
+    TooFewBuffers,
+
+    /// The buffer length is zero or not a multiple of [`AI_DMA_ALIGN`]
+    /// bytes.
+    InvalidLength,
+
+    /// The memory block cannot hold every buffer.
+    OutOfMemory,
+}
+
+/// # Sample callback
+///
+/// Called from [`AudioOutput::on_interrupt`] with each free buffer, as
+/// interleaved stereo samples.
+pub type AudioCallback = fn(&mut [i16]);
+
+/// # Double-buffered audio output
+///
+/// Shared between the main loop and the AI interrupt handler, so its state is
+/// only touched inside critical sections.
+pub struct AudioOutput<const N: usize> {
+    revision: AiRevision,
+    buffers: [*mut i16; N],
+    len: usize,
+    inner: Mutex<RefCell<Inner<N>>>,
+}
+
+struct Inner<const N: usize> {
+    ai: Ai,
+    callback: Option<AudioCallback>,
+    /// Whether [`AudioOutput::fill`] is writing the next free buffer.
+    filling: bool,
+    ring: Ring<N>,
+    underruns: u32,
+    /// Physical end of the last transfer queued.
+    last_end: Option<u32>,
+}
+
+/// Which buffers are filled, and how far they are queued to the AI.
+struct Ring<const N: usize> {
+    /// Oldest filled buffer still playing or waiting to.
+    playing: usize,
+    /// Filled buffers, from `playing`.
+    filled: usize,
+    /// Filled buffers entirely queued to the AI, from `playing`.
+    submitted: usize,
+    /// Bytes of the next buffer to submit already queued.
+    offset: usize,
+    /// Transfers in the FIFO, oldest first, and whether each ends a buffer.
+    fifo: [bool; 2],
+    fifo_len: usize,
+}
+
+impl<const N: usize> Ring<N> {
+    const fn new() -> Self {
+        Self {
+            playing: 0,
+            filled: 0,
+            submitted: 0,
+            offset: 0,
+            fifo: [false; 2],
+            fifo_len: 0,
+        }
+    }
+
+    /// Index of the buffer `offset` places after the oldest playing one.
+    fn next(&self, offset: usize) -> usize {
+        (self.playing + offset) % N
+    }
+
+    /// The next free buffer, if any.
+    fn free(&self) -> Option<usize> {
+        (self.filled < N).then(|| self.next(self.filled))
+    }
+
+    /// Recycles the buffers the AI has finished with, given the transfers
+    /// still in its FIFO. Returns whether the FIFO ran dry.
+    fn update(&mut self, queued: usize) -> bool {
+        let underrun = self.fifo_len > 0 && queued == 0;
+
+        while self.fifo_len > queued {
+            let ends_buffer = self.fifo[0];
+            self.fifo = [self.fifo[1], false];
+            self.fifo_len -= 1;
+
+            if ends_buffer {
+                self.playing = self.next(1);
+                self.filled -= 1;
+                self.submitted -= 1;
+            }
+        }
+
+        underrun
+    }
+
+    /// Passes `queue` the buffer index, byte offset and length of each
+    /// transfer of at most `max` bytes that fits in the FIFO, for buffers of
+    /// `len` bytes. Stops at the first transfer `queue` fails to queue, which
+    /// is passed again on the next call.
+    fn submit(
+        &mut self,
+        len: usize,
+        max: usize,
+        mut queue: impl FnMut(usize, usize, usize) -> bool,
+    ) {
+        while self.submitted < self.filled && self.fifo_len < 2 {
+            let transfer = (len - self.offset).min(max);
+            if !queue(self.next(self.submitted), self.offset, transfer) {
+                break;
+            }
+
+            self.offset += transfer;
+            let ends_buffer = self.offset == len;
+            if ends_buffer {
+                self.offset = 0;
+                self.submitted += 1;
+            }
+
+            self.fifo[self.fifo_len] = ends_buffer;
+            self.fifo_len += 1;
+        }
+    }
+}
+
+// The buffers are owned exclusively through the `'static` borrow, and each
+// is only written by whoever marked it as being filled.
+unsafe impl<const N: usize> Send for AudioOutput<N> {}
+unsafe impl<const N: usize> Sync for AudioOutput<N> {}
+
+impl<const N: usize> AudioOutput<N> {
+    /// Splits `memory` into `N` buffers of `frames` stereo sample pairs,
+    /// for an AI of the given `revision`.
+    ///
+    /// The sample rate should already be set with [`Ai::set_frequency`].
+    /// `memory` is written back and invalidated in the data cache, so no
+    /// dirty line can later be written back over the uncached samples.
+    pub fn new(
+        mut ai: Ai,
+        revision: AiRevision,
+        memory: &'static mut [u8],
+        frames: usize,
+    ) -> Result<Self, AudioOutputError> {
+        if N < 2 {
+            return Err(AudioOutputError::TooFewBuffers);
+        }
+
+        let len = frames * 4;
+        if len == 0 || !len.is_multiple_of(AI_DMA_ALIGN as usize) {
+            return Err(AudioOutputError::InvalidLength);
+        }
+
+        let skip = memory.as_ptr().align_offset(AI_DMA_ALIGN as usize);
+        if skip + len * N > memory.len() {
+            return Err(AudioOutputError::OutOfMemory);
+        }
+
+        writeback_invalidate_dcache(memory.as_ptr(), memory.len());
+        let base = uncached(unsafe { memory.as_mut_ptr().add(skip) });
+        let buffers = core::array::from_fn(|index| unsafe { base.add(index * len) as *mut i16 });
+
+        ai.set_dma_enable(true);
+
+        Ok(Self {
+            revision,
+            buffers,
+            len,
+            inner: Mutex::new(RefCell::new(Inner {
+                ai,
+                callback: None,
+                filling: false,
+                ring: Ring::new(),
+                underruns: 0,
+                last_end: None,
+            })),
+        })
+    }
+
+    /// Stereo sample pairs per buffer.
+    pub fn frames(&self) -> usize {
+        self.len / 4
+    }
+
+    /// Number of underruns since the output was created.
+    pub fn underruns(&self) -> u32 {
+        critical_section::with(|cs| self.inner.borrow_ref(cs).underruns)
+    }
+
+    /// Number of buffers neither playing nor waiting to.
+    pub fn free(&self) -> usize {
+        critical_section::with(|cs| N - self.inner.borrow_ref(cs).ring.filled)
+    }
+
+    /// Sets the callback [`AudioOutput::on_interrupt`] fills free buffers
+    /// with, or removes it.
+    pub fn set_callback(&self, callback: Option<AudioCallback>) {
+        critical_section::with(|cs| self.inner.borrow_ref_mut(cs).callback = callback);
+    }
+
+    /// Fills the next free buffer with `f` and queues it.
+    ///
+    /// `f` runs outside the critical section, so the AI interrupt keeps being
+    /// serviced while it does. Returns `false` without calling `f` when every
+    /// buffer is filled.
+    pub fn fill(&self, f: impl FnOnce(&mut [i16])) -> bool {
+        let buffer = critical_section::with(|cs| {
+            let mut inner = self.inner.borrow_ref_mut(cs);
+            self.update(&mut inner);
+            let index = inner.ring.free().filter(|_| !inner.filling)?;
+            inner.filling = true;
+            Some(self.buffers[index])
+        });
+        let Some(buffer) = buffer else {
+            return false;
+        };
+
+        // Recycling buffers moves `playing` and `filled` together, so the
+        // buffer stays the next free one until it is marked filled.
+        f(unsafe { core::slice::from_raw_parts_mut(buffer, self.len / 2) });
+
+        critical_section::with(|cs| {
+            let mut inner = self.inner.borrow_ref_mut(cs);
+            inner.filling = false;
+            inner.ring.filled += 1;
+            self.submit(&mut inner);
+        });
+        true
+    }
+
+    /// Routes the AI interrupt to the CPU.
+    pub fn enable_interrupt(&self, mi: &mut Mi) {
+        unsafe {
+            core::ptr::write_volatile(
+                &mut mi.mi_intr_mask_reg,
+                MiIntrMaskReg(0).with_set_ai_mask(true),
+            );
+        }
+    }
+
+    /// Handles the AI interrupt.
+    ///
+    /// Acknowledges the interrupt, recycles finished buffers, refills them
+    /// from the callback and queues whatever is ready. The callback is left
+    /// alone while [`AudioOutput::fill`] is running.
+    pub fn on_interrupt(&self) {
+        critical_section::with(|cs| {
+            let mut inner = self.inner.borrow_ref_mut(cs);
+            inner.ai.acknowledge_interrupt();
+            self.update(&mut inner);
+
+            if let Some(callback) = inner.callback.filter(|_| !inner.filling) {
+                while let Some(index) = inner.ring.free() {
+                    let buffer = self.buffers[index];
+                    callback(unsafe { core::slice::from_raw_parts_mut(buffer, self.len / 2) });
+                    inner.ring.filled += 1;
+                }
+            }
+
+            self.submit(&mut inner);
+        });
+    }
+
+    /// Stops DMA and returns the AI.
+    pub fn release(self) -> Ai {
+        let mut ai = self.inner.into_inner().into_inner().ai;
+        ai.set_dma_enable(false);
+        ai
+    }
+
+    /// Recycles the buffers the AI has finished with.
+    fn update(&self, inner: &mut Inner<N>) {
+        let queued = inner.ai.queued();
+        if inner.ring.update(queued) {
+            inner.underruns += 1;
+        }
+    }
+
+    /// Queues ready buffers while the FIFO has room.
+    fn submit(&self, inner: &mut Inner<N>) {
+        let Inner {
+            ai, ring, last_end, ..
+        } = inner;
+        let max = self.revision.max_length() as usize;
+        ring.submit(self.len, max, |index, offset, len| {
+            let address = physical(self.buffers[index]) + offset as u32;
+            let queued = ai.queue(self.revision, dma_address(address, *last_end), len as u32);
+
+            // Buffers are aligned and sized for DMA, and the ring only queues
+            // while the FIFO has room, so this cannot fail. If it does, the
+            // transfer stays unqueued and is retried.
+            debug_assert!(queued.is_ok(), "AI DMA failed: {queued:?}");
+            if queued.is_ok() {
+                *last_end = Some(address + len as u32);
+            }
+            queued.is_ok()
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::vec::Vec;
+
+    use super::*;
+
+    /// Submits whatever fits and returns the transfers queued.
+    fn submit<const N: usize>(
+        ring: &mut Ring<N>,
+        len: usize,
+        max: usize,
+    ) -> Vec<(usize, usize, usize)> {
+        let mut transfers = Vec::new();
+        ring.submit(len, max, |index, offset, len| {
+            transfers.push((index, offset, len));
+            true
+        });
+        transfers
+    }
+
+    #[test]
+    fn rotates_buffers() {
+        let mut ring = Ring::<3>::new();
+        assert_eq!(ring.free(), Some(0));
+        ring.filled = 2;
+        assert_eq!(ring.free(), Some(2));
+
+        assert_eq!(submit(&mut ring, 64, 1024), [(0, 0, 64), (1, 0, 64)]);
+        ring.filled += 1;
+        assert_eq!(ring.free(), None);
+        assert_eq!(submit(&mut ring, 64, 1024), []);
+
+        assert!(!ring.update(1));
+        assert_eq!(ring.free(), Some(0));
+        assert_eq!(submit(&mut ring, 64, 1024), [(2, 0, 64)]);
+        ring.filled += 1;
+
+        assert!(!ring.update(1));
+        assert_eq!(submit(&mut ring, 64, 1024), [(0, 0, 64)]);
+        assert_eq!(ring.free(), Some(1));
+    }
+
+    #[test]
+    fn splits_long_buffers() {
+        let mut ring = Ring::<2>::new();
+        ring.filled = 2;
+        assert_eq!(submit(&mut ring, 160, 64), [(0, 0, 64), (0, 64, 64)]);
+
+        assert!(!ring.update(1));
+        assert_eq!(submit(&mut ring, 160, 64), [(0, 128, 32)]);
+        assert_eq!(ring.free(), None);
+
+        assert!(!ring.update(1));
+        assert_eq!(ring.free(), None);
+        assert!(ring.update(0));
+        assert_eq!(ring.free(), Some(0));
+        assert_eq!(submit(&mut ring, 160, 64), [(1, 0, 64), (1, 64, 64)]);
+    }
+
+    #[test]
+    fn retries_failed_transfers() {
+        let mut ring = Ring::<2>::new();
+        ring.filled = 1;
+        ring.submit(64, 1024, |_, _, _| false);
+        assert_eq!((ring.submitted, ring.fifo_len), (0, 0));
+        assert_eq!(submit(&mut ring, 64, 1024), [(0, 0, 64)]);
+    }
+
+    #[test]
+    fn counts_underruns() {
+        let mut ring = Ring::<2>::new();
+        assert!(!ring.update(0));
+
+        ring.filled = 1;
+        submit(&mut ring, 64, 1024);
+        assert!(ring.update(0));
+        assert_eq!(ring.filled, 0);
+        assert_eq!(ring.fifo_len, 0);
+        assert!(!ring.update(0));
+    }
+}
@@ -0,0 +1,378 @@
+//! # VADPCM
+//!
+//! VADPCM is the vector ADPCM format of N64 sound assets. Samples are coded
+//! in 9-byte frames of 16 samples: a header byte holding a scale exponent in
+//! the high nibble and a predictor index in the low nibble, then 16 signed
+//! 4-bit residuals.
+//!
+//! Each predictor of the [`Codebook`] predicts 8 samples at a time from the
+//! previous `order` samples. The predictor's `order` vectors of 8 11-bit
+//! fixed point coefficients are its responses to each previous sample, and
+//! the response to the residuals follows from the last of them.
+//!
+//! [`Decoder`] decodes on the CPU to mono samples, or to stereo frames ready
+//! for [`AudioOutput`](super::output::AudioOutput) buffers. [`Stream`] plays
+//! a whole sound through its [`Loop`].
+//!
+//! With the `std` feature, [`encoder`] encodes sounds on the host and
+//! [`aifc`] and [`wav`] read and write the files they come in.
+
+#[cfg(feature = "std")]
+pub mod aifc;
+#[cfg(feature = "std")]
+pub mod encoder;
+#[cfg(feature = "std")]
+pub mod wav;
+
+/// # Frame size
+///
+/// Bytes per VADPCM frame.
+pub const FRAME_SIZE: usize = 9;
+
+/// # Samples per frame
+pub const FRAME_SAMPLES: usize = 16;
+
+/// # Maximum predictor order
+pub const MAX_ORDER: usize = 8;
+
+/// # Maximum predictor count
+pub const MAX_PREDICTORS: usize = 16;
+
+/// # VADPCM error
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VadpcmError {
+    /// The order is zero or above [`MAX_ORDER`].
+    InvalidOrder,
+
+    /// The predictor count is zero or above [`MAX_PREDICTORS`].
+    InvalidPredictorCount,
+
+    /// The codebook does not hold `order * predictors * 8` coefficients.
+    InvalidCodebookSize,
+
+    /// A frame selects a predictor the codebook does not have.
+    InvalidPredictor,
+
+    /// The loop is empty, ends past the end of the sound, or does not start
+    /// on a frame boundary.
+    InvalidLoop,
+}
+
+/// # Codebook
+///
+/// Predictor coefficients as stored in the `VADPCMCODES` chunk of an AIFC
+/// file: for each predictor, `order` vectors of 8 coefficients.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Codebook<'a> {
+    order: usize,
+    predictors: usize,
+    coefficients: &'a [i16],
+}
+
+impl<'a> Codebook<'a> {
+    /// Checks `coefficients` against `order` and `predictors`.
+    pub fn new(
+        order: usize,
+        predictors: usize,
+        coefficients: &'a [i16],
+    ) -> Result<Self, VadpcmError> {
+        if order == 0 || order > MAX_ORDER {
+            return Err(VadpcmError::InvalidOrder);
+        }
+        if predictors == 0 || predictors > MAX_PREDICTORS {
+            return Err(VadpcmError::InvalidPredictorCount);
+        }
+        if coefficients.len() != order * predictors * 8 {
+            return Err(VadpcmError::InvalidCodebookSize);
+        }
+
+        Ok(Self {
+            order,
+            predictors,
+            coefficients,
+        })
+    }
+
+    /// Number of previous samples each prediction uses.
+    pub fn order(&self) -> usize {
+        self.order
+    }
+
+    /// Number of predictors.
+    pub fn predictors(&self) -> usize {
+        self.predictors
+    }
+
+    /// The coefficients.
+    pub fn coefficients(&self) -> &'a [i16] {
+        self.coefficients
+    }
+
+    /// Coefficient `index` of vector `vector` of `predictor`.
+    fn coefficient(&self, predictor: usize, vector: usize, index: usize) -> i32 {
+        i32::from(self.coefficients[(predictor * self.order + vector) * 8 + index])
+    }
+
+    /// Weight of `input` for output sample `sample` of `predictor`, where the
+    /// inputs are the `order` previous samples followed by the 8 residuals.
+    pub(crate) fn weight(&self, predictor: usize, sample: usize, input: usize) -> i32 {
+        let order = self.order;
+        if input < order {
+            return self.coefficient(predictor, input, sample);
+        }
+
+        // The response to a residual is the response to the previous sample,
+        // delayed by the residual's position.
+        let residual = input - order;
+        match sample.checked_sub(residual) {
+            None => 0,
+            Some(0) => 2048,
+            Some(delay) => self.coefficient(predictor, order - 1, delay - 1),
+        }
+    }
+}
+
+/// # Loop
+///
+/// A loop of a VADPCM sound, as stored in the `VADPCMLOOPS` chunk. Decoding
+/// resumes at `start` with the predictor state saved by the encoder.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Loop {
+    /// First sample of the loop.
+    pub start: u32,
+    /// Sample after the last one of the loop.
+    pub end: u32,
+    /// Times to jump back to `start` on reaching `end`, or `u32::MAX` to
+    /// loop forever.
+    pub count: u32,
+    /// Decoder state at `start`.
+    pub state: [i16; FRAME_SAMPLES],
+}
+
+/// # VADPCM decoder
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Decoder<'a> {
+    codebook: Codebook<'a>,
+    state: [i16; FRAME_SAMPLES],
+}
+
+impl<'a> Decoder<'a> {
+    /// A decoder starting from silence.
+    pub fn new(codebook: Codebook<'a>) -> Self {
+        Self {
+            codebook,
+            state: [0; FRAME_SAMPLES],
+        }
+    }
+
+    /// The codebook.
+    pub fn codebook(&self) -> &Codebook<'a> {
+        &self.codebook
+    }
+
+    /// The last frame decoded, which predicts the next one.
+    pub fn state(&self) -> &[i16; FRAME_SAMPLES] {
+        &self.state
+    }
+
+    /// Replaces the state, e.g. to go back to the start of a [`Loop`].
+    pub fn set_state(&mut self, state: [i16; FRAME_SAMPLES]) {
+        self.state = state;
+    }
+
+    /// Restarts from silence.
+    pub fn reset(&mut self) {
+        self.state = [0; FRAME_SAMPLES];
+    }
+
+    /// Decodes one frame into 16 samples.
+    pub fn decode_frame(
+        &mut self,
+        frame: &[u8; FRAME_SIZE],
+        out: &mut [i16; FRAME_SAMPLES],
+    ) -> Result<(), VadpcmError> {
+        let scale = 1i32 << (frame[0] >> 4);
+        let predictor = usize::from(frame[0] & 0xF);
+        if predictor >= self.codebook.predictors {
+            return Err(VadpcmError::InvalidPredictor);
+        }
+
+        let mut residuals = [0i32; FRAME_SAMPLES];
+        for (index, residual) in residuals.iter_mut().enumerate() {
+            let nibble = (frame[1 + index / 2] >> (4 - (index & 1) * 4)) & 0xF;
+            *residual = i32::from(((nibble << 4) as i8) >> 4) * scale;
+        }
+
+        let order = self.codebook.order;
+        for half in 0..2 {
+            let mut input = [0i32; MAX_ORDER + 8];
+            for (index, value) in input[..order].iter_mut().enumerate() {
+                *value = match half {
+                    0 => i32::from(self.state[FRAME_SAMPLES - order + index]),
+                    _ => i32::from(out[8 - order + index]),
+                };
+            }
+            input[order..order + 8].copy_from_slice(&residuals[half * 8..half * 8 + 8]);
+
+            for sample in 0..8 {
+                // Scaled residuals reach 15 bits past the samples, so the
+                // sum needs the RSP accumulator's width.
+                let sum: i64 = input[..order + 8]
+                    .iter()
+                    .enumerate()
+                    .map(|(index, &value)| {
+                        i64::from(self.codebook.weight(predictor, sample, index)) * i64::from(value)
+                    })
+                    .sum();
+
+                // Rounds toward negative infinity, like the RSP microcode.
+                out[half * 8 + sample] = saturate(sum >> 11);
+            }
+        }
+
+        self.state = *out;
+        Ok(())
+    }
+
+    /// Decodes whole frames of `input` into `out` while both last, and
+    /// returns the number of samples written.
+    pub fn decode(&mut self, input: &[u8], out: &mut [i16]) -> Result<usize, VadpcmError> {
+        let mut written = 0;
+        for (frame, out) in input
+            .chunks_exact(FRAME_SIZE)
+            .zip(out.chunks_exact_mut(FRAME_SAMPLES))
+        {
+            let frame = frame.try_into().unwrap_or_else(|_| unreachable!());
+            let out = out.try_into().unwrap_or_else(|_| unreachable!());
+            self.decode_frame(frame, out)?;
+            written += FRAME_SAMPLES;
+        }
+        Ok(written)
+    }
+
+    /// Decodes whole frames of `input` into `out` as interleaved stereo
+    /// frames with the same sample on both sides, and returns the number of
+    /// stereo frames written.
+    pub fn decode_stereo(&mut self, input: &[u8], out: &mut [i16]) -> Result<usize, VadpcmError> {
+        let mut written = 0;
+        for (frame, out) in input
+            .chunks_exact(FRAME_SIZE)
+            .zip(out.chunks_exact_mut(FRAME_SAMPLES * 2))
+        {
+            let mut samples = [0; FRAME_SAMPLES];
+            let frame = frame.try_into().unwrap_or_else(|_| unreachable!());
+            self.decode_frame(frame, &mut samples)?;
+
+            for (pair, sample) in out.chunks_exact_mut(2).zip(samples) {
+                pair[0] = sample;
+                pair[1] = sample;
+            }
+            written += FRAME_SAMPLES;
+        }
+        Ok(written)
+    }
+}
+
+fn saturate(value: i64) -> i16 {
+    value.clamp(i64::from(i16::MIN), i64::from(i16::MAX)) as i16
+}
+
+/// # VADPCM stream
+///
+/// Decodes a sound from its first sample, jumping back to the start of its
+/// [`Loop`] at the end of the loop for as many times as the loop counts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Stream<'a> {
+    decoder: Decoder<'a>,
+    frames: &'a [u8],
+    looping: Option<Loop>,
+    remaining: u32,
+    position: u32,
+    samples: [i16; FRAME_SAMPLES],
+}
+
+impl<'a> Stream<'a> {
+    /// Plays the whole `frames` of a sound, looping through `looping`.
+    ///
+    /// The loop must start on a frame boundary before its end, and end
+    /// within the sound.
+    pub fn new(
+        codebook: Codebook<'a>,
+        frames: &'a [u8],
+        looping: Option<Loop>,
+    ) -> Result<Self, VadpcmError> {
+        let length = frames.len() / FRAME_SIZE * FRAME_SAMPLES;
+        if let Some(looping) = looping {
+            if looping.start >= looping.end
+                || looping.end as usize > length
+                || !(looping.start as usize).is_multiple_of(FRAME_SAMPLES)
+            {
+                return Err(VadpcmError::InvalidLoop);
+            }
+        }
+
+        Ok(Self {
+            decoder: Decoder::new(codebook),
+            frames,
+            looping,
+            remaining: looping.map_or(0, |looping| looping.count),
+            position: 0,
+            samples: [0; FRAME_SAMPLES],
+        })
+    }
+
+    /// The next sample to play.
+    pub fn position(&self) -> u32 {
+        self.position
+    }
+
+    /// Jumps left before the loop ends, or `u32::MAX` when it never does.
+    pub fn remaining(&self) -> u32 {
+        self.remaining
+    }
+
+    /// Decodes into `out` until it is full or the sound ends, and returns the
+    /// number of samples written.
+    pub fn read(&mut self, out: &mut [i16]) -> Result<usize, VadpcmError> {
+        let length = (self.frames.len() / FRAME_SIZE * FRAME_SAMPLES) as u32;
+        let mut written = 0;
+
+        while written < out.len() {
+            let end = match self.looping {
+                Some(looping) if self.remaining > 0 => looping.end,
+                _ => length,
+            };
+
+            if self.position >= end {
+                match self.looping {
+                    Some(looping) if self.remaining > 0 => {
+                        if self.remaining != u32::MAX {
+                            self.remaining -= 1;
+                        }
+                        self.position = looping.start;
+                        self.decoder.set_state(looping.state);
+                    }
+                    _ => break,
+                }
+            }
+
+            let frame = self.position as usize / FRAME_SAMPLES;
+            let offset = self.position as usize % FRAME_SAMPLES;
+            if offset == 0 {
+                let start = frame * FRAME_SIZE;
+                let data = &self.frames[start..start + FRAME_SIZE];
+                let data = data.try_into().unwrap_or_else(|_| unreachable!());
+                self.decoder.decode_frame(data, &mut self.samples)?;
+            }
+
+            let count = (FRAME_SAMPLES - offset)
+                .min((end - self.position) as usize)
+                .min(out.len() - written);
+            out[written..written + count].copy_from_slice(&self.samples[offset..offset + count]);
+            written += count;
+            self.position += count as u32;
+        }
+
+        Ok(written)
+    }
+}
@@ -0,0 +1,375 @@
+//! # AIFF and AIFC files
+//!
+//! Reads uncompressed AIFF and AIFC files, and VADPCM AIFC files as written
+//! by the SGI tools, with their codebook and loops in `APPL` chunks. Writes
+//! uncompressed AIFF and VADPCM AIFC.
+
+use std::vec::Vec;
+
+use super::{Codebook, Loop, VadpcmError, FRAME_SAMPLES};
+
+const VERSION_STAMP: u32 = 0xA280_5140;
+const CODES_NAME: &[u8] = b"VADPCMCODES";
+const LOOPS_NAME: &[u8] = b"VADPCMLOOPS";
+const VADPCM_NAME: &[u8] = b"VADPCM ~4-1";
+
+/// # File error
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileError {
+    /// The data is not a file of the expected type, or is truncated.
+    InvalidFormat,
+
+    /// The file uses a compression or sample size that is not supported.
+    UnsupportedFormat,
+
+    /// The VADPCM codebook is invalid.
+    InvalidCodebook(VadpcmError),
+}
+
+/// # PCM sound
+///
+/// Mono signed 16-bit samples.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pcm {
+    /// Sample rate in Hz.
+    pub rate: u32,
+    pub samples: Vec<i16>,
+}
+
+/// # VADPCM sound
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VadpcmSound {
+    /// Sample rate in Hz.
+    pub rate: u32,
+    /// Number of samples, up to 15 fewer than the frames hold.
+    pub samples: u32,
+    pub order: usize,
+    pub predictors: usize,
+    /// Codebook coefficients, see [`Codebook`].
+    pub coefficients: Vec<i16>,
+    pub loops: Vec<Loop>,
+    /// 9-byte frames.
+    pub data: Vec<u8>,
+}
+
+impl VadpcmSound {
+    /// The codebook, checked against the order and predictor count.
+    pub fn codebook(&self) -> Result<Codebook<'_>, VadpcmError> {
+        Codebook::new(self.order, self.predictors, &self.coefficients)
+    }
+}
+
+/// # Audio file contents
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AudioFile {
+    Pcm(Pcm),
+    Vadpcm(VadpcmSound),
+}
+
+/// Reads an AIFF or AIFC file.
+///
+/// Multichannel PCM is mixed down to mono.
+pub fn read(data: &[u8]) -> Result<AudioFile, FileError> {
+    let mut reader = Reader(data);
+    if reader.bytes(4)? != b"FORM" {
+        return Err(FileError::InvalidFormat);
+    }
+    let size = reader.u32()? as usize;
+    let mut form = Reader(data.get(8..8 + size).ok_or(FileError::InvalidFormat)?);
+    let compressed = match form.bytes(4)? {
+        b"AIFF" => false,
+        b"AIFC" => true,
+        _ => return Err(FileError::InvalidFormat),
+    };
+
+    let mut common = None;
+    let mut sound = None;
+    let mut codebook = None;
+    let mut loops = Vec::new();
+
+    while !form.0.is_empty() {
+        let kind = form.bytes(4)?;
+        let size = form.u32()? as usize;
+        let mut chunk = Reader(form.bytes(size)?);
+        if size % 2 == 1 && !form.0.is_empty() {
+            form.bytes(1)?;
+        }
+
+        match kind {
+            b"COMM" => {
+                let channels = chunk.u16()?;
+                let frames = chunk.u32()?;
+                let sample_size = chunk.u16()?;
+                let rate = extended_to_u32(chunk.bytes(10)?.try_into().unwrap());
+                let compression = match compressed {
+                    true => chunk.bytes(4)?.try_into().unwrap(),
+                    false => *b"NONE",
+                };
+                common = Some((channels, frames, sample_size, rate, compression));
+            }
+            b"SSND" => {
+                let offset = chunk.u32()? as usize;
+                chunk.u32()?;
+                sound = Some(chunk.0.get(offset..).ok_or(FileError::InvalidFormat)?);
+            }
+            b"APPL" if chunk.bytes(4)? == b"stoc" => {
+                let name = chunk.pstring()?;
+                if name == CODES_NAME {
+                    chunk.u16()?;
+                    let order = usize::from(chunk.u16()?);
+                    let predictors = usize::from(chunk.u16()?);
+                    let coefficients = (0..order * predictors * 8)
+                        .map(|_| chunk.u16().map(|value| value as i16))
+                        .collect::<Result<Vec<_>, _>>()?;
+                    codebook = Some((order, predictors, coefficients));
+                } else if name == LOOPS_NAME {
+                    chunk.u16()?;
+                    for _ in 0..chunk.u16()? {
+                        let (start, end, count) = (chunk.u32()?, chunk.u32()?, chunk.u32()?);
+                        let mut state = [0; FRAME_SAMPLES];
+                        for value in state.iter_mut() {
+                            *value = chunk.u16()? as i16;
+                        }
+                        loops.push(Loop {
+                            start,
+                            end,
+                            count,
+                            state,
+                        });
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let (channels, frames, sample_size, rate, compression) =
+        common.ok_or(FileError::InvalidFormat)?;
+    let sound = sound.ok_or(FileError::InvalidFormat)?;
+
+    match &compression {
+        b"VAPC" => {
+            let (order, predictors, coefficients) = codebook.ok_or(FileError::InvalidFormat)?;
+            Codebook::new(order, predictors, &coefficients).map_err(FileError::InvalidCodebook)?;
+            if channels != 1 {
+                return Err(FileError::UnsupportedFormat);
+            }
+
+            let len = (frames as usize).div_ceil(FRAME_SAMPLES) * 9;
+            Ok(AudioFile::Vadpcm(VadpcmSound {
+                rate,
+                samples: frames,
+                order,
+                predictors,
+                coefficients,
+                loops,
+                data: sound.get(..len).ok_or(FileError::InvalidFormat)?.to_vec(),
+            }))
+        }
+        b"NONE" | b"twos" | b"sowt" => {
+            let little_endian = &compression == b"sowt";
+            let samples = pcm_samples(sound, channels, frames, sample_size, little_endian)?;
+            Ok(AudioFile::Pcm(Pcm { rate, samples }))
+        }
+        _ => Err(FileError::UnsupportedFormat),
+    }
+}
+
+/// Writes 16-bit mono PCM as an AIFF file.
+pub fn write_aiff(pcm: &Pcm) -> Vec<u8> {
+    let mut common = Vec::new();
+    common.extend_from_slice(&1u16.to_be_bytes());
+    common.extend_from_slice(&(pcm.samples.len() as u32).to_be_bytes());
+    common.extend_from_slice(&16u16.to_be_bytes());
+    common.extend_from_slice(&u32_to_extended(pcm.rate));
+
+    let mut sound = Vec::with_capacity(8 + pcm.samples.len() * 2);
+    sound.extend_from_slice(&[0; 8]);
+    for sample in &pcm.samples {
+        sound.extend_from_slice(&sample.to_be_bytes());
+    }
+
+    form(b"AIFF", &[(b"COMM", common), (b"SSND", sound)])
+}
+
+/// Writes a VADPCM sound as an AIFC file.
+pub fn write_aifc(sound: &VadpcmSound) -> Vec<u8> {
+    let mut version = Vec::new();
+    version.extend_from_slice(&VERSION_STAMP.to_be_bytes());
+
+    let mut common = Vec::new();
+    common.extend_from_slice(&1u16.to_be_bytes());
+    common.extend_from_slice(&sound.samples.to_be_bytes());
+    common.extend_from_slice(&16u16.to_be_bytes());
+    common.extend_from_slice(&u32_to_extended(sound.rate));
+    common.extend_from_slice(b"VAPC");
+    push_pstring(&mut common, VADPCM_NAME);
+
+    let mut codes = application(CODES_NAME);
+    codes.extend_from_slice(&(sound.order as u16).to_be_bytes());
+    codes.extend_from_slice(&(sound.predictors as u16).to_be_bytes());
+    for coefficient in &sound.coefficients {
+        codes.extend_from_slice(&coefficient.to_be_bytes());
+    }
+
+    let mut chunks = std::vec![(b"FVER", version), (b"COMM", common), (b"APPL", codes)];
+
+    if !sound.loops.is_empty() {
+        let mut loops = application(LOOPS_NAME);
+        loops.extend_from_slice(&(sound.loops.len() as u16).to_be_bytes());
+        for entry in &sound.loops {
+            loops.extend_from_slice(&entry.start.to_be_bytes());
+            loops.extend_from_slice(&entry.end.to_be_bytes());
+            loops.extend_from_slice(&entry.count.to_be_bytes());
+            for value in entry.state {
+                loops.extend_from_slice(&value.to_be_bytes());
+            }
+        }
+        chunks.push((b"APPL", loops));
+    }
+
+    let mut data = Vec::with_capacity(8 + sound.data.len());
+    data.extend_from_slice(&[0; 8]);
+    data.extend_from_slice(&sound.data);
+    chunks.push((b"SSND", data));
+
+    form(b"AIFC", &chunks)
+}
+
+/// Converts an 80-bit IEEE extended float to the nearest integer.
+pub fn extended_to_u32(bytes: [u8; 10]) -> u32 {
+    let exponent = i32::from(u16::from_be_bytes([bytes[0], bytes[1]]) & 0x7FFF);
+    let mantissa = u64::from_be_bytes(bytes[2..10].try_into().unwrap());
+    if bytes[0] & 0x80 != 0 || mantissa == 0 {
+        return 0;
+    }
+
+    // The integer part holds `exponent - 16383 + 1` bits of the mantissa.
+    match 16383 + 63 - exponent {
+        shift @ 1..=63 => (((mantissa >> (shift - 1)) + 1) >> 1) as u32,
+        shift if shift <= 0 => u32::MAX,
+        _ => 0,
+    }
+}
+
+/// Converts an integer to an 80-bit IEEE extended float.
+pub fn u32_to_extended(value: u32) -> [u8; 10] {
+    let mut bytes = [0; 10];
+    if value == 0 {
+        return bytes;
+    }
+
+    let shift = value.leading_zeros();
+    let exponent = (16383 + 31 - shift) as u16;
+    bytes[0..2].copy_from_slice(&exponent.to_be_bytes());
+    bytes[2..10].copy_from_slice(&((u64::from(value) << (32 + shift)).to_be_bytes()));
+    bytes
+}
+
+fn pcm_samples(
+    data: &[u8],
+    channels: u16,
+    frames: u32,
+    sample_size: u16,
+    little_endian: bool,
+) -> Result<Vec<i16>, FileError> {
+    let bytes = match sample_size {
+        8 => 1,
+        16 => 2,
+        _ => return Err(FileError::UnsupportedFormat),
+    };
+    let channels = usize::from(channels);
+    if channels == 0 {
+        return Err(FileError::InvalidFormat);
+    }
+
+    let len = frames as usize * channels * bytes;
+    let data = data.get(..len).ok_or(FileError::InvalidFormat)?;
+
+    Ok(data
+        .chunks_exact(channels * bytes)
+        .map(|frame| {
+            let sum: i32 = frame
+                .chunks_exact(bytes)
+                .map(|sample| match (sample, little_endian) {
+                    (&[byte], _) => i32::from(byte as i8) << 8,
+                    (&[low, high], true) | (&[high, low], false) => {
+                        i32::from(i16::from_be_bytes([high, low]))
+                    }
+                    _ => unreachable!(),
+                })
+                .sum();
+            (sum / channels as i32) as i16
+        })
+        .collect())
+}
+
+/// Starts an `APPL` chunk with an application specific name.
+fn application(name: &[u8]) -> Vec<u8> {
+    let mut chunk = Vec::new();
+    chunk.extend_from_slice(b"stoc");
+    push_pstring(&mut chunk, name);
+    chunk.extend_from_slice(&1u16.to_be_bytes());
+    chunk
+}
+
+/// Appends a Pascal string, padded to an even length.
+fn push_pstring(buffer: &mut Vec<u8>, string: &[u8]) {
+    buffer.push(string.len() as u8);
+    buffer.extend_from_slice(string);
+    if string.len().is_multiple_of(2) {
+        buffer.push(0);
+    }
+}
+
+fn form(kind: &[u8; 4], chunks: &[(&[u8; 4], Vec<u8>)]) -> Vec<u8> {
+    let mut body = Vec::new();
+    body.extend_from_slice(kind);
+    for (name, data) in chunks {
+        body.extend_from_slice(*name);
+        body.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        body.extend_from_slice(data);
+        if data.len() % 2 == 1 {
+            body.push(0);
+        }
+    }
+
+    let mut file = Vec::with_capacity(8 + body.len());
+    file.extend_from_slice(b"FORM");
+    file.extend_from_slice(&(body.len() as u32).to_be_bytes());
+    file.extend_from_slice(&body);
+    file
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], FileError> {
+        if self.0.len() < len {
+            return Err(FileError::InvalidFormat);
+        }
+        let (bytes, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Ok(bytes)
+    }
+
+    fn u16(&mut self) -> Result<u16, FileError> {
+        self.bytes(2)
+            .map(|bytes| u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn u32(&mut self) -> Result<u32, FileError> {
+        self.bytes(4)
+            .map(|bytes| u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    /// Reads a Pascal string, padded to an even length.
+    fn pstring(&mut self) -> Result<&'a [u8], FileError> {
+        let len = usize::from(self.bytes(1)?[0]);
+        let string = self.bytes(len)?;
+        if len.is_multiple_of(2) {
+            self.bytes(1)?;
+        }
+        Ok(string)
+    }
+}
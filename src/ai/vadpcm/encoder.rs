@@ -0,0 +1,401 @@
+//! # VADPCM encoder
+//!
+//! Replaces the SGI `tabledesign` and `vadpcm_enc` tools.
+//!
+//! [`design_codebook`] computes the autocorrelation of every frame and
+//! clusters the frames by which linear predictor fits them best, refining
+//! each predictor to the optimum for its frames with Levinson-Durbin
+//! recursion. [`encode`] then picks, for every frame, the predictor and
+//! scale that decode closest to the input, simulating the decoder exactly.
+
+use std::vec::Vec;
+
+use super::{
+    aifc::{Pcm, VadpcmSound},
+    Codebook, Decoder, Loop, VadpcmError, FRAME_SAMPLES, FRAME_SIZE, MAX_ORDER, MAX_PREDICTORS,
+};
+
+/// Largest scale exponent that still fits the header nibble and leaves
+/// residuals within 16 bits.
+const MAX_SCALE: u8 = 12;
+
+/// # Encoder options
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EncoderOptions {
+    order: usize,
+    predictors: usize,
+    iterations: usize,
+    looping: Option<(u32, u32, u32)>,
+}
+
+impl EncoderOptions {
+    /// The `tabledesign` defaults: order 2, 4 predictors.
+    pub const fn new() -> Self {
+        Self {
+            order: 2,
+            predictors: 4,
+            iterations: 16,
+            looping: None,
+        }
+    }
+
+    /// Sets the number of previous samples each prediction uses.
+    pub const fn order(mut self, order: usize) -> Self {
+        self.order = order;
+        self
+    }
+
+    /// Sets the number of predictors in the codebook.
+    pub const fn predictors(mut self, predictors: usize) -> Self {
+        self.predictors = predictors;
+        self
+    }
+
+    /// Sets the number of refinement passes after each predictor is added.
+    pub const fn iterations(mut self, iterations: usize) -> Self {
+        self.iterations = iterations;
+        self
+    }
+
+    /// Loops from sample `start` up to `end`, `count` times or forever with
+    /// `u32::MAX`.
+    ///
+    /// `start` must be a multiple of [`FRAME_SAMPLES`], as the loop state is
+    /// the decoder state at the start of a frame.
+    pub const fn looping(mut self, start: u32, end: u32, count: u32) -> Self {
+        self.looping = Some((start, end, count));
+        self
+    }
+}
+
+impl Default for EncoderOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Designs a codebook of `predictors` predictors of order `order` for
+/// `samples`.
+pub fn design_codebook(
+    samples: &[i16],
+    order: usize,
+    predictors: usize,
+    iterations: usize,
+) -> Result<Vec<i16>, VadpcmError> {
+    if order == 0 || order > MAX_ORDER {
+        return Err(VadpcmError::InvalidOrder);
+    }
+    if predictors == 0 || predictors > MAX_PREDICTORS {
+        return Err(VadpcmError::InvalidPredictorCount);
+    }
+
+    let frames: Vec<Vec<f64>> = (0..samples.len().div_ceil(FRAME_SAMPLES))
+        .map(|frame| autocorrelation(samples, frame * FRAME_SAMPLES, order))
+        .filter(|r| r[0] > 0.0)
+        .collect();
+
+    let total = frames
+        .iter()
+        .fold(std::vec![0.0; order + 1], |sum, r| add(&sum, r));
+    let mut centroids = std::vec![levinson(&total, order)];
+    let mut assignment = std::vec![0; frames.len()];
+
+    while centroids.len() < predictors {
+        // Split the predictor that fits its frames worst.
+        let mut distortion = std::vec![0.0; centroids.len()];
+        for (r, &cluster) in frames.iter().zip(&assignment) {
+            distortion[cluster] += prediction_error(&centroids[cluster], r);
+        }
+        let worst = (0..centroids.len())
+            .max_by(|&a, &b| distortion[a].total_cmp(&distortion[b]))
+            .unwrap_or(0);
+
+        let original = centroids[worst].clone();
+        centroids[worst] = original.iter().map(|a| a * 1.01 + 0.01).collect();
+        centroids.push(original.iter().map(|a| a * 0.99 - 0.01).collect());
+
+        for _ in 0..iterations.max(1) {
+            for (r, cluster) in frames.iter().zip(assignment.iter_mut()) {
+                *cluster = (0..centroids.len())
+                    .min_by(|&a, &b| {
+                        prediction_error(&centroids[a], r)
+                            .total_cmp(&prediction_error(&centroids[b], r))
+                    })
+                    .unwrap_or(0);
+            }
+
+            for (index, centroid) in centroids.iter_mut().enumerate() {
+                let members = frames
+                    .iter()
+                    .zip(&assignment)
+                    .filter(|(_, &cluster)| cluster == index);
+                let sum = members.fold(std::vec![0.0; order + 1], |sum, (r, _)| add(&sum, r));
+                if sum[0] > 0.0 {
+                    *centroid = levinson(&sum, order);
+                }
+            }
+        }
+    }
+
+    Ok(centroids
+        .iter()
+        .flat_map(|predictor| vectors(predictor))
+        .collect())
+}
+
+/// Encodes `pcm` to VADPCM with a codebook designed for it.
+pub fn encode(pcm: &Pcm, options: &EncoderOptions) -> Result<VadpcmSound, VadpcmError> {
+    let coefficients = design_codebook(
+        &pcm.samples,
+        options.order,
+        options.predictors,
+        options.iterations,
+    )?;
+    let codebook = Codebook::new(options.order, options.predictors, &coefficients)?;
+
+    let loop_points = match options.looping {
+        Some((start, end, count))
+            if start < end
+                && end as usize <= pcm.samples.len()
+                && (start as usize).is_multiple_of(FRAME_SAMPLES) =>
+        {
+            Some((start, end, count))
+        }
+        Some(_) => return Err(VadpcmError::InvalidLoop),
+        None => None,
+    };
+
+    let mut decoder = Decoder::new(codebook);
+    let mut loops = Vec::new();
+    let frames = pcm.samples.len().div_ceil(FRAME_SAMPLES);
+    let mut data = Vec::with_capacity(frames * FRAME_SIZE);
+
+    for frame in 0..frames {
+        // Playback jumps to the frame starting the loop with the state left
+        // by the frame before it.
+        if let Some((start, end, count)) = loop_points {
+            if frame * FRAME_SAMPLES == start as usize {
+                loops.push(Loop {
+                    start,
+                    end,
+                    count,
+                    state: *decoder.state(),
+                });
+            }
+        }
+
+        let mut target = [0i32; FRAME_SAMPLES];
+        for (index, value) in target.iter_mut().enumerate() {
+            let sample = pcm.samples.get(frame * FRAME_SAMPLES + index);
+            *value = sample.copied().map_or(0, i32::from);
+        }
+
+        let encoded = encode_frame(&decoder, &target);
+        let mut out = [0; FRAME_SAMPLES];
+        decoder.decode_frame(&encoded, &mut out)?;
+        data.extend_from_slice(&encoded);
+    }
+
+    Ok(VadpcmSound {
+        rate: pcm.rate,
+        samples: pcm.samples.len() as u32,
+        order: options.order,
+        predictors: options.predictors,
+        coefficients,
+        loops,
+        data,
+    })
+}
+
+/// Picks the predictor, scale and residuals that decode closest to
+/// `target` from the decoder's state.
+fn encode_frame(decoder: &Decoder, target: &[i32; FRAME_SAMPLES]) -> [u8; FRAME_SIZE] {
+    let codebook = decoder.codebook();
+    let mut best = ([0u8; FRAME_SIZE], i64::MAX);
+
+    for predictor in 0..codebook.predictors() {
+        for scale in 0..=MAX_SCALE {
+            let (frame, error) = try_frame(codebook, decoder.state(), target, predictor, scale);
+            if error < best.1 {
+                best = (frame, error);
+            }
+        }
+    }
+
+    best.0
+}
+
+/// Quantizes `target` with one predictor and scale, returning the frame and
+/// its squared error.
+fn try_frame(
+    codebook: &Codebook,
+    state: &[i16; FRAME_SAMPLES],
+    target: &[i32; FRAME_SAMPLES],
+    predictor: usize,
+    scale: u8,
+) -> ([u8; FRAME_SIZE], i64) {
+    let order = codebook.order();
+    let step = 1i32 << scale;
+    let mut residuals = [0i32; FRAME_SAMPLES];
+    let mut out = [0i32; FRAME_SAMPLES];
+    let mut error = 0i64;
+
+    for half in 0..2 {
+        let previous: Vec<i32> = match half {
+            0 => state[FRAME_SAMPLES - order..]
+                .iter()
+                .map(|&value| i32::from(value))
+                .collect(),
+            _ => out[8 - order..8].to_vec(),
+        };
+
+        for sample in 0..8 {
+            // Accumulated in 64 bits like the decoder, as full scale samples
+            // and residuals overflow 32.
+            let mut sum: i64 = previous
+                .iter()
+                .enumerate()
+                .map(|(index, &value)| {
+                    i64::from(codebook.weight(predictor, sample, index)) * i64::from(value)
+                })
+                .sum();
+            for residual in 0..sample {
+                let weight = codebook.weight(predictor, sample, order + residual);
+                sum += i64::from(weight) * i64::from(residuals[half * 8 + residual] * step);
+            }
+
+            // The residual's own weight is exactly one, so it adds to the
+            // rounded prediction unchanged. At most 16 products of 16-bit
+            // weights and 16-bit values, the prediction fits in 32 bits.
+            let prediction = (sum >> 11) as i32;
+            let index = half * 8 + sample;
+            let residual = divide_rounded(target[index] - prediction, step).clamp(-8, 7);
+            let value = (prediction + residual * step).clamp(-0x8000, 0x7FFF);
+
+            residuals[index] = residual;
+            out[index] = value;
+            error += i64::from(target[index] - value).pow(2);
+        }
+    }
+
+    let mut frame = [0u8; FRAME_SIZE];
+    frame[0] = (scale << 4) | predictor as u8;
+    for (index, pair) in residuals.chunks_exact(2).enumerate() {
+        frame[1 + index] = (((pair[0] & 0xF) << 4) | (pair[1] & 0xF)) as u8;
+    }
+    (frame, error)
+}
+
+fn divide_rounded(value: i32, divisor: i32) -> i32 {
+    match value >= 0 {
+        true => (value + divisor / 2) / divisor,
+        false => -((-value + divisor / 2) / divisor),
+    }
+}
+
+/// Autocorrelation at lags `0..=order` of the frame starting at `start`,
+/// reaching back into the previous frame.
+fn autocorrelation(samples: &[i16], start: usize, order: usize) -> Vec<f64> {
+    let sample = |index: isize| match index {
+        index if index < 0 => 0.0,
+        index => samples
+            .get(index as usize)
+            .map_or(0.0, |&value| f64::from(value)),
+    };
+
+    (0..=order)
+        .map(|lag| {
+            (start..start + FRAME_SAMPLES)
+                .map(|index| {
+                    let index = index as isize;
+                    sample(index) * sample(index - lag as isize)
+                })
+                .sum()
+        })
+        .collect()
+}
+
+fn add(a: &[f64], b: &[f64]) -> Vec<f64> {
+    a.iter().zip(b).map(|(a, b)| a + b).collect()
+}
+
+/// Predictor coefficients minimizing the error for autocorrelation `r`,
+/// such that `x[n]` is predicted by `sum(a[i] * x[n - 1 - i])`.
+fn levinson(r: &[f64], order: usize) -> Vec<f64> {
+    let mut a = std::vec![0.0; order];
+    let mut error = r[0];
+
+    for i in 0..order {
+        if error <= 0.0 {
+            break;
+        }
+
+        let correlation = r[i + 1] - (0..i).map(|j| a[j] * r[i - j]).sum::<f64>();
+        // Keep the filter stable when the frames are nearly degenerate.
+        let reflection = (correlation / error).clamp(-0.9999, 0.9999);
+
+        let previous = a.clone();
+        a[i] = reflection;
+        for j in 0..i {
+            a[j] = previous[j] - reflection * previous[i - 1 - j];
+        }
+        error *= 1.0 - reflection * reflection;
+    }
+
+    a
+}
+
+/// Prediction error energy of predictor `a` on a frame with
+/// autocorrelation `r`.
+fn prediction_error(a: &[f64], r: &[f64]) -> f64 {
+    let mut error = r[0];
+    for i in 0..a.len() {
+        error -= 2.0 * a[i] * r[i + 1];
+        for j in 0..a.len() {
+            error += a[i] * a[j] * r[i.abs_diff(j)];
+        }
+    }
+    error
+}
+
+/// Codebook vectors of predictor `a`: the responses of the next 8 samples
+/// to each previous sample, oldest first, in 11-bit fixed point.
+fn vectors(a: &[f64]) -> Vec<i16> {
+    let order = a.len();
+    let mut coefficients = Vec::with_capacity(order * 8);
+
+    for input in 0..order {
+        let mut history = std::vec![0.0; order];
+        history[input] = 1.0;
+
+        for _ in 0..8 {
+            let value: f64 = (0..order).map(|i| a[i] * history[order - 1 - i]).sum();
+            history.remove(0);
+            history.push(value);
+
+            let fixed = (value * 2048.0).round().clamp(-32768.0, 32767.0);
+            coefficients.push(fixed as i16);
+        }
+    }
+
+    coefficients
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quantizes_full_scale_without_overflow() {
+        // Each previous sample contributes 2^30 before the shift.
+        let coefficients = [i16::MIN; 16];
+        let codebook = Codebook::new(2, 1, &coefficients).unwrap();
+        let state = [i16::MIN; FRAME_SAMPLES];
+        let target = [i32::from(i16::MAX); FRAME_SAMPLES];
+
+        for scale in 0..=MAX_SCALE {
+            let (frame, _) = try_frame(&codebook, &state, &target, 0, scale);
+            assert_eq!(frame[0], scale << 4);
+        }
+    }
+}
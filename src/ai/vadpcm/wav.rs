@@ -0,0 +1,84 @@
+//! # WAV files
+//!
+//! Reads 8-bit and 16-bit PCM WAV files, including the
+//! `WAVE_FORMAT_EXTENSIBLE` ones many editors write.
+
+use std::vec::Vec;
+
+use super::aifc::{FileError, Pcm};
+
+/// `WAVE_FORMAT_PCM`
+const FORMAT_PCM: u16 = 1;
+
+/// `WAVE_FORMAT_EXTENSIBLE`, whose subformat GUID holds the real format.
+const FORMAT_EXTENSIBLE: u16 = 0xFFFE;
+
+/// `KSDATAFORMAT_SUBTYPE_PCM`, as stored in the file.
+const SUBTYPE_PCM: [u8; 16] = [
+    0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x10, 0x00, 0x80, 0x00, 0x00, 0xAA, 0x00, 0x38, 0x9B, 0x71,
+];
+
+/// Reads a PCM WAV file.
+///
+/// Multichannel files are mixed down to mono.
+pub fn read(data: &[u8]) -> Result<Pcm, FileError> {
+    if data.get(0..4) != Some(b"RIFF") || data.get(8..12) != Some(b"WAVE") {
+        return Err(FileError::InvalidFormat);
+    }
+
+    let mut format = None;
+    let mut sound = None;
+    let mut chunks = data.get(12..).ok_or(FileError::InvalidFormat)?;
+
+    while chunks.len() >= 8 {
+        let kind = &chunks[0..4];
+        let size = u32::from_le_bytes(chunks[4..8].try_into().unwrap()) as usize;
+        let body = chunks.get(8..8 + size).ok_or(FileError::InvalidFormat)?;
+        chunks = chunks.get(8 + size + size % 2..).unwrap_or(&[]);
+
+        match kind {
+            b"fmt " if body.len() >= 16 => {
+                let field = |offset: usize| u16::from_le_bytes([body[offset], body[offset + 1]]);
+                let rate = u32::from_le_bytes(body[4..8].try_into().unwrap());
+                let tag = match field(0) {
+                    FORMAT_EXTENSIBLE if body.get(24..40) == Some(&SUBTYPE_PCM) => FORMAT_PCM,
+                    tag => tag,
+                };
+                format = Some((tag, field(2), rate, field(14)));
+            }
+            b"data" => sound = Some(body),
+            _ => {}
+        }
+    }
+
+    let (tag, channels, rate, bits) = format.ok_or(FileError::InvalidFormat)?;
+    let sound = sound.ok_or(FileError::InvalidFormat)?;
+    let channels = usize::from(channels);
+    if tag != FORMAT_PCM || channels == 0 {
+        return Err(FileError::UnsupportedFormat);
+    }
+
+    let bytes = match bits {
+        8 => 1,
+        16 => 2,
+        _ => return Err(FileError::UnsupportedFormat),
+    };
+
+    let samples: Vec<i16> = sound
+        .chunks_exact(channels * bytes)
+        .map(|frame| {
+            let sum: i32 = frame
+                .chunks_exact(bytes)
+                .map(|sample| match *sample {
+                    // 8-bit samples are unsigned.
+                    [byte] => (i32::from(byte) - 0x80) << 8,
+                    [low, high] => i32::from(i16::from_le_bytes([low, high])),
+                    _ => unreachable!(),
+                })
+                .sum();
+            (sum / channels as i32) as i16
+        })
+        .collect();
+
+    Ok(Pcm { rate, samples })
+}
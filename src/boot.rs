@@ -0,0 +1,144 @@
+//! # Boot environment
+//!
+//! IPL3 leaves a block of system information at `0x80000300` before jumping
+//! to the game, in the layout libultra exposes as `osTvType`, `osRomType`,
+//! `osRomBase`, `osResetType`, `osCicId`, `osVersion`, `osMemSize` and
+//! `osAppNMIBuffer`.
+
+use crate::enums;
+
+/// # Boot environment address
+pub const BOOT_INFO_ADDR: u32 = 0x8000_0300;
+
+/// # Boot environment size
+///
+/// Eight words followed by the 64-byte application NMI buffer.
+pub const BOOT_INFO_SIZE: usize = 0x5C;
+
+enums! [
+    /// # TV type
+    u32 => TvType {
+        0 => Pal,
+        1 => Ntsc,
+        2 => Mpal,
+    },
+
+    /// # ROM type
+    u32 => RomType {
+        0 => Cartridge,
+        1 => DiskDrive,
+    },
+
+    /// # Reset type
+    u32 => ResetType {
+        0 => Cold,
+        1 => Nmi,
+    },
+];
+
+/// # CIC variant
+///
+/// Identified from the seed IPL3 stores in `osCicId`. The 6101, 7101 and
+/// 7102 share the 6102's seed, and each NTSC chip shares its seed with the
+/// matching PAL 71xx chip.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cic {
+    Cic6102,
+    Cic6103,
+    Cic6105,
+    Cic6106,
+    Cic8303,
+    Unknown(u32),
+}
+
+impl From<u32> for Cic {
+    fn from(value: u32) -> Self {
+        match value & 0xFF {
+            0x3F => Cic::Cic6102,
+            0x78 => Cic::Cic6103,
+            0x91 => Cic::Cic6105,
+            0x85 => Cic::Cic6106,
+            0xDD => Cic::Cic8303,
+            _ => Cic::Unknown(value),
+        }
+    }
+}
+
+/// # Boot environment
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BootInfo {
+    pub tv_type: TvType,
+    pub rom_type: RomType,
+    pub rom_base: u32,
+    pub reset_type: ResetType,
+    pub cic: Cic,
+    pub version: u32,
+    pub memory_size: u32,
+    pub app_nmi_buffer: [u8; 64],
+}
+
+impl BootInfo {
+    /// Reads the block IPL3 left in RDRAM.
+    ///
+    /// Returns `None` if the block does not hold valid values, e.g. when the
+    /// program was not booted through IPL3.
+    pub fn read() -> Option<Self> {
+        let mut raw = [0; BOOT_INFO_SIZE];
+        for (offset, byte) in raw.iter_mut().enumerate() {
+            *byte = unsafe { core::ptr::read_volatile((BOOT_INFO_ADDR as *const u8).add(offset)) };
+        }
+        Self::parse(&raw)
+    }
+
+    /// Parses the big-endian boot environment block.
+    pub fn parse(raw: &[u8; BOOT_INFO_SIZE]) -> Option<Self> {
+        let word = |index: usize| {
+            let offset = index * 4;
+            u32::from_be_bytes([
+                raw[offset],
+                raw[offset + 1],
+                raw[offset + 2],
+                raw[offset + 3],
+            ])
+        };
+
+        let tv_type = match word(0) {
+            value @ 0..=2 => TvType::from(value),
+            _ => return None,
+        };
+
+        let rom_type = match word(1) {
+            value @ 0..=1 => RomType::from(value),
+            _ => return None,
+        };
+
+        let reset_type = match word(3) {
+            value @ 0..=1 => ResetType::from(value),
+            _ => return None,
+        };
+
+        let memory_size = match word(6) {
+            value @ 0x0010_0000..=0x0080_0000 if value.is_multiple_of(0x0010_0000) => value,
+            _ => return None,
+        };
+
+        let mut app_nmi_buffer = [0; 64];
+        app_nmi_buffer.copy_from_slice(&raw[0x1C..]);
+
+        Some(Self {
+            tv_type,
+            rom_type,
+            rom_base: word(2),
+            reset_type,
+            cic: Cic::from(word(4)),
+            version: word(5),
+            memory_size,
+            app_nmi_buffer,
+        })
+    }
+
+    /// Whether an Expansion Pak is installed.
+    pub fn has_expansion_pak(&self) -> bool {
+        self.memory_size > 0x0040_0000
+    }
+}
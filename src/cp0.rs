@@ -0,0 +1,317 @@
+//! # Coprocessor 0 (CP0)
+//!
+//! Raw accessors for the VR4300 system control coprocessor. These are only
+//! functional when compiled for the VR4300; on any other target they panic,
+//! which keeps the register encodings built on top of them host-testable.
+
+use proc_bitfield::bitfield;
+
+use crate::fields;
+
+bitfield! {
+    /// # Status register
+    #[derive(Clone, Copy, PartialEq, Eq)]
+    pub struct Status(pub u32): Debug {
+        pub raw: u32 @ ..,
+        pub interrupt_enable: bool @ 0,
+        pub exception_level: bool @ 1,
+        pub error_level: bool @ 2,
+        pub interrupt_mask: u8 [InterruptMask] @ 8..16,
+        pub bootstrap_vectors: bool @ 22,
+        pub fpu_register_mode: bool @ 26,
+        pub cp0_usable: bool @ 28,
+        pub cp1_usable: bool @ 29,
+    }
+}
+
+bitfield! {
+    /// # Cause register
+    #[derive(Clone, Copy, PartialEq, Eq)]
+    pub struct Cause(pub u32): Debug {
+        pub raw: u32 @ ..,
+        pub exc_code: u8 [read_only, get ExcCode] @ 2..7,
+        pub interrupt_pending: u8 [InterruptMask] @ 8..16,
+        pub coprocessor_error: u8 [read_only, get CoprocessorIndex] @ 28..30,
+        pub branch_delay: bool [read_only] @ 31,
+    }
+}
+
+fields! [
+    /// # Coprocessor index
+    ux::u2 => CoprocessorIndex,
+
+    /// # Interrupt mask
+    u8 => InterruptMask,
+];
+
+/// # Exception code
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExcCode {
+    Interrupt,
+    TlbModification,
+    TlbLoadMiss,
+    TlbStoreMiss,
+    AddressErrorLoad,
+    AddressErrorStore,
+    InstructionBusError,
+    DataBusError,
+    Syscall,
+    Breakpoint,
+    ReservedInstruction,
+    CoprocessorUnusable,
+    ArithmeticOverflow,
+    Trap,
+    FloatingPoint,
+    Watch,
+    Reserved(u8),
+}
+
+impl From<u8> for ExcCode {
+    fn from(value: u8) -> Self {
+        match value {
+            0 => ExcCode::Interrupt,
+            1 => ExcCode::TlbModification,
+            2 => ExcCode::TlbLoadMiss,
+            3 => ExcCode::TlbStoreMiss,
+            4 => ExcCode::AddressErrorLoad,
+            5 => ExcCode::AddressErrorStore,
+            6 => ExcCode::InstructionBusError,
+            7 => ExcCode::DataBusError,
+            8 => ExcCode::Syscall,
+            9 => ExcCode::Breakpoint,
+            10 => ExcCode::ReservedInstruction,
+            11 => ExcCode::CoprocessorUnusable,
+            12 => ExcCode::ArithmeticOverflow,
+            13 => ExcCode::Trap,
+            15 => ExcCode::FloatingPoint,
+            23 => ExcCode::Watch,
+            value => ExcCode::Reserved(value),
+        }
+    }
+}
+
+impl ExcCode {
+    /// Human-readable description.
+    pub fn description(self) -> &'static str {
+        match self {
+            ExcCode::Interrupt => "Interrupt",
+            ExcCode::TlbModification => "TLB modification",
+            ExcCode::TlbLoadMiss => "TLB miss (load/fetch)",
+            ExcCode::TlbStoreMiss => "TLB miss (store)",
+            ExcCode::AddressErrorLoad => "Address error (load/fetch)",
+            ExcCode::AddressErrorStore => "Address error (store)",
+            ExcCode::InstructionBusError => "Bus error (fetch)",
+            ExcCode::DataBusError => "Bus error (data)",
+            ExcCode::Syscall => "Syscall",
+            ExcCode::Breakpoint => "Breakpoint",
+            ExcCode::ReservedInstruction => "Reserved instruction",
+            ExcCode::CoprocessorUnusable => "Coprocessor unusable",
+            ExcCode::ArithmeticOverflow => "Arithmetic overflow",
+            ExcCode::Trap => "Trap",
+            ExcCode::FloatingPoint => "Floating point",
+            ExcCode::Watch => "Watch",
+            ExcCode::Reserved(_) => "Reserved",
+        }
+    }
+}
+
+macro_rules! cp0_registers {
+    [$($(#[$($attrss:tt)*])* $index:literal => $read:ident, $write:ident,)*] => {
+        $(
+            $(#[$($attrss)*])*
+            #[inline(always)]
+            pub fn $read() -> u32 {
+                mfc0!($index)
+            }
+
+            $(#[$($attrss)*])*
+            ///
+            /// # Safety
+            ///
+            /// Writing CP0 state can remap memory, mask interrupts or change
+            /// the processor mode out from under the running program.
+            #[inline(always)]
+            pub unsafe fn $write(value: u32) {
+                mtc0!($index, value);
+            }
+        )*
+    };
+}
+
+#[cfg(target_arch = "mips")]
+macro_rules! mfc0 {
+    ($index:literal) => {{
+        let value: u32;
+        unsafe {
+            core::arch::asm!(
+                ".set noat",
+                concat!("mfc0 {0}, $", stringify!($index)),
+                "nop",
+                ".set at",
+                out(reg) value,
+                options(nomem, nostack),
+            )
+        };
+        value
+    }};
+}
+
+#[cfg(not(target_arch = "mips"))]
+macro_rules! mfc0 {
+    ($index:literal) => {
+        unavailable()
+    };
+}
+
+#[cfg(target_arch = "mips")]
+macro_rules! mtc0 {
+    ($index:literal, $value:ident) => {
+        core::arch::asm!(
+            ".set noat",
+            concat!("mtc0 {0}, $", stringify!($index)),
+            "nop",
+            "nop",
+            ".set at",
+            in(reg) $value,
+            options(nostack),
+        )
+    };
+}
+
+#[cfg(not(target_arch = "mips"))]
+macro_rules! mtc0 {
+    ($index:literal, $value:ident) => {{
+        let _ = $value;
+        unavailable()
+    }};
+}
+
+#[cfg(target_arch = "mips")]
+macro_rules! tlb_instruction {
+    ($instruction:literal) => {
+        core::arch::asm!($instruction, "nop", "nop", "nop", "nop", options(nostack))
+    };
+}
+
+#[cfg(not(target_arch = "mips"))]
+macro_rules! tlb_instruction {
+    ($instruction:literal) => {
+        unavailable()
+    };
+}
+
+cp0_registers! [
+    /// Index
+    0 => index, set_index,
+
+    /// Random
+    1 => random, set_random,
+
+    /// Entry low (even page)
+    2 => entry_lo0, set_entry_lo0,
+
+    /// Entry low (odd page)
+    3 => entry_lo1, set_entry_lo1,
+
+    /// Page mask
+    5 => page_mask, set_page_mask,
+
+    /// Wired
+    6 => wired, set_wired,
+
+    /// Bad virtual address
+    8 => bad_vaddr, set_bad_vaddr,
+
+    /// Count
+    9 => count, set_count,
+
+    /// Entry high
+    10 => entry_hi, set_entry_hi,
+
+    /// Compare
+    11 => compare, set_compare,
+
+    /// Status
+    12 => status, set_status,
+
+    /// Cause
+    13 => cause, set_cause,
+
+    /// Exception program counter
+    14 => epc, set_epc,
+];
+
+/// Reads the indexed TLB entry into EntryHi, EntryLo0, EntryLo1 and PageMask.
+///
+/// # Safety
+///
+/// Overwrites EntryHi, including the current ASID.
+#[inline(always)]
+pub unsafe fn tlbr() {
+    tlb_instruction!("tlbr")
+}
+
+/// Writes the TLB entry selected by Index.
+///
+/// # Safety
+///
+/// Changes the virtual memory map.
+#[inline(always)]
+pub unsafe fn tlbwi() {
+    tlb_instruction!("tlbwi")
+}
+
+/// Writes the TLB entry selected by Random.
+///
+/// # Safety
+///
+/// Changes the virtual memory map.
+#[inline(always)]
+pub unsafe fn tlbwr() {
+    tlb_instruction!("tlbwr")
+}
+
+/// Probes the TLB for an entry matching EntryHi, updating Index.
+///
+/// # Safety
+///
+/// Overwrites Index.
+#[inline(always)]
+pub unsafe fn tlbp() {
+    tlb_instruction!("tlbp")
+}
+
+#[cfg(not(target_arch = "mips"))]
+#[cold]
+fn unavailable() -> ! {
+    panic!("CP0 is only accessible on the VR4300")
+}
+
+/// `critical-section` implementation for the single-core VR4300.
+///
+/// Clears Status.IE on entry and restores its previous value on exit. The
+/// read-modify-write of Status is not atomic, so interrupt handlers must
+/// leave Status as they found it.
+///
+/// Only built for the VR4300, so host builds with the feature enabled can
+/// still bring their own implementation.
+#[cfg(all(feature = "critical-section-impl", target_arch = "mips"))]
+struct SingleCoreCriticalSection;
+
+#[cfg(all(feature = "critical-section-impl", target_arch = "mips"))]
+critical_section::set_impl!(SingleCoreCriticalSection);
+
+#[cfg(all(feature = "critical-section-impl", target_arch = "mips"))]
+unsafe impl critical_section::Impl for SingleCoreCriticalSection {
+    unsafe fn acquire() -> critical_section::RawRestoreState {
+        let status = Status(status());
+        set_status(status.with_interrupt_enable(false).raw());
+        status.interrupt_enable()
+    }
+
+    unsafe fn release(was_enabled: critical_section::RawRestoreState) {
+        if was_enabled {
+            set_status(Status(status()).with_interrupt_enable(true).raw());
+        }
+    }
+}
@@ -0,0 +1,225 @@
+//! # Exceptions
+//!
+//! Saved processor context and a crash screen for unhandled exceptions. With
+//! the `rt` feature every exception vector saves an [`ExceptionFrame`] and
+//! passes it to the handler declared with `exception!`. The default handler
+//! passes interrupts on to the handler declared with `interrupt!` and calls
+//! [`crash`] for everything else.
+
+use core::fmt;
+
+use crate::{
+    cp0::{Cause, ExcCode, Status},
+    vi::{
+        console::Console,
+        framebuffer::FramebufferError,
+        mode::VideoMode,
+        pixel::{Pixel, Rgba5551, Rgba8888},
+        Vi,
+    },
+};
+
+/// # Exception frame size
+///
+/// Size in bytes of [`ExceptionFrame`], kept a multiple of 8 for the stack.
+pub const EXCEPTION_FRAME_SIZE: usize = 416;
+
+/// # GPR names
+pub const GPR_NAMES: [&str; 32] = [
+    "zr", "at", "v0", "v1", "a0", "a1", "a2", "a3", "t0", "t1", "t2", "t3", "t4", "t5", "t6", "t7",
+    "s0", "s1", "s2", "s3", "s4", "s5", "s6", "s7", "t8", "t9", "k0", "k1", "gp", "sp", "fp", "ra",
+];
+
+/// Number of stack words shown by [`crash`].
+const STACK_WORDS: usize = 16;
+
+/// # Crash screen lines
+///
+/// Framebuffer lines drawn over by [`crash`], enough for the report and
+/// within every video mode.
+pub const SCREEN_LINES: u32 = 224;
+
+const SCREEN_BACKGROUND: Rgba8888 = Rgba8888::new(0, 0, 0x40, 0xFF);
+
+/// # Exception frame
+///
+/// Context saved on entry to an exception. `$k0` and `$k1` are clobbered by
+/// the vector and not meaningful. The FPU registers are only saved when CP1
+/// was usable: with Status.FR set `fpr` holds all 32 64-bit registers,
+/// otherwise each even entry holds an even/odd register pair and the odd
+/// entries are unused. Changes to `epc` and the general purpose registers
+/// take effect when a handler returns.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[repr(C)]
+pub struct ExceptionFrame {
+    pub gpr: [u32; 32],
+    pub hi: u32,
+    pub lo: u32,
+    pub status: u32,
+    pub cause: u32,
+    pub epc: u32,
+    pub bad_vaddr: u32,
+    pub fcsr: u32,
+    _reserved: u32,
+    pub fpr: [u64; 32],
+}
+
+const _: () = assert!(core::mem::size_of::<ExceptionFrame>() == EXCEPTION_FRAME_SIZE);
+
+impl ExceptionFrame {
+    /// Saved Status register.
+    pub fn status(&self) -> Status {
+        Status(self.status)
+    }
+
+    /// Saved Cause register.
+    pub fn cause(&self) -> Cause {
+        Cause(self.cause)
+    }
+
+    /// Decoded exception code.
+    pub fn exc_code(&self) -> ExcCode {
+        self.cause().exc_code()
+    }
+
+    /// Stack pointer at the time of the exception.
+    pub fn sp(&self) -> u32 {
+        self.gpr[29]
+    }
+
+    /// Address of the faulting instruction, accounting for branch delay slots.
+    pub fn fault_address(&self) -> u32 {
+        match self.cause().branch_delay() {
+            true => self.epc.wrapping_add(4),
+            false => self.epc,
+        }
+    }
+}
+
+impl fmt::Display for ExceptionFrame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Exception: {}", self.exc_code().description())?;
+        writeln!(
+            f,
+            "EPC      {:08X}  BadVAddr {:08X}",
+            self.epc, self.bad_vaddr
+        )?;
+        writeln!(
+            f,
+            "Cause    {:08X}  Status   {:08X}",
+            self.cause, self.status
+        )?;
+
+        for (row, registers) in self.gpr.chunks(3).enumerate() {
+            for (column, value) in registers.iter().enumerate() {
+                if column > 0 {
+                    write!(f, " ")?;
+                }
+                write!(f, "{} {:08X}", GPR_NAMES[row * 3 + column], value)?;
+            }
+            writeln!(f)?;
+        }
+
+        writeln!(f, "hi {:08X} lo {:08X}", self.hi, self.lo)
+    }
+}
+
+static mut LOG: Option<fn(fmt::Arguments)> = None;
+
+static mut VIDEO_MODE: Option<&'static VideoMode> = None;
+
+/// Registers a debug log channel that [`crash`] also reports to.
+pub fn set_log(log: fn(fmt::Arguments)) {
+    critical_section::with(|_| unsafe { LOG = Some(log) });
+}
+
+/// Registers the video mode the VI is displaying, which [`crash`] needs to
+/// draw over the framebuffer.
+pub fn set_video_mode(mode: &'static VideoMode) {
+    critical_section::with(|_| unsafe { VIDEO_MODE = Some(mode) });
+}
+
+/// Reports an unhandled exception and halts.
+///
+/// The report is drawn over the framebuffer currently pointed to by the VI
+/// registers, if a mode was given to [`set_video_mode`], and written to the
+/// channel given to [`set_log`].
+pub fn crash(frame: &ExceptionFrame) -> ! {
+    let stack = stack_words(frame.sp());
+
+    if let Some(mode) = unsafe { VIDEO_MODE } {
+        let vi = unsafe { Vi::new() };
+        let _ = unsafe { show::<Rgba5551>(&vi, mode, frame, stack.as_ref()) }
+            .or_else(|_| unsafe { show::<Rgba8888>(&vi, mode, frame, stack.as_ref()) });
+    }
+
+    if let Some(log) = unsafe { LOG } {
+        log(format_args!("{}", Report(frame, stack.as_ref())));
+    }
+
+    #[allow(clippy::empty_loop)]
+    loop {}
+}
+
+struct Report<'a>(&'a ExceptionFrame, Option<&'a [u32; STACK_WORDS]>);
+
+impl fmt::Display for Report<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        report(f, self.0, self.1)
+    }
+}
+
+fn report(
+    out: &mut impl fmt::Write,
+    frame: &ExceptionFrame,
+    stack: Option<&[u32; STACK_WORDS]>,
+) -> fmt::Result {
+    write!(out, "{}", frame)?;
+    writeln!(out, "Stack at {:08X}:", frame.sp())?;
+
+    match stack {
+        Some(words) => {
+            for row in words.chunks(4) {
+                for word in row {
+                    write!(out, " {:08X}", word)?;
+                }
+                writeln!(out)?;
+            }
+            Ok(())
+        }
+        None => writeln!(out, " (invalid)"),
+    }
+}
+
+/// Reads the words at the top of the stack, if `sp` points into RDRAM.
+fn stack_words(sp: u32) -> Option<[u32; STACK_WORDS]> {
+    let end = sp.checked_add((STACK_WORDS * 4) as u32)?;
+    if !sp.is_multiple_of(4) || sp < 0x8000_0000 || end > 0x8080_0000 {
+        return None;
+    }
+
+    let mut words = [0; STACK_WORDS];
+    for (index, word) in words.iter_mut().enumerate() {
+        *word = unsafe { core::ptr::read_volatile((sp as *const u32).add(index)) };
+    }
+    Some(words)
+}
+
+/// Draws the report over the displayed framebuffer, if it holds `P`.
+///
+/// # Safety
+///
+/// Writes to whatever RDRAM the VI is currently scanning out.
+unsafe fn show<P: Pixel + From<Rgba8888>>(
+    vi: &Vi,
+    mode: &VideoMode,
+    frame: &ExceptionFrame,
+    stack: Option<&[u32; STACK_WORDS]>,
+) -> Result<(), FramebufferError> {
+    let foreground = P::from(Rgba8888::WHITE);
+    let background = P::from(SCREEN_BACKGROUND);
+    let mut console = Console::displayed(vi, mode, SCREEN_LINES, foreground, background)?;
+    console.clear();
+    let _ = report(&mut console, frame, stack);
+    Ok(())
+}
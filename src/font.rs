@@ -0,0 +1,118 @@
+//! # 8x8 bitmap font
+//!
+//! Printable ASCII glyphs from the public domain X11 misc-fixed 5x8 face,
+//! padded into 8x8 cells. Each glyph is eight rows, top to bottom, with the
+//! most significant bit being the leftmost pixel.
+
+/// # Glyph width in pixels
+pub const GLYPH_WIDTH: u32 = 8;
+
+/// # Glyph height in pixels
+pub const GLYPH_HEIGHT: u32 = 8;
+
+/// Returns the glyph for `c`, substituting `?` for anything outside
+/// printable ASCII.
+pub fn glyph(c: char) -> &'static [u8; 8] {
+    match c {
+        ' '..='~' => &GLYPHS[c as usize - 0x20],
+        _ => &GLYPHS['?' as usize - 0x20],
+    }
+}
+
+static GLYPHS: [[u8; 8]; 95] = [
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // ' '
+    [0x00, 0x10, 0x10, 0x10, 0x10, 0x00, 0x10, 0x00], // '!'
+    [0x00, 0x28, 0x28, 0x28, 0x00, 0x00, 0x00, 0x00], // '"'
+    [0x28, 0x28, 0x7C, 0x28, 0x7C, 0x28, 0x28, 0x00], // '#'
+    [0x10, 0x38, 0x50, 0x38, 0x14, 0x38, 0x10, 0x00], // '$'
+    [0x00, 0x20, 0x28, 0x10, 0x28, 0x08, 0x00, 0x00], // '%'
+    [0x20, 0x50, 0x50, 0x20, 0x50, 0x50, 0x28, 0x00], // '&'
+    [0x00, 0x10, 0x10, 0x10, 0x00, 0x00, 0x00, 0x00], // '\''
+    [0x00, 0x10, 0x20, 0x20, 0x20, 0x20, 0x10, 0x00], // '('
+    [0x00, 0x20, 0x10, 0x10, 0x10, 0x10, 0x20, 0x00], // ')'
+    [0x00, 0x00, 0x48, 0x30, 0x78, 0x30, 0x48, 0x00], // '*'
+    [0x00, 0x00, 0x10, 0x10, 0x7C, 0x10, 0x10, 0x00], // '+'
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x18, 0x10, 0x20], // ','
+    [0x00, 0x00, 0x00, 0x00, 0x78, 0x00, 0x00, 0x00], // '-'
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x10, 0x38, 0x10], // '.'
+    [0x00, 0x08, 0x08, 0x10, 0x20, 0x40, 0x40, 0x00], // '/'
+    [0x00, 0x10, 0x28, 0x28, 0x28, 0x28, 0x10, 0x00], // '0'
+    [0x00, 0x10, 0x30, 0x10, 0x10, 0x10, 0x38, 0x00], // '1'
+    [0x00, 0x30, 0x48, 0x08, 0x30, 0x40, 0x78, 0x00], // '2'
+    [0x00, 0x78, 0x10, 0x30, 0x08, 0x48, 0x30, 0x00], // '3'
+    [0x00, 0x10, 0x30, 0x50, 0x78, 0x10, 0x10, 0x00], // '4'
+    [0x00, 0x78, 0x40, 0x70, 0x08, 0x48, 0x30, 0x00], // '5'
+    [0x00, 0x30, 0x40, 0x70, 0x48, 0x48, 0x30, 0x00], // '6'
+    [0x00, 0x78, 0x08, 0x10, 0x10, 0x20, 0x20, 0x00], // '7'
+    [0x00, 0x30, 0x48, 0x30, 0x48, 0x48, 0x30, 0x00], // '8'
+    [0x00, 0x30, 0x48, 0x48, 0x38, 0x08, 0x30, 0x00], // '9'
+    [0x00, 0x00, 0x30, 0x30, 0x00, 0x30, 0x30, 0x00], // ':'
+    [0x00, 0x00, 0x18, 0x18, 0x00, 0x18, 0x10, 0x20], // ';'
+    [0x00, 0x08, 0x10, 0x20, 0x20, 0x10, 0x08, 0x00], // '<'
+    [0x00, 0x00, 0x00, 0x78, 0x00, 0x78, 0x00, 0x00], // '='
+    [0x00, 0x20, 0x10, 0x08, 0x08, 0x10, 0x20, 0x00], // '>'
+    [0x00, 0x10, 0x28, 0x08, 0x10, 0x00, 0x10, 0x00], // '?'
+    [0x18, 0x24, 0x4C, 0x54, 0x54, 0x48, 0x20, 0x18], // '@'
+    [0x00, 0x30, 0x48, 0x48, 0x78, 0x48, 0x48, 0x00], // 'A'
+    [0x00, 0x70, 0x48, 0x70, 0x48, 0x48, 0x70, 0x00], // 'B'
+    [0x00, 0x30, 0x48, 0x40, 0x40, 0x48, 0x30, 0x00], // 'C'
+    [0x00, 0x70, 0x48, 0x48, 0x48, 0x48, 0x70, 0x00], // 'D'
+    [0x00, 0x78, 0x40, 0x70, 0x40, 0x40, 0x78, 0x00], // 'E'
+    [0x00, 0x78, 0x40, 0x70, 0x40, 0x40, 0x40, 0x00], // 'F'
+    [0x00, 0x30, 0x48, 0x40, 0x58, 0x48, 0x30, 0x00], // 'G'
+    [0x00, 0x48, 0x48, 0x78, 0x48, 0x48, 0x48, 0x00], // 'H'
+    [0x00, 0x38, 0x10, 0x10, 0x10, 0x10, 0x38, 0x00], // 'I'
+    [0x00, 0x38, 0x10, 0x10, 0x10, 0x50, 0x20, 0x00], // 'J'
+    [0x00, 0x48, 0x50, 0x60, 0x50, 0x50, 0x48, 0x00], // 'K'
+    [0x00, 0x40, 0x40, 0x40, 0x40, 0x40, 0x78, 0x00], // 'L'
+    [0x00, 0x48, 0x78, 0x78, 0x48, 0x48, 0x48, 0x00], // 'M'
+    [0x00, 0x48, 0x68, 0x78, 0x58, 0x58, 0x48, 0x00], // 'N'
+    [0x00, 0x30, 0x48, 0x48, 0x48, 0x48, 0x30, 0x00], // 'O'
+    [0x00, 0x70, 0x48, 0x48, 0x70, 0x40, 0x40, 0x00], // 'P'
+    [0x00, 0x30, 0x48, 0x48, 0x68, 0x58, 0x30, 0x08], // 'Q'
+    [0x00, 0x70, 0x48, 0x48, 0x70, 0x48, 0x48, 0x00], // 'R'
+    [0x00, 0x30, 0x48, 0x20, 0x10, 0x48, 0x30, 0x00], // 'S'
+    [0x00, 0x38, 0x10, 0x10, 0x10, 0x10, 0x10, 0x00], // 'T'
+    [0x00, 0x48, 0x48, 0x48, 0x48, 0x48, 0x30, 0x00], // 'U'
+    [0x00, 0x48, 0x48, 0x48, 0x48, 0x30, 0x30, 0x00], // 'V'
+    [0x00, 0x48, 0x48, 0x48, 0x78, 0x78, 0x48, 0x00], // 'W'
+    [0x00, 0x48, 0x48, 0x30, 0x30, 0x48, 0x48, 0x00], // 'X'
+    [0x00, 0x44, 0x44, 0x28, 0x10, 0x10, 0x10, 0x00], // 'Y'
+    [0x00, 0x78, 0x08, 0x10, 0x20, 0x40, 0x78, 0x00], // 'Z'
+    [0x00, 0x38, 0x20, 0x20, 0x20, 0x20, 0x38, 0x00], // '['
+    [0x00, 0x40, 0x40, 0x20, 0x10, 0x08, 0x08, 0x00], // '\\'
+    [0x00, 0x38, 0x08, 0x08, 0x08, 0x08, 0x38, 0x00], // ']'
+    [0x00, 0x10, 0x28, 0x00, 0x00, 0x00, 0x00, 0x00], // '^'
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x78], // '_'
+    [0x00, 0x20, 0x10, 0x00, 0x00, 0x00, 0x00, 0x00], // '`'
+    [0x00, 0x00, 0x00, 0x38, 0x48, 0x48, 0x38, 0x00], // 'a'
+    [0x00, 0x40, 0x40, 0x70, 0x48, 0x48, 0x70, 0x00], // 'b'
+    [0x00, 0x00, 0x00, 0x18, 0x20, 0x20, 0x18, 0x00], // 'c'
+    [0x00, 0x08, 0x08, 0x38, 0x48, 0x48, 0x38, 0x00], // 'd'
+    [0x00, 0x00, 0x00, 0x30, 0x58, 0x60, 0x30, 0x00], // 'e'
+    [0x00, 0x10, 0x28, 0x20, 0x70, 0x20, 0x20, 0x00], // 'f'
+    [0x00, 0x00, 0x00, 0x30, 0x48, 0x38, 0x08, 0x30], // 'g'
+    [0x00, 0x40, 0x40, 0x70, 0x48, 0x48, 0x48, 0x00], // 'h'
+    [0x00, 0x10, 0x00, 0x30, 0x10, 0x10, 0x38, 0x00], // 'i'
+    [0x00, 0x08, 0x00, 0x08, 0x08, 0x08, 0x28, 0x10], // 'j'
+    [0x00, 0x40, 0x40, 0x48, 0x70, 0x48, 0x48, 0x00], // 'k'
+    [0x00, 0x30, 0x10, 0x10, 0x10, 0x10, 0x38, 0x00], // 'l'
+    [0x00, 0x00, 0x00, 0x68, 0x54, 0x54, 0x54, 0x00], // 'm'
+    [0x00, 0x00, 0x00, 0x70, 0x48, 0x48, 0x48, 0x00], // 'n'
+    [0x00, 0x00, 0x00, 0x30, 0x48, 0x48, 0x30, 0x00], // 'o'
+    [0x00, 0x00, 0x00, 0x70, 0x48, 0x70, 0x40, 0x40], // 'p'
+    [0x00, 0x00, 0x00, 0x38, 0x48, 0x38, 0x08, 0x08], // 'q'
+    [0x00, 0x00, 0x00, 0x50, 0x68, 0x40, 0x40, 0x00], // 'r'
+    [0x00, 0x00, 0x00, 0x18, 0x30, 0x08, 0x30, 0x00], // 's'
+    [0x00, 0x20, 0x20, 0x70, 0x20, 0x28, 0x10, 0x00], // 't'
+    [0x00, 0x00, 0x00, 0x48, 0x48, 0x48, 0x38, 0x00], // 'u'
+    [0x00, 0x00, 0x00, 0x28, 0x28, 0x28, 0x10, 0x00], // 'v'
+    [0x00, 0x00, 0x00, 0x44, 0x54, 0x54, 0x28, 0x00], // 'w'
+    [0x00, 0x00, 0x00, 0x48, 0x30, 0x30, 0x48, 0x00], // 'x'
+    [0x00, 0x00, 0x00, 0x48, 0x48, 0x38, 0x48, 0x30], // 'y'
+    [0x00, 0x00, 0x00, 0x78, 0x10, 0x20, 0x78, 0x00], // 'z'
+    [0x18, 0x20, 0x10, 0x60, 0x10, 0x20, 0x18, 0x00], // '{'
+    [0x00, 0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x00], // '|'
+    [0x60, 0x10, 0x20, 0x18, 0x20, 0x10, 0x60, 0x00], // '}'
+    [0x00, 0x28, 0x50, 0x00, 0x00, 0x00, 0x00, 0x00], // '~'
+];
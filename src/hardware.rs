@@ -17,6 +17,11 @@ pub struct Hardware {
 static mut TAKEN: bool = false;
 
 impl Hardware {
+    /// Creates the peripheral singleton without checking whether it exists.
+    ///
+    /// # Safety
+    ///
+    /// Only one `Hardware` may be live at a time; prefer [`Hardware::take`].
     pub unsafe fn new() -> Self {
         TAKEN = true;
         Self {
@@ -34,11 +39,17 @@ impl Hardware {
         }
     }
 
+    /// Returns the peripheral singleton the first time it is called.
+    ///
+    /// Needs a `critical-section` implementation, like the
+    /// `critical-section-impl` feature.
     pub fn take() -> Option<Self> {
-        if unsafe { !TAKEN } {
-            Some(unsafe { Self::new() })
-        } else {
-            None
-        }
+        critical_section::with(|_| {
+            if unsafe { !TAKEN } {
+                Some(unsafe { Self::new() })
+            } else {
+                None
+            }
+        })
     }
 }
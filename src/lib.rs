@@ -1,19 +1,31 @@
 //! # Nintendo 64 PAC
 
 #![no_std]
+#![cfg_attr(target_arch = "mips", feature(asm_experimental_arch))]
+
+#[cfg(any(test, feature = "std"))]
+extern crate std;
 
 pub mod ai;
+pub mod boot;
+pub mod cp0;
 pub mod dpc;
 pub mod dps;
+pub mod exception;
+pub mod font;
 pub mod hardware;
 mod macros;
+pub mod memory;
 pub mod mi;
 pub mod pc;
 pub mod pi;
 pub mod rdram;
 pub mod ri;
+#[cfg(feature = "rt")]
+pub mod rt;
 pub mod si;
 pub mod sp;
+pub mod tlb;
 pub mod vi;
 
 pub mod prelude {
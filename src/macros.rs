@@ -31,6 +31,10 @@ macro_rules! registers {
 		pub struct $name(core::marker::PhantomData<()>);
 
 		impl $name {
+			/// # Safety
+			///
+			/// Aliases the register block; callers must not create more than
+			/// one handle outside of [`Hardware`](crate::hardware::Hardware).
 			pub unsafe fn new() -> Self {
 				Self(core::marker::PhantomData)
 			}
@@ -70,7 +74,7 @@ macro_rules! fields {
     [$($(#[$($attrss:tt)*])* $size:path => $name:ident,)*] => {
 		$(
 			$(#[$($attrss)*])*
-			#[derive(Debug)]
+			#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 			pub struct $name(pub $size);
 
 			impl core::ops::Deref for $name {
@@ -83,37 +87,38 @@ macro_rules! fields {
 
 			impl From<$name> for u8 {
 				fn from(value: $name) -> Self {
-					value.into()
+					u32::from(value.0) as u8
 				}
 			}
 
 			impl From<$name> for u16 {
 				fn from(value: $name) -> Self {
-					value.into()
+					u32::from(value.0) as u16
 				}
 			}
 
 			impl From<$name> for u32 {
 				fn from(value: $name) -> Self {
-					value.into()
+					u32::from(value.0)
 				}
 			}
 
 			impl From<u8> for $name {
 				fn from(value: u8) -> Self {
-					value.into()
+					u32::from(value).into()
 				}
 			}
 
 			impl From<u16> for $name {
 				fn from(value: u16) -> Self {
-					value.into()
+					u32::from(value).into()
 				}
 			}
 
 			impl From<u32> for $name {
 				fn from(value: u32) -> Self {
-					value.into()
+					let value = value & u32::from(<$size>::MAX);
+					Self(<$size>::try_from(value).unwrap_or_else(|_| unreachable!()))
 				}
 			}
 		)*
@@ -127,7 +132,7 @@ macro_rules! enums {
 	},)*] => {
 		$(
 			$(#[$($attrss)*])*
-			#[derive(Debug)]
+			#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 			pub enum $name { $($key,)* }
 
 			impl From<$size> for $name {
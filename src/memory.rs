@@ -0,0 +1,67 @@
+//! # Memory segments
+//!
+//! The CPU sees RDRAM through KSEG0, cached, at `0x80000000` and through
+//! KSEG1, uncached, at `0xA0000000`. Peripherals only see physical
+//! addresses.
+
+/// # KSEG0 base
+pub const KSEG0_BASE: u32 = 0x8000_0000;
+
+/// # KSEG1 base
+pub const KSEG1_BASE: u32 = 0xA000_0000;
+
+/// Physical address of a KSEG0 or KSEG1 pointer.
+pub fn physical<T>(ptr: *const T) -> u32 {
+    ptr as usize as u32 & 0x1FFF_FFFF
+}
+
+/// KSEG1 alias of a KSEG0 or KSEG1 pointer.
+///
+/// Off the console pointers are returned unchanged, so code using this can
+/// run on the host against ordinary memory.
+pub fn uncached<T>(ptr: *mut T) -> *mut T {
+    match cfg!(target_arch = "mips") {
+        true => (physical(ptr) | KSEG1_BASE) as usize as *mut T,
+        false => ptr,
+    }
+}
+
+/// # Data cache line size
+pub const DCACHE_LINE: usize = 16;
+
+/// Writes back dirty data cache lines covering `len` bytes at `ptr`, so a
+/// DMA reading RDRAM sees the CPU's writes.
+pub fn writeback_dcache<T>(ptr: *const T, len: usize) {
+    dcache_op::<0x19>(ptr as usize, len);
+}
+
+/// Writes back and invalidates data cache lines covering `len` bytes at
+/// `ptr`, so the CPU rereads RDRAM after a DMA writes it.
+///
+/// Lines partly outside the range are written back first, so data sharing
+/// them survives.
+pub fn writeback_invalidate_dcache<T>(ptr: *const T, len: usize) {
+    dcache_op::<0x15>(ptr as usize, len);
+}
+
+/// Runs a hit `cache` operation on every line covering the range. Off the
+/// console there is no cache to maintain.
+#[cfg_attr(not(target_arch = "mips"), allow(unused_variables))]
+fn dcache_op<const OP: u8>(address: usize, len: usize) {
+    #[cfg(target_arch = "mips")]
+    if len > 0 {
+        let start = address & !(DCACHE_LINE - 1);
+        for line in (start..address + len).step_by(DCACHE_LINE) {
+            unsafe {
+                core::arch::asm!(
+                    ".set noat",
+                    "cache {op}, 0({0})",
+                    ".set at",
+                    in(reg) line,
+                    op = const OP,
+                    options(nostack),
+                );
+            }
+        }
+    }
+}
@@ -4,6 +4,9 @@ use proc_bitfield::bitfield;
 
 use crate::{fields, registers};
 
+pub mod dma;
+pub mod queue;
+
 /// # PI base address
 pub const PI_BASE_ADDR: u32 = 0x0460_0000;
 
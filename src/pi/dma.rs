@@ -0,0 +1,427 @@
+//! # PI DMA
+//!
+//! Blocking transfers between RDRAM and the cartridge bus.
+//!
+//! The PI only moves data between an 8-byte aligned RDRAM address and a
+//! 2-byte aligned cartridge address. Bytes before the first aligned RDRAM
+//! address and after the last whole 8 bytes are copied by the CPU through
+//! 32-bit PI I/O accesses instead. A transfer whose cartridge address cannot
+//! be aligned along with RDRAM goes through an aligned buffer on the stack,
+//! costing a CPU copy of every byte.
+//!
+//! The data cache is written back over the buffer before every transfer, and
+//! invalidated over it before a read, so buffers can be ordinary cached
+//! memory. Reads only DMA whole [`DCACHE_LINE`]s of the buffer: a line shared
+//! with other data, such as the stack an interrupt handler uses, could be
+//! refilled during the DMA and later hide the bytes it wrote, so the CPU
+//! copies the bytes of partial lines too.
+
+use core::ptr::{read_volatile, write_volatile};
+
+use crate::memory::{
+    physical, writeback_dcache, writeback_invalidate_dcache, DCACHE_LINE, KSEG1_BASE,
+};
+
+use super::{Pi, PiCartAddrReg, PiDramAddrReg, PiRdLenReg, PiStatusReg, PiWrLenReg};
+
+/// # PI DMA alignment
+///
+/// Alignment in bytes of the RDRAM address of a PI DMA.
+pub const PI_DMA_RDRAM_ALIGN: usize = 8;
+
+/// # PI DMA cartridge alignment
+pub const PI_DMA_CART_ALIGN: u32 = 2;
+
+/// # Maximum PI DMA length
+pub const PI_DMA_MAX_LEN: usize = 0x0100_0000;
+
+/// # Cartridge address space end
+///
+/// The PI bus decodes 29 address bits.
+pub const PI_CART_END: u32 = 0x2000_0000;
+
+/// Size of the stack buffer misaligned transfers go through.
+const BOUNCE_LEN: usize = 512;
+
+/// Bounce buffer, aligned to data cache lines so maintaining the cache over
+/// it cannot touch neighbouring stack data.
+#[repr(C, align(16))]
+struct Bounce([u8; BOUNCE_LEN]);
+
+/// # PI error
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PiError {
+    /// The PI reported an error, e.g. for an access to an unmapped address.
+    Transfer,
+
+    /// The transfer runs past the end of the cartridge address space.
+    OutOfRange,
+
+    /// A queued transfer is not aligned for DMA.
+    Misaligned,
+
+    /// Every slot of the DMA queue is taken.
+    QueueFull,
+}
+
+/// Direction of a transfer.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Direction {
+    FromCart,
+    ToCart,
+}
+
+impl Pi {
+    /// Whether a DMA or I/O access is in progress.
+    pub fn is_busy(&self) -> bool {
+        let status = unsafe { read_volatile(&self.pi_status_reg) };
+        status.dma_busy() || status.io_busy()
+    }
+
+    /// Waits for the PI to go idle.
+    pub fn wait_idle(&self) {
+        while self.is_busy() {
+            core::hint::spin_loop();
+        }
+    }
+
+    /// Resets the PI controller, aborting any DMA and clearing its error.
+    pub fn reset(&mut self) {
+        unsafe {
+            write_volatile(
+                &mut self.pi_status_reg,
+                PiStatusReg(0)
+                    .with_reset_controller(true)
+                    .with_clear_intr(true),
+            );
+        }
+    }
+
+    /// Acknowledges the PI interrupt.
+    pub fn acknowledge_interrupt(&mut self) {
+        unsafe {
+            write_volatile(
+                &mut self.pi_status_reg,
+                PiStatusReg(0).with_clear_intr(true),
+            );
+        }
+    }
+
+    /// Reads the 32-bit word at `cart_addr`, which is rounded down to a
+    /// multiple of 4.
+    pub fn io_read(&self, cart_addr: u32) -> u32 {
+        self.wait_idle();
+        unsafe { read_volatile(io_pointer(cart_addr)) }
+    }
+
+    /// Writes the 32-bit word at `cart_addr`, which is rounded down to a
+    /// multiple of 4.
+    pub fn io_write(&mut self, cart_addr: u32, value: u32) {
+        self.wait_idle();
+        unsafe { write_volatile(io_pointer(cart_addr), value) }
+    }
+
+    /// Copies `buffer.len()` bytes from the cartridge bus at `cart_addr`
+    /// into `buffer`.
+    pub fn read_from_cart(&mut self, cart_addr: u32, buffer: &mut [u8]) -> Result<(), PiError> {
+        check_range(cart_addr, buffer.len())?;
+        let Some((head, middle)) = split(cart_addr, buffer.as_ptr(), buffer.len(), DCACHE_LINE)
+        else {
+            return self.read_bounced(cart_addr, buffer);
+        };
+
+        for (offset, byte) in buffer[..head].iter_mut().enumerate() {
+            *byte = self.io_read_byte(cart_addr + offset as u32);
+        }
+
+        if middle > 0 {
+            let chunk = &mut buffer[head..head + middle];
+            writeback_invalidate_dcache(chunk.as_ptr(), middle);
+            self.dma(
+                Direction::FromCart,
+                cart_addr + head as u32,
+                chunk.as_ptr(),
+                middle,
+            )?;
+        }
+
+        let tail = head + middle;
+        for (offset, byte) in buffer[tail..].iter_mut().enumerate() {
+            *byte = self.io_read_byte(cart_addr + (tail + offset) as u32);
+        }
+
+        Ok(())
+    }
+
+    /// Copies `buffer` to the cartridge bus at `cart_addr`.
+    ///
+    /// Bytes copied by the CPU are merged into the cartridge's existing
+    /// 32-bit words, so the device must support reading them back.
+    pub fn write_to_cart(&mut self, cart_addr: u32, buffer: &[u8]) -> Result<(), PiError> {
+        check_range(cart_addr, buffer.len())?;
+        let Some((head, middle)) =
+            split(cart_addr, buffer.as_ptr(), buffer.len(), PI_DMA_RDRAM_ALIGN)
+        else {
+            return self.write_bounced(cart_addr, buffer);
+        };
+
+        for (offset, byte) in buffer[..head].iter().enumerate() {
+            self.io_write_byte(cart_addr + offset as u32, *byte);
+        }
+
+        if middle > 0 {
+            let chunk = &buffer[head..head + middle];
+            writeback_dcache(chunk.as_ptr(), middle);
+            self.dma(
+                Direction::ToCart,
+                cart_addr + head as u32,
+                chunk.as_ptr(),
+                middle,
+            )?;
+        }
+
+        let tail = head + middle;
+        for (offset, byte) in buffer[tail..].iter().enumerate() {
+            self.io_write_byte(cart_addr + (tail + offset) as u32, *byte);
+        }
+
+        Ok(())
+    }
+
+    /// Reads through the bounce buffer, starting the DMA at the even
+    /// cartridge address before an odd one. The bytes after the last whole 8
+    /// are copied by the CPU, so nothing past the range is read.
+    fn read_bounced(&mut self, cart_addr: u32, buffer: &mut [u8]) -> Result<(), PiError> {
+        let start = cart_addr & !1;
+        let skip = (cart_addr & 1) as usize;
+        let end = skip + buffer.len();
+        let whole = end & !(PI_DMA_RDRAM_ALIGN - 1);
+
+        let mut bounce = Bounce([0; BOUNCE_LEN]);
+        for offset in (0..whole).step_by(BOUNCE_LEN) {
+            let len = (whole - offset).min(BOUNCE_LEN);
+            writeback_invalidate_dcache(bounce.0.as_ptr(), len);
+            self.dma(
+                Direction::FromCart,
+                start + offset as u32,
+                bounce.0.as_mut_ptr(),
+                len,
+            )?;
+
+            let first = offset.max(skip);
+            buffer[first - skip..offset + len - skip]
+                .copy_from_slice(&bounce.0[first - offset..len]);
+        }
+
+        for offset in whole.max(skip)..end {
+            buffer[offset - skip] = self.io_read_byte(start + offset as u32);
+        }
+
+        Ok(())
+    }
+
+    /// Writes through the bounce buffer. An odd first byte and the bytes
+    /// after the last whole 8 are copied by the CPU.
+    fn write_bounced(&mut self, cart_addr: u32, buffer: &[u8]) -> Result<(), PiError> {
+        let (first, rest) = buffer.split_at((cart_addr & 1) as usize);
+        if let Some(&byte) = first.first() {
+            self.io_write_byte(cart_addr, byte);
+        }
+        let cart_addr = cart_addr + first.len() as u32;
+
+        let mut bounce = Bounce([0; BOUNCE_LEN]);
+        let whole = rest.len() & !(PI_DMA_RDRAM_ALIGN - 1);
+        for (index, chunk) in rest[..whole].chunks(BOUNCE_LEN).enumerate() {
+            bounce.0[..chunk.len()].copy_from_slice(chunk);
+            writeback_dcache(bounce.0.as_ptr(), chunk.len());
+            self.dma(
+                Direction::ToCart,
+                cart_addr + (index * BOUNCE_LEN) as u32,
+                bounce.0.as_ptr(),
+                chunk.len(),
+            )?;
+        }
+
+        for (offset, byte) in rest[whole..].iter().enumerate() {
+            self.io_write_byte(cart_addr + (whole + offset) as u32, *byte);
+        }
+
+        Ok(())
+    }
+
+    /// Runs DMAs of up to [`PI_DMA_MAX_LEN`] bytes, waiting for each.
+    ///
+    /// The controller is reset after an error, so the next transfer starts
+    /// from a clean state.
+    fn dma(
+        &mut self,
+        direction: Direction,
+        cart_addr: u32,
+        dram: *const u8,
+        len: usize,
+    ) -> Result<(), PiError> {
+        let mut done = 0;
+        while done < len {
+            let chunk = (len - done).min(PI_DMA_MAX_LEN);
+            self.wait_idle();
+            unsafe {
+                self.start_dma(
+                    direction,
+                    cart_addr + done as u32,
+                    physical(dram.add(done)),
+                    chunk,
+                );
+            }
+            self.wait_idle();
+
+            let status = unsafe { read_volatile(&self.pi_status_reg) };
+            if status.error() {
+                self.reset();
+                return Err(PiError::Transfer);
+            }
+            self.acknowledge_interrupt();
+            done += chunk;
+        }
+        Ok(())
+    }
+
+    /// Programs and starts one DMA.
+    ///
+    /// # Safety
+    ///
+    /// The PI must be idle, `dram` an aligned physical address and `len` at
+    /// most [`PI_DMA_MAX_LEN`].
+    pub(crate) unsafe fn start_dma(
+        &mut self,
+        direction: Direction,
+        cart_addr: u32,
+        dram: u32,
+        len: usize,
+    ) {
+        let length = (len - 1) as u32;
+        write_volatile(
+            &mut self.pi_dram_addr_reg,
+            PiDramAddrReg(0).with_starting_rdram_address(dram.into()),
+        );
+        write_volatile(
+            &mut self.pi_cart_addr_reg,
+            PiCartAddrReg(0).with_starting_ad16_address(cart_addr.into()),
+        );
+        match direction {
+            Direction::FromCart => write_volatile(
+                &mut self.pi_wr_len_reg,
+                PiWrLenReg(0).with_write_data_length(length.into()),
+            ),
+            Direction::ToCart => write_volatile(
+                &mut self.pi_rd_len_reg,
+                PiRdLenReg(0).with_read_data_length(length.into()),
+            ),
+        }
+    }
+
+    fn io_read_byte(&self, cart_addr: u32) -> u8 {
+        let word = self.io_read(cart_addr);
+        word.to_be_bytes()[(cart_addr & 3) as usize]
+    }
+
+    fn io_write_byte(&mut self, cart_addr: u32, byte: u8) {
+        let mut bytes = self.io_read(cart_addr).to_be_bytes();
+        bytes[(cart_addr & 3) as usize] = byte;
+        self.io_write(cart_addr, u32::from_be_bytes(bytes));
+    }
+}
+
+/// Uncached CPU pointer to the word holding `cart_addr`.
+fn io_pointer(cart_addr: u32) -> *mut u32 {
+    ((KSEG1_BASE | (cart_addr & 0x1FFF_FFFC)) as usize) as *mut u32
+}
+
+/// Checks that `len` bytes from `cart_addr` are within the cartridge
+/// address space.
+pub(crate) fn check_range(cart_addr: u32, len: usize) -> Result<(), PiError> {
+    match u32::try_from(len)
+        .ok()
+        .and_then(|len| cart_addr.checked_add(len))
+    {
+        Some(end) if end <= PI_CART_END => Ok(()),
+        _ => Err(PiError::OutOfRange),
+    }
+}
+
+/// Splits a transfer into the bytes copied by the CPU before DMA starts and
+/// the bytes moved by DMA, which start and end on an `align` boundary in
+/// RDRAM; the rest are copied by the CPU afterwards.
+///
+/// Returns `None` if the cartridge address cannot be aligned along with
+/// RDRAM, unless the transfer is too short to use DMA anyway.
+fn split(cart_addr: u32, dram: *const u8, len: usize, align: usize) -> Option<(usize, usize)> {
+    let head = dram.align_offset(align).min(len);
+    let middle = (len - head) & !(align - 1);
+    let aligned_cart = (cart_addr + head as u32).is_multiple_of(PI_DMA_CART_ALIGN);
+    match (aligned_cart, middle) {
+        (true, _) | (false, 0) => Some((head, middle)),
+        (false, _) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[repr(C, align(16))]
+    struct Aligned([u8; 64]);
+
+    const RDRAM: usize = PI_DMA_RDRAM_ALIGN;
+
+    #[test]
+    fn checks_range() {
+        assert_eq!(check_range(0x1000_0000, 0x100), Ok(()));
+        assert_eq!(check_range(PI_CART_END - 4, 4), Ok(()));
+        assert_eq!(check_range(PI_CART_END - 4, 5), Err(PiError::OutOfRange));
+        assert_eq!(check_range(PI_CART_END, 0), Ok(()));
+        assert_eq!(check_range(u32::MAX, 2), Err(PiError::OutOfRange));
+    }
+
+    #[test]
+    fn splits_around_dma() {
+        let memory = Aligned([0; 64]);
+        let base = memory.0.as_ptr();
+
+        assert_eq!(split(0x1000_0000, base, 64, RDRAM), Some((0, 64)));
+        assert_eq!(split(0x1000_0000, base, 63, RDRAM), Some((0, 56)));
+        assert_eq!(
+            split(0x1000_0003, unsafe { base.add(3) }, 61, RDRAM),
+            Some((5, 56))
+        );
+        assert_eq!(
+            split(0x1000_0000, unsafe { base.add(1) }, 6, RDRAM),
+            Some((6, 0))
+        );
+    }
+
+    #[test]
+    fn reads_whole_cache_lines() {
+        let memory = Aligned([0; 64]);
+        let base = memory.0.as_ptr();
+
+        assert_eq!(split(0x1000_0000, base, 63, DCACHE_LINE), Some((0, 48)));
+        assert_eq!(
+            split(0x1000_0008, unsafe { base.add(8) }, 56, DCACHE_LINE),
+            Some((8, 48))
+        );
+        assert_eq!(
+            split(0x1000_0008, unsafe { base.add(8) }, 20, DCACHE_LINE),
+            Some((8, 0))
+        );
+    }
+
+    #[test]
+    fn bounces_misaligned_cart_address() {
+        let memory = Aligned([0; 64]);
+        let base = memory.0.as_ptr();
+
+        assert_eq!(split(0x1000_0001, base, 64, RDRAM), None);
+        assert_eq!(split(0x1000_0000, unsafe { base.add(1) }, 63, RDRAM), None);
+        assert_eq!(split(0x1000_0001, base, 4, RDRAM), Some((0, 0)));
+    }
+}
@@ -0,0 +1,702 @@
+//! # Asynchronous PI DMA
+//!
+//! [`DmaQueue`] runs PI DMAs in the background. Each request takes ownership
+//! of its buffer and returns a [`Transfer`] future, which resolves to the
+//! buffer once the PI interrupt reports the DMA done. Up to `N` requests can
+//! be outstanding; they run one after another in submission order, each
+//! started from the interrupt handler as soon as the previous one finishes.
+//!
+//! The queue lives in a `static` and needs the PI interrupt routed to
+//! [`DmaQueue::on_interrupt`]:
+//!
+//! ```ignore
+//! static PI_QUEUE: DmaQueue<4> = DmaQueue::new();
+//!
+//! PI_QUEUE.start(hardware.pi, &mut hardware.mi);
+//! let (result, level) = PI_QUEUE.read_from_cart(0x1010_0000, buffer)?.await;
+//! ```
+//!
+//! Unlike [`Pi::read_from_cart`], requests are not split around misaligned
+//! bytes: the RDRAM address and length must be multiples of
+//! [`PI_DMA_RDRAM_ALIGN`] and the cartridge address of
+//! [`PI_DMA_CART_ALIGN`]. Reads from the cartridge must also cover whole
+//! data cache lines, [`DCACHE_LINE`] aligned, as the cache is only
+//! invalidated before the DMA and a line shared with other data could be
+//! refilled or written back over it while the DMA runs.
+
+use core::{
+    cell::RefCell,
+    future::Future,
+    pin::Pin,
+    ptr::read_volatile,
+    task::{Context, Poll, Waker},
+};
+
+use critical_section::{CriticalSection, Mutex};
+
+use crate::{
+    memory::{physical, writeback_dcache, writeback_invalidate_dcache, DCACHE_LINE},
+    mi::{Mi, MiIntrMaskReg},
+};
+
+use super::{
+    dma::{check_range, Direction, PiError, PI_DMA_CART_ALIGN, PI_DMA_MAX_LEN, PI_DMA_RDRAM_ALIGN},
+    Pi,
+};
+
+/// # DMA source buffer
+///
+/// Memory a DMA reads from.
+///
+/// # Safety
+///
+/// The memory must stay valid and unmoved for as long as the implementor
+/// exists, even if it is forgotten.
+pub unsafe trait ReadBuffer {
+    /// Start and length of the memory in bytes.
+    fn read_buffer(&self) -> (*const u8, usize);
+}
+
+/// # DMA destination buffer
+///
+/// Memory a DMA writes to.
+///
+/// # Safety
+///
+/// As for [`ReadBuffer`], and nothing else may access the memory while the
+/// implementor exists.
+pub unsafe trait WriteBuffer {
+    /// Start and length of the memory in bytes.
+    fn write_buffer(&mut self) -> (*mut u8, usize);
+}
+
+unsafe impl ReadBuffer for &'static [u8] {
+    fn read_buffer(&self) -> (*const u8, usize) {
+        (self.as_ptr(), self.len())
+    }
+}
+
+unsafe impl ReadBuffer for &'static mut [u8] {
+    fn read_buffer(&self) -> (*const u8, usize) {
+        (self.as_ptr(), self.len())
+    }
+}
+
+unsafe impl<const L: usize> ReadBuffer for &'static [u8; L] {
+    fn read_buffer(&self) -> (*const u8, usize) {
+        (self.as_ptr(), L)
+    }
+}
+
+unsafe impl<const L: usize> ReadBuffer for &'static mut [u8; L] {
+    fn read_buffer(&self) -> (*const u8, usize) {
+        (self.as_ptr(), L)
+    }
+}
+
+unsafe impl WriteBuffer for &'static mut [u8] {
+    fn write_buffer(&mut self) -> (*mut u8, usize) {
+        (self.as_mut_ptr(), self.len())
+    }
+}
+
+unsafe impl<const L: usize> WriteBuffer for &'static mut [u8; L] {
+    fn write_buffer(&mut self) -> (*mut u8, usize) {
+        (self.as_mut_ptr(), L)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    Free,
+    /// Waiting to start, with the order it was submitted in.
+    Queued(u32),
+    /// Running, with the bytes already transferred.
+    Running(usize),
+    /// Running, but its transfer was dropped.
+    Abandoned(usize),
+    Done(Result<(), PiError>),
+}
+
+struct Request {
+    state: State,
+    direction: Direction,
+    cart_addr: u32,
+    /// Physical RDRAM address.
+    dram: u32,
+    len: usize,
+    waker: Option<Waker>,
+}
+
+impl Request {
+    const FREE: Self = Self {
+        state: State::Free,
+        direction: Direction::FromCart,
+        cart_addr: 0,
+        dram: 0,
+        len: 0,
+        waker: None,
+    };
+}
+
+/// The request slots, in the order they were submitted.
+struct Requests<const N: usize> {
+    requests: [Request; N],
+    sequence: u32,
+}
+
+impl<const N: usize> Requests<N> {
+    const fn new() -> Self {
+        Self {
+            requests: [Request::FREE; N],
+            sequence: 0,
+        }
+    }
+
+    /// Queues a request in a free slot and returns its index.
+    fn insert(
+        &mut self,
+        direction: Direction,
+        cart_addr: u32,
+        dram: u32,
+        len: usize,
+    ) -> Result<usize, PiError> {
+        let sequence = self.sequence;
+        let (index, request) = self
+            .requests
+            .iter_mut()
+            .enumerate()
+            .find(|(_, request)| request.state == State::Free)
+            .ok_or(PiError::QueueFull)?;
+
+        *request = Request {
+            // Empty requests have nothing to wait for.
+            state: match len {
+                0 => State::Done(Ok(())),
+                _ => State::Queued(sequence),
+            },
+            direction,
+            cart_addr,
+            dram,
+            len,
+            waker: None,
+        };
+        self.sequence = sequence.wrapping_add(1);
+        Ok(index)
+    }
+
+    /// The request whose DMA is running and its index, if any.
+    fn running(&mut self) -> Option<(usize, &mut Request)> {
+        self.requests
+            .iter_mut()
+            .enumerate()
+            .find(|(_, request)| matches!(request.state, State::Running(_) | State::Abandoned(_)))
+    }
+
+    /// Marks the oldest queued request running, unless one already is, and
+    /// returns its index.
+    fn start_next(&mut self) -> Option<usize> {
+        if self.running().is_some() {
+            return None;
+        }
+
+        // Sequence numbers wrap, so age is measured from the next one.
+        let sequence = self.sequence;
+        let (index, request) = self
+            .requests
+            .iter_mut()
+            .enumerate()
+            .filter_map(|(index, request)| match request.state {
+                State::Queued(queued) => Some((sequence.wrapping_sub(queued), index, request)),
+                _ => None,
+            })
+            .max_by_key(|(age, _, _)| *age)
+            .map(|(_, index, request)| (index, request))?;
+
+        request.state = State::Running(0);
+        Some(index)
+    }
+
+    /// Records the end of the running DMA and returns the index and offset of
+    /// the next DMA to start: the rest of the same request, or the next one.
+    fn finish(&mut self, error: bool) -> Option<(usize, usize)> {
+        if let Some((index, request)) = self.running() {
+            let (done, abandoned) = match request.state {
+                State::Running(done) => (done, false),
+                State::Abandoned(done) => (done, true),
+                _ => unreachable!(),
+            };
+            let done = done + chunk(request.len - done);
+
+            if !error && done < request.len {
+                request.state = match abandoned {
+                    true => State::Abandoned(done),
+                    false => State::Running(done),
+                };
+                return Some((index, done));
+            }
+
+            request.state = match (abandoned, error) {
+                (true, _) => State::Free,
+                (false, true) => State::Done(Err(PiError::Transfer)),
+                (false, false) => State::Done(Ok(())),
+            };
+            if let Some(waker) = request.waker.take() {
+                waker.wake();
+            }
+        }
+
+        self.start_next().map(|index| (index, 0))
+    }
+
+    /// Takes the result of request `index` if it is done, otherwise
+    /// registers `waker`.
+    fn poll(&mut self, index: usize, waker: &Waker) -> Poll<Result<(), PiError>> {
+        let request = &mut self.requests[index];
+        match request.state {
+            State::Done(result) => {
+                request.state = State::Free;
+                Poll::Ready(result)
+            }
+            _ => {
+                request.waker = Some(waker.clone());
+                Poll::Pending
+            }
+        }
+    }
+
+    /// Forgets request `index`, letting a running DMA finish first.
+    fn cancel(&mut self, index: usize) {
+        let request = &mut self.requests[index];
+        request.waker = None;
+        request.state = match request.state {
+            State::Running(done) => State::Abandoned(done),
+            State::Abandoned(done) => State::Abandoned(done),
+            _ => State::Free,
+        };
+    }
+}
+
+struct Inner<const N: usize> {
+    pi: Option<Pi>,
+    requests: Requests<N>,
+}
+
+impl<const N: usize> Inner<N> {
+    /// Starts the next DMA, if there is one and the PI is taken over.
+    fn start(&mut self, next: Option<(usize, usize)>) {
+        if let (Some(pi), Some((index, done))) = (self.pi.as_mut(), next) {
+            unsafe { start(pi, &self.requests.requests[index], done) };
+        }
+    }
+}
+
+/// # PI DMA queue
+pub struct DmaQueue<const N: usize> {
+    inner: Mutex<RefCell<Inner<N>>>,
+}
+
+impl<const N: usize> DmaQueue<N> {
+    /// An empty queue, which does nothing until [`DmaQueue::start`].
+    pub const fn new() -> Self {
+        Self {
+            inner: Mutex::new(RefCell::new(Inner {
+                pi: None,
+                requests: Requests::new(),
+            })),
+        }
+    }
+
+    /// Takes over the PI, starts any requests queued before and routes the
+    /// PI interrupt to the CPU.
+    pub fn start(&self, pi: Pi, mi: &mut Mi) {
+        critical_section::with(|cs| {
+            let mut inner = self.inner.borrow_ref_mut(cs);
+            inner.pi = Some(pi);
+            let next = inner.requests.start_next().map(|index| (index, 0));
+            inner.start(next);
+        });
+        unsafe {
+            core::ptr::write_volatile(
+                &mut mi.mi_intr_mask_reg,
+                MiIntrMaskReg(0).with_set_pi_mask(true),
+            );
+        }
+    }
+
+    /// Queues a DMA from the cartridge bus at `cart_addr` into `buffer`.
+    ///
+    /// On error the buffer is handed back untouched.
+    pub fn read_from_cart<B: WriteBuffer>(
+        &self,
+        cart_addr: u32,
+        mut buffer: B,
+    ) -> Result<Transfer<'_, B, N>, (PiError, B)> {
+        let (ptr, len) = buffer.write_buffer();
+        match self.submit(Direction::FromCart, cart_addr, ptr, len) {
+            Ok(index) => Ok(Transfer {
+                queue: self,
+                index,
+                buffer: Some(buffer),
+            }),
+            Err(error) => Err((error, buffer)),
+        }
+    }
+
+    /// Queues a DMA from `buffer` to the cartridge bus at `cart_addr`.
+    ///
+    /// On error the buffer is handed back untouched.
+    pub fn write_to_cart<B: ReadBuffer>(
+        &self,
+        cart_addr: u32,
+        buffer: B,
+    ) -> Result<Transfer<'_, B, N>, (PiError, B)> {
+        let (ptr, len) = buffer.read_buffer();
+        match self.submit(Direction::ToCart, cart_addr, ptr, len) {
+            Ok(index) => Ok(Transfer {
+                queue: self,
+                index,
+                buffer: Some(buffer),
+            }),
+            Err(error) => Err((error, buffer)),
+        }
+    }
+
+    /// Handles the PI interrupt.
+    ///
+    /// Completes the running request, wakes its transfer and starts the next
+    /// one. Does nothing unless the MI reports a PI interrupt and the DMA
+    /// has stopped, so it can be called for every interrupt.
+    pub fn on_interrupt(&self, mi: &Mi) {
+        if !unsafe { read_volatile(&mi.mi_intr_reg) }.pi_intr() {
+            return;
+        }
+
+        critical_section::with(|cs| {
+            let mut inner = self.inner.borrow_ref_mut(cs);
+            let Some(pi) = inner.pi.as_mut() else {
+                return;
+            };
+
+            let status = unsafe { read_volatile(&pi.pi_status_reg) };
+            if status.dma_busy() {
+                return;
+            }
+            if status.error() {
+                pi.reset();
+            } else {
+                pi.acknowledge_interrupt();
+            }
+
+            let next = inner.requests.finish(status.error());
+            inner.start(next);
+        });
+    }
+
+    fn submit(
+        &self,
+        direction: Direction,
+        cart_addr: u32,
+        ptr: *const u8,
+        len: usize,
+    ) -> Result<usize, PiError> {
+        let dram = physical(ptr);
+        let align = match direction {
+            Direction::FromCart => DCACHE_LINE,
+            Direction::ToCart => PI_DMA_RDRAM_ALIGN,
+        };
+        if !(dram as usize).is_multiple_of(align)
+            || !len.is_multiple_of(align)
+            || !cart_addr.is_multiple_of(PI_DMA_CART_ALIGN)
+        {
+            return Err(PiError::Misaligned);
+        }
+        check_range(cart_addr, len)?;
+
+        match direction {
+            Direction::FromCart => writeback_invalidate_dcache(ptr, len),
+            Direction::ToCart => writeback_dcache(ptr, len),
+        }
+
+        critical_section::with(|cs| {
+            let mut inner = self.inner.borrow_ref_mut(cs);
+            let index = inner.requests.insert(direction, cart_addr, dram, len)?;
+            let next = inner.requests.start_next().map(|index| (index, 0));
+            inner.start(next);
+            Ok(index)
+        })
+    }
+
+    /// Polls request `index`, registering `waker` if it is not done.
+    fn poll(&self, cs: CriticalSection, index: usize, waker: &Waker) -> Poll<Result<(), PiError>> {
+        self.inner.borrow_ref_mut(cs).requests.poll(index, waker)
+    }
+
+    /// Forgets request `index`, letting a running DMA finish first.
+    fn cancel(&self, index: usize) {
+        critical_section::with(|cs| self.inner.borrow_ref_mut(cs).requests.cancel(index));
+    }
+}
+
+impl<const N: usize> Default for DmaQueue<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Length of the next DMA of a request with `remaining` bytes left.
+fn chunk(remaining: usize) -> usize {
+    remaining.min(PI_DMA_MAX_LEN)
+}
+
+/// Starts the DMA of `request` from byte `done`.
+///
+/// # Safety
+///
+/// The PI must be idle.
+unsafe fn start(pi: &mut Pi, request: &Request, done: usize) {
+    pi.start_dma(
+        request.direction,
+        request.cart_addr + done as u32,
+        request.dram + done as u32,
+        chunk(request.len - done),
+    );
+}
+
+/// # PI DMA transfer
+///
+/// Resolves to the result of the DMA and its buffer. Dropping it early lets
+/// the DMA finish but discards the buffer.
+pub struct Transfer<'q, B, const N: usize> {
+    queue: &'q DmaQueue<N>,
+    index: usize,
+    buffer: Option<B>,
+}
+
+impl<B, const N: usize> Transfer<'_, B, N> {
+    /// Whether the DMA has finished.
+    pub fn is_done(&self) -> bool {
+        self.buffer.is_none()
+            || critical_section::with(|cs| {
+                matches!(
+                    self.queue.inner.borrow_ref(cs).requests.requests[self.index].state,
+                    State::Done(_)
+                )
+            })
+    }
+}
+
+impl<B: Unpin, const N: usize> Future for Transfer<'_, B, N> {
+    type Output = (Result<(), PiError>, B);
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        // The slot is freed on completion and may belong to another request
+        // by now, so it must not be polled again.
+        assert!(self.buffer.is_some(), "transfer polled after completion");
+
+        let poll = critical_section::with(|cs| self.queue.poll(cs, self.index, cx.waker()));
+        match poll {
+            Poll::Ready(result) => {
+                let buffer = self.buffer.take().unwrap_or_else(|| unreachable!());
+                Poll::Ready((result, buffer))
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+impl<B, const N: usize> Drop for Transfer<'_, B, N> {
+    fn drop(&mut self) {
+        if self.buffer.is_some() {
+            self.queue.cancel(self.index);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        task::Wake,
+    };
+
+    use super::*;
+
+    struct CountingWaker(AtomicUsize);
+
+    impl Wake for CountingWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    fn insert<const N: usize>(requests: &mut Requests<N>, len: usize) -> usize {
+        requests
+            .insert(Direction::FromCart, 0x1000_0000, 0x1000, len)
+            .unwrap()
+    }
+
+    #[test]
+    fn runs_requests_in_order() {
+        let mut requests = Requests::<3>::new();
+        let first = insert(&mut requests, 16);
+        let second = insert(&mut requests, 16);
+        assert_eq!(requests.start_next(), Some(first));
+        assert_eq!(requests.start_next(), None);
+
+        // The slot freed by the first request is reused, but runs last.
+        assert_eq!(requests.finish(false), Some((second, 0)));
+        assert_eq!(requests.poll(first, Waker::noop()), Poll::Ready(Ok(())));
+        let third = insert(&mut requests, 16);
+        let fourth = insert(&mut requests, 16);
+        assert_eq!(third, first);
+
+        assert_eq!(requests.finish(false), Some((third, 0)));
+        assert_eq!(requests.finish(false), Some((fourth, 0)));
+        assert_eq!(requests.finish(false), None);
+        assert_eq!(
+            requests.insert(Direction::FromCart, 0, 0, 16),
+            Err(PiError::QueueFull)
+        );
+    }
+
+    #[test]
+    fn orders_across_sequence_wrap() {
+        let mut requests = Requests::<2>::new();
+        requests.sequence = u32::MAX;
+        let first = insert(&mut requests, 16);
+        let second = insert(&mut requests, 16);
+        assert_eq!(requests.start_next(), Some(first));
+        assert_eq!(requests.finish(false), Some((second, 0)));
+    }
+
+    #[test]
+    fn splits_long_requests() {
+        let mut requests = Requests::<2>::new();
+        let long = insert(&mut requests, PI_DMA_MAX_LEN * 2 + 16);
+        let short = insert(&mut requests, 16);
+        assert_eq!(requests.start_next(), Some(long));
+
+        assert_eq!(requests.finish(false), Some((long, PI_DMA_MAX_LEN)));
+        assert_eq!(requests.finish(false), Some((long, PI_DMA_MAX_LEN * 2)));
+        assert_eq!(requests.finish(false), Some((short, 0)));
+        assert_eq!(requests.poll(long, Waker::noop()), Poll::Ready(Ok(())));
+    }
+
+    #[test]
+    fn reports_errors() {
+        let mut requests = Requests::<2>::new();
+        let long = insert(&mut requests, PI_DMA_MAX_LEN * 2);
+        let short = insert(&mut requests, 16);
+        requests.start_next();
+
+        assert_eq!(requests.finish(true), Some((short, 0)));
+        assert_eq!(
+            requests.poll(long, Waker::noop()),
+            Poll::Ready(Err(PiError::Transfer))
+        );
+    }
+
+    #[test]
+    fn wakes_on_completion() {
+        let waker = Arc::new(CountingWaker(AtomicUsize::new(0)));
+        let mut requests = Requests::<1>::new();
+        let index = insert(&mut requests, 16);
+        requests.start_next();
+
+        assert_eq!(requests.poll(index, &waker.clone().into()), Poll::Pending);
+        assert_eq!(waker.0.load(Ordering::Relaxed), 0);
+        requests.finish(false);
+        assert_eq!(waker.0.load(Ordering::Relaxed), 1);
+        assert_eq!(requests.poll(index, Waker::noop()), Poll::Ready(Ok(())));
+        assert_eq!(requests.requests[index].state, State::Free);
+    }
+
+    #[test]
+    fn completes_empty_requests_at_once() {
+        let mut requests = Requests::<1>::new();
+        let index = insert(&mut requests, 0);
+        assert_eq!(requests.start_next(), None);
+        assert_eq!(requests.poll(index, Waker::noop()), Poll::Ready(Ok(())));
+    }
+
+    #[test]
+    fn cancels_queued_requests() {
+        let mut requests = Requests::<3>::new();
+        let first = insert(&mut requests, 16);
+        let second = insert(&mut requests, 16);
+        let third = insert(&mut requests, 16);
+        requests.start_next();
+
+        requests.cancel(second);
+        assert_eq!(requests.requests[second].state, State::Free);
+        assert_eq!(requests.finish(false), Some((third, 0)));
+        assert_eq!(requests.poll(first, Waker::noop()), Poll::Ready(Ok(())));
+    }
+
+    #[test]
+    fn abandons_running_requests() {
+        let waker = Arc::new(CountingWaker(AtomicUsize::new(0)));
+        let mut requests = Requests::<2>::new();
+        let long = insert(&mut requests, PI_DMA_MAX_LEN + 16);
+        let short = insert(&mut requests, 16);
+        requests.start_next();
+        assert_eq!(requests.poll(long, &waker.clone().into()), Poll::Pending);
+
+        // The slot stays taken until the DMA really stops.
+        requests.cancel(long);
+        assert_eq!(requests.requests[long].state, State::Abandoned(0));
+        assert_eq!(requests.start_next(), None);
+        assert_eq!(requests.finish(false), Some((long, PI_DMA_MAX_LEN)));
+        assert_eq!(
+            requests.requests[long].state,
+            State::Abandoned(PI_DMA_MAX_LEN)
+        );
+
+        assert_eq!(requests.finish(false), Some((short, 0)));
+        assert_eq!(requests.requests[long].state, State::Free);
+        assert_eq!(waker.0.load(Ordering::Relaxed), 0);
+    }
+
+    #[test]
+    fn repolled_transfers_leave_reused_slots_alone() {
+        let queue = DmaQueue::<1>::new();
+        let index = critical_section::with(|cs| {
+            let mut inner = queue.inner.borrow_ref_mut(cs);
+            let index = insert(&mut inner.requests, 0);
+            inner.requests.start_next();
+            index
+        });
+        let mut transfer = Transfer {
+            queue: &queue,
+            index,
+            buffer: Some(()),
+        };
+        let mut cx = Context::from_waker(Waker::noop());
+        assert!(matches!(
+            Pin::new(&mut transfer).poll(&mut cx),
+            Poll::Ready((Ok(()), ()))
+        ));
+
+        // Another request takes the freed slot and finishes.
+        critical_section::with(|cs| {
+            let mut inner = queue.inner.borrow_ref_mut(cs);
+            assert_eq!(insert(&mut inner.requests, 0), index);
+            inner.requests.requests[index].state = State::Done(Err(PiError::Misaligned));
+        });
+
+        let repoll = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            Pin::new(&mut transfer).poll(&mut cx)
+        }));
+        assert!(repoll.is_err());
+        critical_section::with(|cs| {
+            assert_eq!(
+                queue.inner.borrow_ref(cs).requests.requests[index].state,
+                State::Done(Err(PiError::Misaligned))
+            );
+        });
+    }
+}
@@ -0,0 +1,285 @@
+//! # Minimal runtime
+//!
+//! Provides `_start`, BSS clearing, stack setup and exception vector
+//! installation for programs booted by IPL3, handing control to the function
+//! named with [`entry!`](crate::entry) along with the [`Hardware`] singleton.
+//!
+//! Link with the bundled template by passing `-Tlink.x` to the linker. The
+//! stack starts at the top of RDRAM as reported by IPL3 in `osMemSize`.
+//!
+//! Every exception vector saves the full context into an [`ExceptionFrame`]
+//! on a dedicated exception stack and calls the handler declared with
+//! [`exception!`](crate::exception!), restoring the context and returning
+//! with `eret` if the handler returns. Without one, interrupts go to the
+//! handler declared with [`interrupt!`](crate::interrupt!) and any other
+//! exception calls [`crash`]. Without an interrupt handler, pending
+//! interrupt lines are masked in Status so they stop firing.
+//!
+//! There is a single exception stack holding a single frame, so exceptions
+//! do not nest: a handler must not re-enable interrupts or fault, as the
+//! nested exception would overwrite the frame in use.
+//!
+//! [`ExceptionFrame`]: crate::exception::ExceptionFrame
+//! [`crash`]: crate::exception::crash
+//! [`Hardware`]: crate::hardware::Hardware
+
+/// # Exception vectors
+///
+/// TLB refill, XTLB refill and general exception vectors, in KSEG0.
+pub const EXCEPTION_VECTORS: [u32; 3] = [0x8000_0000, 0x8000_0080, 0x8000_0180];
+
+/// Declares the program entry point.
+///
+/// The function must have the signature `fn(Hardware) -> !` and is called
+/// once the runtime has set up the stack, cleared BSS and installed the
+/// exception vectors.
+#[macro_export]
+macro_rules! entry {
+    ($path:path) => {
+        #[export_name = "__n64_main"]
+        pub unsafe extern "C" fn __n64_main() -> ! {
+            let main: fn($crate::hardware::Hardware) -> ! = $path;
+            match $crate::hardware::Hardware::take() {
+                Some(hardware) => main(hardware),
+                None => unreachable!(),
+            }
+        }
+    };
+}
+
+/// Declares the exception handler.
+///
+/// The function must have the signature `fn(&mut ExceptionFrame)`. It runs
+/// with interrupts disabled on the exception stack; if it returns, execution
+/// resumes at the frame's `epc` with the frame's registers. It receives
+/// interrupts too, and replaces the handler declared with
+/// [`interrupt!`](crate::interrupt!).
+#[macro_export]
+macro_rules! exception {
+    ($path:path) => {
+        #[export_name = "__n64_exception_handler"]
+        pub unsafe extern "C" fn __n64_exception_handler(
+            frame: &mut $crate::exception::ExceptionFrame,
+        ) {
+            let handler: fn(&mut $crate::exception::ExceptionFrame) = $path;
+            handler(frame)
+        }
+    };
+}
+
+/// Declares the interrupt handler.
+///
+/// The function must have the signature `fn(&mut ExceptionFrame)` and is
+/// called for every interrupt exception, like one declared with
+/// [`exception!`](crate::exception!). It must acknowledge the pending
+/// interrupts, e.g. by calling the drivers' `on_interrupt`, before returning.
+#[macro_export]
+macro_rules! interrupt {
+    ($path:path) => {
+        #[export_name = "__n64_interrupt_handler"]
+        pub unsafe extern "C" fn __n64_interrupt_handler(
+            frame: &mut $crate::exception::ExceptionFrame,
+        ) {
+            let handler: fn(&mut $crate::exception::ExceptionFrame) = $path;
+            handler(frame)
+        }
+    };
+}
+
+/// # Exception stack size
+pub const EXCEPTION_STACK_SIZE: usize = 8 * 1024;
+
+/// Encodes the jump placed at each exception vector.
+///
+/// ```text
+/// lui   $k0, %hi(handler)
+/// addiu $k0, $k0, %lo(handler)
+/// jr    $k0
+/// nop
+/// ```
+pub const fn trampoline(handler: u32) -> [u32; 4] {
+    let hi = (handler.wrapping_add(0x8000) >> 16) & 0xFFFF;
+    let lo = handler & 0xFFFF;
+    [0x3C1A_0000 | hi, 0x275A_0000 | lo, 0x0340_0008, 0x0000_0000]
+}
+
+#[cfg(target_arch = "mips")]
+core::arch::global_asm!(
+    ".section .text.entry, \"ax\"",
+    ".global _start",
+    ".set push",
+    ".set noreorder",
+    "_start:",
+    // Stack at the top of RDRAM, as reported by IPL3.
+    "    lui   $t0, 0x8000",
+    "    lw    $t1, 0x318($t0)",
+    "    addu  $sp, $t0, $t1",
+    "    addiu $sp, $sp, -16",
+    "    la    $gp, _gp",
+    // Enable the FPU.
+    "    mfc0  $t0, $12",
+    "    lui   $t1, 0x2000",
+    "    or    $t0, $t0, $t1",
+    "    mtc0  $t0, $12",
+    // Clear BSS.
+    "    la    $t0, __bss_start",
+    "    la    $t1, __bss_end",
+    "1:  beq   $t0, $t1, 2f",
+    "    nop",
+    "    sw    $zero, 0($t0)",
+    "    b     1b",
+    "    addiu $t0, $t0, 4",
+    "2:  jal   __n64_install_vectors",
+    "    nop",
+    "    jal   __n64_main",
+    "    nop",
+    "3:  b     3b",
+    "    nop",
+    ".set pop",
+);
+
+#[cfg(target_arch = "mips")]
+static mut EXCEPTION_STACK: [u64; EXCEPTION_STACK_SIZE / 8] = [0; EXCEPTION_STACK_SIZE / 8];
+
+#[cfg(target_arch = "mips")]
+macro_rules! exception_entry {
+    ($($gpr:literal)*; $($fpr:literal)*; $($pair:literal)*) => {
+        core::arch::global_asm!(
+            ".section .text.__n64_exception, \"ax\"",
+            ".global __n64_exception",
+            ".set push",
+            ".set noreorder",
+            ".set noat",
+            ".set hardfloat",
+            ".set fp=64",
+            "__n64_exception:",
+            "    la    $k0, {stack} + {stack_size} - {frame_size}",
+            $(concat!("    sw    $", $gpr, ", ", $gpr, " * 4($k0)"),)*
+            "    mfhi  $t0",
+            "    sw    $t0, 128($k0)",
+            "    mflo  $t0",
+            "    sw    $t0, 132($k0)",
+            "    mfc0  $t0, $12",
+            "    sw    $t0, 136($k0)",
+            "    mfc0  $t1, $13",
+            "    sw    $t1, 140($k0)",
+            "    mfc0  $t1, $14",
+            "    sw    $t1, 144($k0)",
+            "    mfc0  $t1, $8",
+            "    sw    $t1, 148($k0)",
+            // Only touch the FPU when CP1 is usable.
+            "    lui   $t1, 0x2000",
+            "    and   $t0, $t0, $t1",
+            "    beqz  $t0, 1f",
+            "    nop",
+            "    cfc1  $t0, $31",
+            "    sw    $t0, 152($k0)",
+            // Status.FR selects 32 64-bit registers over 16 even/odd pairs.
+            "    lw    $t0, 136($k0)",
+            "    lui   $t1, 0x0400",
+            "    and   $t0, $t0, $t1",
+            "    beqz  $t0, 3f",
+            "    nop",
+            $(concat!("    sdc1  $f", $fpr, ", 160 + ", $fpr, " * 8($k0)"),)*
+            "    b     1f",
+            "    nop",
+            "3:",
+            $(concat!("    sdc1  $f", $pair, ", 160 + ", $pair, " * 8($k0)"),)*
+            "1:  move  $a0, $k0",
+            "    addiu $sp, $k0, -16",
+            "    jal   __n64_exception_handler",
+            "    nop",
+            "    la    $k0, {stack} + {stack_size} - {frame_size}",
+            "    lw    $t0, 136($k0)",
+            "    lui   $t1, 0x2000",
+            "    and   $t0, $t0, $t1",
+            "    beqz  $t0, 2f",
+            "    nop",
+            "    lw    $t0, 152($k0)",
+            "    ctc1  $t0, $31",
+            "    lw    $t0, 136($k0)",
+            "    lui   $t1, 0x0400",
+            "    and   $t0, $t0, $t1",
+            "    beqz  $t0, 4f",
+            "    nop",
+            $(concat!("    ldc1  $f", $fpr, ", 160 + ", $fpr, " * 8($k0)"),)*
+            "    b     2f",
+            "    nop",
+            "4:",
+            $(concat!("    ldc1  $f", $pair, ", 160 + ", $pair, " * 8($k0)"),)*
+            "2:  lw    $t0, 144($k0)",
+            "    mtc0  $t0, $14",
+            "    lw    $t0, 128($k0)",
+            "    mthi  $t0",
+            "    lw    $t0, 132($k0)",
+            "    mtlo  $t0",
+            $(concat!("    lw    $", $gpr, ", ", $gpr, " * 4($k0)"),)*
+            "    nop",
+            "    eret",
+            ".set pop",
+            stack = sym EXCEPTION_STACK,
+            stack_size = const EXCEPTION_STACK_SIZE,
+            frame_size = const crate::exception::EXCEPTION_FRAME_SIZE,
+        );
+    };
+}
+
+#[cfg(target_arch = "mips")]
+exception_entry!(
+    "1" "2" "3" "4" "5" "6" "7" "8" "9" "10" "11" "12" "13" "14" "15" "16"
+    "17" "18" "19" "20" "21" "22" "23" "24" "25" "28" "29" "30" "31";
+    "0" "1" "2" "3" "4" "5" "6" "7" "8" "9" "10" "11" "12" "13" "14" "15"
+    "16" "17" "18" "19" "20" "21" "22" "23" "24" "25" "26" "27" "28" "29" "30" "31";
+    "0" "2" "4" "6" "8" "10" "12" "14" "16" "18" "20" "22" "24" "26" "28" "30"
+);
+
+#[cfg(target_arch = "mips")]
+#[no_mangle]
+unsafe extern "C" fn __n64_default_exception_handler(frame: &mut crate::exception::ExceptionFrame) {
+    extern "C" {
+        fn __n64_interrupt_handler(frame: &mut crate::exception::ExceptionFrame);
+    }
+
+    match frame.exc_code() {
+        crate::cp0::ExcCode::Interrupt => __n64_interrupt_handler(frame),
+        _ => crate::exception::crash(frame),
+    }
+}
+
+#[cfg(target_arch = "mips")]
+#[no_mangle]
+unsafe extern "C" fn __n64_default_interrupt_handler(frame: &mut crate::exception::ExceptionFrame) {
+    use crate::cp0::{self, InterruptMask, Status};
+
+    // Nothing acknowledges these lines, so returning with them unmasked
+    // would take the same interrupt again straight away.
+    let pending = frame.cause().interrupt_pending().0;
+    let status = Status(cp0::status());
+    let mask = InterruptMask(status.interrupt_mask().0 & !pending);
+    cp0::set_status(status.with_interrupt_mask(mask).raw());
+}
+
+#[cfg(target_arch = "mips")]
+#[no_mangle]
+unsafe extern "C" fn __n64_install_vectors() {
+    extern "C" {
+        fn __n64_exception();
+    }
+
+    let code = trampoline(__n64_exception as *const () as u32);
+    for vector in EXCEPTION_VECTORS {
+        // Write through KSEG1 so nothing lingers in the data cache, then drop
+        // any stale instruction cache line for the vector.
+        let uncached = (vector | 0xA000_0000) as *mut u32;
+        for (offset, word) in code.iter().enumerate() {
+            core::ptr::write_volatile(uncached.add(offset), *word);
+        }
+        core::arch::asm!(
+            ".set noat",
+            "cache 0x10, 0({0})",
+            ".set at",
+            in(reg) vector,
+            options(nostack),
+        );
+    }
+}
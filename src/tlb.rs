@@ -0,0 +1,414 @@
+//! # Translation lookaside buffer (TLB)
+//!
+//! The VR4300 has 32 TLB entries, each mapping an even/odd pair of pages
+//! that share a virtual page number (VPN2) and a page size.
+
+use proc_bitfield::bitfield;
+
+use crate::{cp0, enums, fields};
+
+/// # Number of TLB entries
+pub const TLB_ENTRY_COUNT: u8 = 32;
+
+/// # RDRAM size
+///
+/// Upper bound of the physical range that [`Entry::map`] accepts, covering an
+/// Expansion Pak.
+pub const RDRAM_SIZE: u32 = 0x0080_0000;
+
+bitfield! {
+    /// # Index register
+    #[derive(Clone, Copy, PartialEq, Eq)]
+    pub struct Index(pub u32): Debug {
+        pub raw: u32 @ ..,
+        pub index: u8 [EntryIndex] @ 0..5,
+        pub probe_failure: bool [read_only] @ 31,
+    }
+}
+
+bitfield! {
+    /// # EntryHi register
+    #[derive(Clone, Copy, PartialEq, Eq)]
+    pub struct EntryHi(pub u32): Debug {
+        pub raw: u32 @ ..,
+        pub asid: u8 [Asid] @ 0..8,
+        pub vpn2: u32 [Vpn2] @ 13..32,
+    }
+}
+
+bitfield! {
+    /// # EntryLo register
+    #[derive(Clone, Copy, PartialEq, Eq)]
+    pub struct EntryLo(pub u32): Debug {
+        pub raw: u32 @ ..,
+        pub global: bool @ 0,
+        pub valid: bool @ 1,
+        pub dirty: bool @ 2,
+        pub cache_algorithm: u8 [CacheAlgorithm] @ 3..6,
+        pub pfn: u32 [Pfn] @ 6..26,
+    }
+}
+
+bitfield! {
+    /// # PageMask register
+    #[derive(Clone, Copy, PartialEq, Eq)]
+    pub struct PageMask(pub u32): Debug {
+        pub raw: u32 @ ..,
+        pub mask: u16 @ 13..25,
+    }
+}
+
+fields! [
+    /// # Address space ID
+    u8 => Asid,
+
+    /// # TLB entry index
+    ux::u5 => EntryIndex,
+
+    /// # Page frame number
+    ux::u20 => Pfn,
+
+    /// # Virtual page number (divided by two)
+    ux::u19 => Vpn2,
+];
+
+enums! [
+    /// # Cache algorithm
+    ///
+    /// The VR4300 only defines uncached and cacheable; the other encodings
+    /// are reserved but can still be read back from the TLB.
+    u8 => CacheAlgorithm {
+        0 => Reserved0,
+        1 => Reserved1,
+        2 => Uncached,
+        3 => Cacheable,
+        4 => Reserved4,
+        5 => Reserved5,
+        6 => Reserved6,
+        7 => Reserved7,
+    },
+];
+
+/// # Page size
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PageSize {
+    FourKilobytes,
+    SixteenKilobytes,
+    SixtyFourKilobytes,
+    TwoHundredFiftySixKilobytes,
+    OneMegabyte,
+    FourMegabytes,
+    SixteenMegabytes,
+}
+
+impl PageSize {
+    /// Every page size, smallest first.
+    pub const ALL: [PageSize; 7] = [
+        PageSize::FourKilobytes,
+        PageSize::SixteenKilobytes,
+        PageSize::SixtyFourKilobytes,
+        PageSize::TwoHundredFiftySixKilobytes,
+        PageSize::OneMegabyte,
+        PageSize::FourMegabytes,
+        PageSize::SixteenMegabytes,
+    ];
+
+    /// Size of a single page in bytes.
+    pub const fn bytes(self) -> u32 {
+        match self {
+            PageSize::FourKilobytes => 0x1000,
+            PageSize::SixteenKilobytes => 0x4000,
+            PageSize::SixtyFourKilobytes => 0x1_0000,
+            PageSize::TwoHundredFiftySixKilobytes => 0x4_0000,
+            PageSize::OneMegabyte => 0x10_0000,
+            PageSize::FourMegabytes => 0x40_0000,
+            PageSize::SixteenMegabytes => 0x100_0000,
+        }
+    }
+
+    /// PageMask mask bits selecting this page size.
+    pub const fn mask(self) -> u16 {
+        match self {
+            PageSize::FourKilobytes => 0x000,
+            PageSize::SixteenKilobytes => 0x003,
+            PageSize::SixtyFourKilobytes => 0x00F,
+            PageSize::TwoHundredFiftySixKilobytes => 0x03F,
+            PageSize::OneMegabyte => 0x0FF,
+            PageSize::FourMegabytes => 0x3FF,
+            PageSize::SixteenMegabytes => 0xFFF,
+        }
+    }
+
+    /// The page size selected by PageMask mask bits, if they select one.
+    pub fn from_mask(mask: u16) -> Option<Self> {
+        PageSize::ALL.into_iter().find(|size| size.mask() == mask)
+    }
+}
+
+impl EntryHi {
+    /// Builds an EntryHi matching the page pair containing `virt`.
+    pub fn new(virt: u32, asid: u8) -> Self {
+        Self(0)
+            .with_vpn2(Vpn2::from(virt >> 13))
+            .with_asid(Asid(asid))
+    }
+}
+
+impl EntryLo {
+    /// An EntryLo that maps nothing.
+    ///
+    /// The global bit is still set: the VR4300 only treats an entry as
+    /// global when both halves are, so the unused half of a global entry must
+    /// keep it.
+    pub const INVALID: EntryLo = EntryLo(1);
+
+    /// Builds a valid, global EntryLo for the page starting at `phys`.
+    pub fn new(phys: u32, cache_algorithm: CacheAlgorithm, writable: bool) -> Self {
+        Self(0)
+            .with_pfn(Pfn::from(phys >> 12))
+            .with_cache_algorithm(cache_algorithm)
+            .with_dirty(writable)
+            .with_valid(true)
+            .with_global(true)
+    }
+}
+
+impl PageMask {
+    /// Builds a PageMask for the given page size.
+    pub fn new(size: PageSize) -> Self {
+        Self(0).with_mask(size.mask())
+    }
+
+    /// The page size, or `None` for a mask that selects no supported size.
+    pub fn size(&self) -> Option<PageSize> {
+        PageSize::from_mask(self.mask())
+    }
+}
+
+/// # Mapping error
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MapError {
+    /// The entry index is not below [`TLB_ENTRY_COUNT`].
+    InvalidIndex,
+
+    /// The length is zero, not a multiple of 4 KB or larger than 32 MB.
+    InvalidLength,
+
+    /// The virtual address lies in an unmapped segment (KSEG0/KSEG1).
+    UnmappedSegment,
+
+    /// An address is not aligned to the chosen page size.
+    Misaligned,
+
+    /// The physical range extends past the end of RDRAM.
+    OutOfRange,
+}
+
+/// # TLB entry
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Entry {
+    pub page_mask: PageMask,
+    pub entry_hi: EntryHi,
+    pub entry_lo0: EntryLo,
+    pub entry_lo1: EntryLo,
+}
+
+impl Entry {
+    /// An entry that never matches, parked at a unique KSEG0 address.
+    ///
+    /// KSEG0 bypasses the TLB, so giving each slot a distinct VPN2 there keeps
+    /// unused entries from ever producing a duplicate match.
+    pub fn invalid(index: u8) -> Self {
+        Self {
+            page_mask: PageMask::new(PageSize::FourKilobytes),
+            entry_hi: EntryHi::new(0x8000_0000 + u32::from(index) * 0x2000, 0),
+            entry_lo0: EntryLo::INVALID,
+            entry_lo1: EntryLo::INVALID,
+        }
+    }
+
+    /// Maps `kilobytes` KB of RDRAM at `phys` to the virtual address `virt`.
+    ///
+    /// The smallest page size whose even/odd pair covers the range is used,
+    /// and the range is rounded up to that page size. `virt` must be aligned
+    /// to twice the page size and `phys` to the page size.
+    pub fn map(virt: u32, phys: u32, kilobytes: u32) -> Result<Self, MapError> {
+        if kilobytes == 0 || !kilobytes.is_multiple_of(4) {
+            return Err(MapError::InvalidLength);
+        }
+
+        let length = kilobytes.checked_mul(1024).ok_or(MapError::InvalidLength)?;
+        let size = PageSize::ALL
+            .into_iter()
+            .find(|size| length <= size.bytes() * 2)
+            .ok_or(MapError::InvalidLength)?;
+        let page = size.bytes();
+
+        if (0x8000_0000..0xC000_0000).contains(&virt) {
+            return Err(MapError::UnmappedSegment);
+        }
+
+        if !virt.is_multiple_of(page * 2) || !phys.is_multiple_of(page) {
+            return Err(MapError::Misaligned);
+        }
+
+        let pages = if length > page { 2 } else { 1 };
+        if u64::from(phys) + u64::from(pages * page) > u64::from(RDRAM_SIZE) {
+            return Err(MapError::OutOfRange);
+        }
+
+        let entry_lo0 = EntryLo::new(phys, CacheAlgorithm::Cacheable, true);
+        let entry_lo1 = match pages {
+            2 => EntryLo::new(phys + page, CacheAlgorithm::Cacheable, true),
+            _ => EntryLo::INVALID,
+        };
+
+        Ok(Self {
+            page_mask: PageMask::new(size),
+            entry_hi: EntryHi::new(virt, 0),
+            entry_lo0,
+            entry_lo1,
+        })
+    }
+
+    /// Makes the 4 KB page at `virt` fault on any access.
+    ///
+    /// The entry matches the whole 8 KB page pair containing `virt`; the
+    /// neighbouring page stays invalid too, so place guards in an otherwise
+    /// unused pair (e.g. directly below a stack).
+    pub fn guard(virt: u32) -> Result<Self, MapError> {
+        if (0x8000_0000..0xC000_0000).contains(&virt) {
+            return Err(MapError::UnmappedSegment);
+        }
+
+        if !virt.is_multiple_of(PageSize::FourKilobytes.bytes()) {
+            return Err(MapError::Misaligned);
+        }
+
+        Ok(Self {
+            page_mask: PageMask::new(PageSize::FourKilobytes),
+            entry_hi: EntryHi::new(virt, 0),
+            entry_lo0: EntryLo::INVALID,
+            entry_lo1: EntryLo::INVALID,
+        })
+    }
+}
+
+/// Writes `entry` into the TLB slot `index`.
+///
+/// # Safety
+///
+/// Changes the virtual memory map. The caller must not create overlapping
+/// entries and must not be interrupted by code that also touches the TLB.
+pub unsafe fn write_indexed(index: u8, entry: &Entry) {
+    let entry_hi = cp0::entry_hi();
+    load(entry);
+    cp0::set_index(u32::from(index));
+    cp0::tlbwi();
+    cp0::set_entry_hi(entry_hi);
+}
+
+/// Writes `entry` into a pseudo-random unwired TLB slot.
+///
+/// # Safety
+///
+/// See [`write_indexed`].
+pub unsafe fn write_random(entry: &Entry) {
+    let entry_hi = cp0::entry_hi();
+    load(entry);
+    cp0::tlbwr();
+    cp0::set_entry_hi(entry_hi);
+}
+
+/// Returns the index of the TLB entry matching `entry_hi`, if any.
+pub fn probe(entry_hi: EntryHi) -> Option<u8> {
+    unsafe {
+        let saved = cp0::entry_hi();
+        cp0::set_entry_hi(entry_hi.raw());
+        cp0::tlbp();
+        let index = Index(cp0::index());
+        cp0::set_entry_hi(saved);
+
+        match index.probe_failure() {
+            true => None,
+            false => Some(index.index().into()),
+        }
+    }
+}
+
+/// Reads the TLB entry at `index`.
+pub fn read(index: u8) -> Entry {
+    unsafe {
+        let saved = cp0::entry_hi();
+        cp0::set_index(u32::from(index));
+        cp0::tlbr();
+        let entry = Entry {
+            page_mask: PageMask(cp0::page_mask()),
+            entry_hi: EntryHi(cp0::entry_hi()),
+            entry_lo0: EntryLo(cp0::entry_lo0()),
+            entry_lo1: EntryLo(cp0::entry_lo1()),
+        };
+        cp0::set_entry_hi(saved);
+        entry
+    }
+}
+
+/// Maps `kilobytes` KB of RDRAM at `phys` to `virt` using TLB slot `index`.
+///
+/// # Safety
+///
+/// See [`write_indexed`]. Any live references into the previous mapping of
+/// `index` are invalidated.
+pub unsafe fn map(index: u8, virt: u32, phys: u32, kilobytes: u32) -> Result<(), MapError> {
+    check_index(index)?;
+    write_indexed(index, &Entry::map(virt, phys, kilobytes)?);
+    Ok(())
+}
+
+/// Removes whatever TLB slot `index` maps.
+///
+/// # Safety
+///
+/// See [`map`].
+pub unsafe fn unmap(index: u8) -> Result<(), MapError> {
+    check_index(index)?;
+    write_indexed(index, &Entry::invalid(index));
+    Ok(())
+}
+
+/// Installs a guard page at `virt` using TLB slot `index`.
+///
+/// # Safety
+///
+/// See [`map`].
+pub unsafe fn guard_page(index: u8, virt: u32) -> Result<(), MapError> {
+    check_index(index)?;
+    write_indexed(index, &Entry::guard(virt)?);
+    Ok(())
+}
+
+/// Invalidates every TLB entry and clears the wired count.
+///
+/// # Safety
+///
+/// See [`map`].
+pub unsafe fn clear() {
+    cp0::set_wired(0);
+    for index in 0..TLB_ENTRY_COUNT {
+        write_indexed(index, &Entry::invalid(index));
+    }
+}
+
+unsafe fn load(entry: &Entry) {
+    cp0::set_page_mask(entry.page_mask.raw());
+    cp0::set_entry_hi(entry.entry_hi.raw());
+    cp0::set_entry_lo0(entry.entry_lo0.raw());
+    cp0::set_entry_lo1(entry.entry_lo1.raw());
+}
+
+fn check_index(index: u8) -> Result<(), MapError> {
+    match index < TLB_ENTRY_COUNT {
+        true => Ok(()),
+        false => Err(MapError::InvalidIndex),
+    }
+}
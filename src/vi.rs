@@ -4,6 +4,21 @@ use proc_bitfield::bitfield;
 
 use crate::{enums, fields, registers};
 
+pub mod buffers;
+pub mod capture;
+pub mod config;
+pub mod console;
+pub mod emulation;
+pub mod filters;
+pub mod framebuffer;
+#[cfg(feature = "embedded-graphics")]
+pub mod graphics;
+pub mod mode;
+pub mod patterns;
+pub mod pixel;
+pub mod raster;
+pub mod timing;
+
 /// # VI base address
 pub const VI_BASE_ADDR: u32 = 0x0440_0000;
 
@@ -56,19 +71,26 @@ registers! {
 
 bitfield! {
     /// # VI status register
+    #[derive(Clone, Copy, PartialEq, Eq)]
     pub struct ViStatusReg(pub u32): Debug {
         pub raw: u32 @ ..,
         pub pixel_size: u8 [PixelSize] @ 0..2,
         pub gamma_dither_enable: bool @ 2,
         pub gamma_enable: bool @ 3,
         pub divot_enable: bool @ 4,
+        pub vbus_clock_enable: bool @ 5,
         pub serrate: bool @ 6,
+        pub test_mode: bool @ 7,
         pub antialias_mode: u8 [AntialiasMode] @ 8..10,
+        pub kill_we: bool @ 11,
+        pub pixel_advance: u8 [PixelAdvance] @ 12..16,
+        pub dither_filter_enable: bool @ 16,
     }
 }
 
 bitfield! {
     /// # VI frame buffer origin register
+    #[derive(Clone, Copy, PartialEq, Eq)]
     pub struct ViOriginReg(pub u32): Debug {
         pub raw: u32 @ ..,
         pub frame_buffer_origin: u32 [RdramAddress] @ 0..24,
@@ -77,6 +99,7 @@ bitfield! {
 
 bitfield! {
     /// # VI frame buffer line width register
+    #[derive(Clone, Copy, PartialEq, Eq)]
     pub struct ViWidthReg(pub u32): Debug {
         pub raw: u32 @ ..,
         pub frame_buffer_line_width: u16 [LineWidth] @ 0..12,
@@ -85,6 +108,7 @@ bitfield! {
 
 bitfield! {
     /// # VI vertical interrupt register
+    #[derive(Clone, Copy, PartialEq, Eq)]
     pub struct ViIntrReg(pub u32): Debug {
         pub raw: u32 @ ..,
         pub intr_half_line: u16 [HalflineIndex] @ 0..10,
@@ -93,6 +117,7 @@ bitfield! {
 
 bitfield! {
     /// # VI current vertical line register
+    #[derive(Clone, Copy, PartialEq, Eq)]
     pub struct ViCurrentReg(pub u32): Debug {
         pub raw: u32 @ ..,
         pub current_half_line: u16 [HalflineIndex] @ 0..10,
@@ -101,6 +126,7 @@ bitfield! {
 
 bitfield! {
     /// # VI timing register
+    #[derive(Clone, Copy, PartialEq, Eq)]
     pub struct ViTimingReg(pub u32): Debug {
         pub raw: u32 @ ..,
         pub horizontal_sync_width: u8 [PixelWidth] @ 0..8,
@@ -112,6 +138,7 @@ bitfield! {
 
 bitfield! {
     /// # VI vertical sync register
+    #[derive(Clone, Copy, PartialEq, Eq)]
     pub struct ViVSyncReg(pub u32): Debug {
         pub raw: u32 @ ..,
         pub half_lines_per_field: u16 [HalflineIndex] @ 0..10,
@@ -120,6 +147,7 @@ bitfield! {
 
 bitfield! {
     /// # VI horizontal sync register
+    #[derive(Clone, Copy, PartialEq, Eq)]
     pub struct ViHSyncReg(pub u32): Debug {
         pub raw: u32 @ ..,
         pub line_duration: u16 [LineDuration] @ 0..12,
@@ -129,6 +157,7 @@ bitfield! {
 
 bitfield! {
     /// # VI horizontal sync leap register
+    #[derive(Clone, Copy, PartialEq, Eq)]
     pub struct ViHSyncLeapReg(pub u32): Debug {
         pub raw: u32 @ ..,
         pub h_sync_period_0: u16 [LineDuration] @ 0..12,
@@ -138,6 +167,7 @@ bitfield! {
 
 bitfield! {
     /// # VI horizontal video register
+    #[derive(Clone, Copy, PartialEq, Eq)]
     pub struct ViHVideoReg(pub u32): Debug {
         pub raw: u32 @ ..,
         pub end_active_video: u16 [PixelIndex] @ 0..10,
@@ -147,6 +177,7 @@ bitfield! {
 
 bitfield! {
     /// # VI vertical video register
+    #[derive(Clone, Copy, PartialEq, Eq)]
     pub struct ViVVideoReg(pub u32): Debug {
         pub raw: u32 @ ..,
         pub end_active_video: u16 [HalflineIndex] @ 0..10,
@@ -156,6 +187,7 @@ bitfield! {
 
 bitfield! {
     /// # VI vertical burst register
+    #[derive(Clone, Copy, PartialEq, Eq)]
     pub struct ViVBurstReg(pub u32): Debug {
         pub raw: u32 @ ..,
         pub end_color_burst: u16 [HalflineIndex] @ 0..10,
@@ -165,6 +197,7 @@ bitfield! {
 
 bitfield! {
     /// # VI X-scale register
+    #[derive(Clone, Copy, PartialEq, Eq)]
     pub struct ViXScaleReg(pub u32): Debug {
         pub raw: u32 @ ..,
         pub inverse_scale_factor: u16 [InverseScaleFactor] @ 0..12,
@@ -174,6 +207,7 @@ bitfield! {
 
 bitfield! {
     /// # VI Y-scale register
+    #[derive(Clone, Copy, PartialEq, Eq)]
     pub struct ViYScaleReg(pub u32): Debug {
         pub raw: u32 @ ..,
         pub inverse_scale_factor: u16 [InverseScaleFactor] @ 0..12,
@@ -200,6 +234,9 @@ fields! [
     /// # Line duration
     ux::u12 => LineDuration,
 
+    /// # Pixel advance
+    ux::u4 => PixelAdvance,
+
     /// # Pixel Index
     ux::u10 => PixelIndex,
 
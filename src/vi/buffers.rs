@@ -0,0 +1,404 @@
+//! # Framebuffer management
+//!
+//! [`FrameBuffers`] cycles the VI through `N` framebuffers carved out of a
+//! block of RDRAM. One buffer is displayed, at most one is queued for display
+//! and the rest can be acquired for drawing. Queued buffers are only swapped
+//! in from the VI interrupt, during vertical blank, so a frame is never shown
+//! half drawn. Interlaced modes swap on even fields only, so both fields of a
+//! frame come from the same buffer.
+//!
+//! On the console, buffers are handed out through KSEG1 so drawing bypasses
+//! the data cache and needs no write back before presenting.
+//!
+//! Once its interrupt is enabled, [`FrameBuffers`] owns the VI interrupt
+//! line. Raster interrupts from a [`RasterScheduler`] are interleaved with
+//! its own through [`FrameBuffers::on_raster_interrupt`].
+
+use core::{cell::RefCell, ptr::write_volatile};
+
+use critical_section::Mutex;
+
+use crate::{
+    memory::{physical, uncached, writeback_invalidate_dcache},
+    mi::{Mi, MiIntrMaskReg},
+};
+
+use super::{mode::VideoMode, raster::RasterScheduler, Vi, ViIntrReg};
+
+/// # Framebuffer alignment
+///
+/// Alignment of each buffer in bytes, as required by the RDP.
+pub const FRAMEBUFFER_ALIGN: usize = 64;
+
+/// # Framebuffer error
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameBuffersError {
+    /// Fewer than two buffers were requested.
+    TooFewBuffers,
+
+    /// The mode's pixel size is blank or the reserved value 1.
+    InvalidPixelSize,
+
+    /// The memory block cannot hold every buffer.
+    OutOfMemory,
+}
+
+/// # Acquired framebuffer
+///
+/// A buffer handed out by [`FrameBuffers::acquire`], to be returned with
+/// [`FrameBuffers::present`] or [`FrameBuffers::discard`].
+#[derive(Debug, PartialEq, Eq)]
+pub struct Frame {
+    index: usize,
+    ptr: *mut u8,
+    len: usize,
+}
+
+impl Frame {
+    /// Index of the buffer.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Physical RDRAM address of the buffer.
+    pub fn address(&self) -> u32 {
+        physical(self.ptr)
+    }
+
+    /// Contents of the buffer.
+    pub fn as_bytes(&self) -> &[u8] {
+        unsafe { core::slice::from_raw_parts(self.ptr, self.len) }
+    }
+
+    /// Mutable contents of the buffer.
+    pub fn as_bytes_mut(&mut self) -> &mut [u8] {
+        unsafe { core::slice::from_raw_parts_mut(self.ptr, self.len) }
+    }
+}
+
+/// # Framebuffer manager
+///
+/// Shared between the main loop and the VI interrupt handler, so its state is
+/// only touched inside critical sections.
+pub struct FrameBuffers<const N: usize> {
+    mode: VideoMode,
+    buffers: [*mut u8; N],
+    len: usize,
+    inner: Mutex<RefCell<Inner<N>>>,
+}
+
+struct Inner<const N: usize> {
+    vi: Vi,
+    slots: Slots<N>,
+    /// Half-line the VI interrupt is programmed for.
+    armed: u32,
+}
+
+/// Which buffers are displayed, queued and acquired.
+struct Slots<const N: usize> {
+    displayed: usize,
+    queued: Option<usize>,
+    acquired: [bool; N],
+}
+
+impl<const N: usize> Slots<N> {
+    const fn new() -> Self {
+        Self {
+            displayed: 0,
+            queued: None,
+            acquired: [false; N],
+        }
+    }
+
+    fn acquire(&mut self) -> Option<usize> {
+        let index = (0..N).find(|&index| {
+            index != self.displayed && Some(index) != self.queued && !self.acquired[index]
+        })?;
+        self.acquired[index] = true;
+        Some(index)
+    }
+
+    fn present(&mut self, index: usize) {
+        self.acquired[index] = false;
+        self.queued = Some(index);
+    }
+
+    fn discard(&mut self, index: usize) {
+        self.acquired[index] = false;
+    }
+
+    /// Swaps in the queued buffer at the start of `field`.
+    fn vblank(&mut self, field: usize) {
+        if let Some(queued) = self.queued.filter(|_| field == 0) {
+            self.displayed = queued;
+            self.queued = None;
+        }
+    }
+}
+
+// The buffers are owned exclusively through the `'static` borrow, and each
+// is only handed out to one `Frame` at a time.
+unsafe impl<const N: usize> Send for FrameBuffers<N> {}
+unsafe impl<const N: usize> Sync for FrameBuffers<N> {}
+
+impl<const N: usize> FrameBuffers<N> {
+    /// Splits `memory` into `N` buffers for `mode` and starts displaying the
+    /// first one.
+    ///
+    /// `memory` is written back and invalidated in the data cache first, so
+    /// no stale line can later be written back over the uncached drawing.
+    pub fn new(
+        mut vi: Vi,
+        mode: VideoMode,
+        memory: &'static mut [u8],
+    ) -> Result<Self, FrameBuffersError> {
+        if N < 2 {
+            return Err(FrameBuffersError::TooFewBuffers);
+        }
+
+        let len = (mode.framebuffer_width() * mode.framebuffer_height()) as usize
+            * bytes_per_pixel(&mode)?;
+        let stride = len.next_multiple_of(FRAMEBUFFER_ALIGN);
+        let skip = memory.as_ptr().align_offset(FRAMEBUFFER_ALIGN);
+        if skip + stride * N > memory.len() {
+            return Err(FrameBuffersError::OutOfMemory);
+        }
+
+        writeback_invalidate_dcache(memory.as_ptr(), memory.len());
+        let base = uncached(unsafe { memory.as_mut_ptr().add(skip) });
+        let buffers = core::array::from_fn(|index| unsafe { base.add(index * stride) });
+
+        vi.set_mode(&mode, physical(buffers[0]));
+
+        Ok(Self {
+            mode,
+            buffers,
+            len,
+            inner: Mutex::new(RefCell::new(Inner {
+                vi,
+                slots: Slots::new(),
+                armed: half_line(mode.fields[0].intr),
+            })),
+        })
+    }
+
+    /// The video mode being displayed.
+    pub fn mode(&self) -> &VideoMode {
+        &self.mode
+    }
+
+    /// Index of the buffer being displayed.
+    pub fn displayed(&self) -> usize {
+        critical_section::with(|cs| self.inner.borrow_ref(cs).slots.displayed)
+    }
+
+    /// Whether a presented buffer is still waiting for vertical blank.
+    pub fn is_pending(&self) -> bool {
+        critical_section::with(|cs| self.inner.borrow_ref(cs).slots.queued.is_some())
+    }
+
+    /// Hands out a buffer that is neither displayed nor queued.
+    ///
+    /// Returns `None` when every such buffer is already acquired, e.g. with
+    /// two buffers while a presented one waits for vertical blank.
+    pub fn acquire(&self) -> Option<Frame> {
+        let index = critical_section::with(|cs| self.inner.borrow_ref_mut(cs).slots.acquire())?;
+
+        Some(Frame {
+            index,
+            ptr: self.buffers[index],
+            len: self.len,
+        })
+    }
+
+    /// Queues `frame` for display at the next vertical blank.
+    ///
+    /// A frame that was queued but not yet displayed is dropped in favour of
+    /// the new one and becomes available to [`FrameBuffers::acquire`] again.
+    pub fn present(&self, frame: Frame) {
+        critical_section::with(|cs| self.inner.borrow_ref_mut(cs).slots.present(frame.index));
+    }
+
+    /// Returns `frame` without displaying it.
+    pub fn discard(&self, frame: Frame) {
+        critical_section::with(|cs| self.inner.borrow_ref_mut(cs).slots.discard(frame.index));
+    }
+
+    /// Routes the VI interrupt to the CPU at the mode's interrupt half-line.
+    ///
+    /// The manager owns the VI interrupt line from then on: raster
+    /// interrupts must go through [`FrameBuffers::on_raster_interrupt`].
+    pub fn enable_interrupt(&self, mi: &mut Mi) {
+        critical_section::with(|cs| {
+            let mut inner = self.inner.borrow_ref_mut(cs);
+            let intr = self.mode.fields[0].intr;
+            inner.armed = half_line(intr);
+            unsafe {
+                write_volatile(&mut inner.vi.vi_intr_reg, intr);
+                write_volatile(
+                    &mut mi.mi_intr_mask_reg,
+                    MiIntrMaskReg(0).with_set_vi_mask(true),
+                );
+            }
+        });
+    }
+
+    /// Handles the VI interrupt.
+    ///
+    /// Acknowledges the interrupt, swaps in the queued buffer and loads the
+    /// registers of the field about to be scanned out.
+    pub fn on_interrupt(&self) {
+        self.handle_interrupt(&[]);
+    }
+
+    /// Handles the VI interrupt, also firing it at the lines of `raster`.
+    ///
+    /// Does the vertical blank work of [`FrameBuffers::on_interrupt`] when
+    /// the interrupt is the mode's, and returns the index of the raster line
+    /// that fired, if any. The raster's own position is not used, so its
+    /// `start` and `on_interrupt` must not be called alongside.
+    pub fn on_raster_interrupt<const M: usize>(
+        &self,
+        raster: &RasterScheduler<M>,
+    ) -> Option<usize> {
+        self.handle_interrupt(raster.lines())
+    }
+
+    fn handle_interrupt(&self, lines: &[u32]) -> Option<usize> {
+        critical_section::with(|cs| {
+            let mut inner = self.inner.borrow_ref_mut(cs);
+            let inner = &mut *inner;
+            let field = inner.vi.current_field();
+            let registers = &self.mode.fields[field];
+            let vblank = half_line(registers.intr);
+            let fired = inner.armed;
+
+            if fired == vblank {
+                inner.slots.vblank(field);
+                let origin = self
+                    .mode
+                    .origin(physical(self.buffers[inner.slots.displayed]), field);
+                unsafe {
+                    write_volatile(&mut inner.vi.vi_origin_reg, origin);
+                    write_volatile(&mut inner.vi.vi_y_scale_reg, registers.y_scale);
+                    write_volatile(&mut inner.vi.vi_v_video_reg, registers.v_video);
+                    write_volatile(&mut inner.vi.vi_v_burst_reg, registers.v_burst);
+                }
+            }
+
+            inner.armed = next_half_line(fired, vblank, lines);
+            let intr = ViIntrReg(0).with_intr_half_line(inner.armed.into());
+            unsafe { write_volatile(&mut inner.vi.vi_intr_reg, intr) };
+            inner.vi.acknowledge_interrupt();
+
+            lines.iter().position(|&line| line << 1 == fired)
+        })
+    }
+
+    /// Stops managing the VI and returns it.
+    pub fn release(self) -> Vi {
+        self.inner.into_inner().into_inner().vi
+    }
+}
+
+/// Decoded from the raw bits, as converting the reserved size 1 to a
+/// `PixelSize` panics.
+fn bytes_per_pixel(mode: &VideoMode) -> Result<usize, FrameBuffersError> {
+    match mode.status.raw() & 0b11 {
+        2 => Ok(2),
+        3 => Ok(4),
+        _ => Err(FrameBuffersError::InvalidPixelSize),
+    }
+}
+
+fn half_line(intr: ViIntrReg) -> u32 {
+    u32::from(intr.intr_half_line())
+}
+
+/// First half-line after `fired` among the vertical blank interrupt and the
+/// raster `lines`, wrapping around to the earliest in the next field.
+fn next_half_line(fired: u32, vblank: u32, lines: &[u32]) -> u32 {
+    let half_lines = || lines.iter().map(|&line| line << 1).chain([vblank]);
+    half_lines()
+        .filter(|&half_line| half_line > fired)
+        .min()
+        .unwrap_or_else(|| half_lines().min().unwrap_or(vblank))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vi::ViStatusReg;
+
+    #[test]
+    fn rotates_buffers() {
+        let mut slots = Slots::<3>::new();
+        let first = slots.acquire().unwrap();
+        let second = slots.acquire().unwrap();
+        assert_eq!((first, second), (1, 2));
+        assert_eq!(slots.acquire(), None);
+
+        slots.present(first);
+        assert_eq!(slots.acquire(), None);
+        slots.vblank(0);
+        assert_eq!(slots.displayed, first);
+        assert_eq!(slots.queued, None);
+        assert_eq!(slots.acquire(), Some(0));
+    }
+
+    #[test]
+    fn replaces_queued_buffer() {
+        let mut slots = Slots::<3>::new();
+        let first = slots.acquire().unwrap();
+        let second = slots.acquire().unwrap();
+        slots.present(first);
+        slots.present(second);
+        assert_eq!(slots.acquire(), Some(first));
+
+        slots.vblank(0);
+        assert_eq!(slots.displayed, second);
+    }
+
+    #[test]
+    fn discards_buffer() {
+        let mut slots = Slots::<2>::new();
+        let frame = slots.acquire().unwrap();
+        slots.discard(frame);
+        assert_eq!(slots.acquire(), Some(frame));
+    }
+
+    #[test]
+    fn swaps_on_even_fields_only() {
+        let mut slots = Slots::<2>::new();
+        let frame = slots.acquire().unwrap();
+        slots.present(frame);
+        slots.vblank(1);
+        assert_eq!(slots.displayed, 0);
+        slots.vblank(0);
+        assert_eq!(slots.displayed, frame);
+    }
+
+    #[test]
+    fn schedules_raster_lines_around_vblank() {
+        let lines = [20, 100];
+        assert_eq!(next_half_line(2, 2, &lines), 40);
+        assert_eq!(next_half_line(40, 2, &lines), 200);
+        assert_eq!(next_half_line(200, 2, &lines), 2);
+        assert_eq!(next_half_line(2, 2, &[]), 2);
+        assert_eq!(next_half_line(2, 2, &[1]), 2);
+    }
+
+    #[test]
+    fn rejects_reserved_pixel_size() {
+        let mut mode = crate::vi::mode::NTSC_LPN1;
+        assert_eq!(bytes_per_pixel(&mode), Ok(2));
+
+        for raw in [0, 1] {
+            mode.status = ViStatusReg(mode.status.raw() & !0b11 | raw);
+            assert_eq!(
+                bytes_per_pixel(&mode),
+                Err(FrameBuffersError::InvalidPixelSize)
+            );
+        }
+    }
+}
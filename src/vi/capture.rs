@@ -0,0 +1,224 @@
+//! # Screenshot capture
+//!
+//! [`Vi::capture`] copies the framebuffer lines the VI is showing, together
+//! with the VI registers, into a self-describing blob that can be sent to a
+//! host and decoded with [`Capture`], which renders it through the
+//! [VI emulation](super::emulation).
+//!
+//! A capture is a header followed by the framebuffer data, all big-endian:
+//!
+//! | Offset | Size | Contents                                      |
+//! |--------|------|-----------------------------------------------|
+//! | 0      | 4    | Magic, `N64V`                                 |
+//! | 4      | 2    | Format version, 1                             |
+//! | 6      | 2    | Header size in bytes                          |
+//! | 8      | 56   | `VI_STATUS` to `VI_Y_SCALE`, in address order |
+//! | 64     | 4    | Framebuffer data size in bytes                |
+//!
+//! The framebuffer data starts at the pixel `VI_ORIGIN` points to and holds
+//! whole lines of `VI_WIDTH` pixels, up to the last line the active video
+//! window samples.
+
+use super::{
+    emulation::{self, EmulationError, EmulationOptions, ViRegisters},
+    Vi, ViCurrentReg, ViHSyncLeapReg, ViHSyncReg, ViHVideoReg, ViIntrReg, ViOriginReg, ViStatusReg,
+    ViTimingReg, ViVBurstReg, ViVSyncReg, ViVVideoReg, ViWidthReg, ViXScaleReg, ViYScaleReg,
+};
+
+/// # Capture magic
+pub const CAPTURE_MAGIC: [u8; 4] = *b"N64V";
+
+/// # Capture format version
+pub const CAPTURE_VERSION: u16 = 1;
+
+/// # Capture header size
+pub const CAPTURE_HEADER_SIZE: usize = 68;
+
+/// End of the largest RDRAM, as a physical address.
+const RDRAM_END: usize = 0x0080_0000;
+
+/// # Capture error
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CaptureError {
+    /// The VI is not displaying a framebuffer.
+    Blank,
+
+    /// The buffer cannot hold the capture.
+    BufferTooSmall,
+
+    /// The data is not a capture, or of an unknown version.
+    InvalidFormat,
+}
+
+/// Bytes of framebuffer data the VI samples for `registers`, from the
+/// origin, or `None` if no framebuffer is shown.
+pub fn framebuffer_size(registers: &ViRegisters) -> Option<usize> {
+    let bytes_per_pixel = match registers.status.raw() & 0b11 {
+        2 => 2,
+        3 => 4,
+        _ => return None,
+    };
+
+    let (_, lines) = registers.output_size();
+    let y_step = u32::from(registers.y_scale.inverse_scale_factor()) as usize;
+    let y_offset = u32::from(registers.y_scale.subpixel_offset()) as usize;
+
+    // Resampling reads one line past the last one sampled.
+    let source_lines = match lines {
+        0 => 0,
+        lines => ((y_offset + (lines as usize - 1) * y_step) >> 10) + 2,
+    };
+
+    let width = u32::from(registers.width.frame_buffer_line_width()) as usize;
+    let origin = (registers.origin.raw() & 0x00FF_FFFF) as usize;
+    let size = source_lines * width * bytes_per_pixel;
+    Some(size.min(RDRAM_END.saturating_sub(origin)))
+}
+
+/// Writes a capture of `framebuffer`, shown with `registers`, into `buffer`
+/// and returns its size.
+///
+/// `framebuffer` starts at the origin and is truncated to
+/// [`framebuffer_size`].
+pub fn encode(
+    registers: &ViRegisters,
+    framebuffer: &[u8],
+    buffer: &mut [u8],
+) -> Result<usize, CaptureError> {
+    let size = framebuffer_size(registers)
+        .ok_or(CaptureError::Blank)?
+        .min(framebuffer.len());
+    let total = CAPTURE_HEADER_SIZE + size;
+    if buffer.len() < total {
+        return Err(CaptureError::BufferTooSmall);
+    }
+
+    let words = [
+        registers.status.raw(),
+        registers.origin.raw(),
+        registers.width.raw(),
+        registers.intr.raw(),
+        registers.current.raw(),
+        registers.timing.raw(),
+        registers.v_sync.raw(),
+        registers.h_sync.raw(),
+        registers.h_sync_leap.raw(),
+        registers.h_video.raw(),
+        registers.v_video.raw(),
+        registers.v_burst.raw(),
+        registers.x_scale.raw(),
+        registers.y_scale.raw(),
+    ];
+
+    buffer[0..4].copy_from_slice(&CAPTURE_MAGIC);
+    buffer[4..6].copy_from_slice(&CAPTURE_VERSION.to_be_bytes());
+    buffer[6..8].copy_from_slice(&(CAPTURE_HEADER_SIZE as u16).to_be_bytes());
+    for (index, word) in words.iter().enumerate() {
+        buffer[8 + index * 4..12 + index * 4].copy_from_slice(&word.to_be_bytes());
+    }
+    buffer[64..68].copy_from_slice(&(size as u32).to_be_bytes());
+    buffer[CAPTURE_HEADER_SIZE..total].copy_from_slice(&framebuffer[..size]);
+
+    Ok(total)
+}
+
+impl Vi {
+    /// Captures the displayed framebuffer into `buffer` and returns the
+    /// capture's size.
+    ///
+    /// Waits for vertical blank first, so the registers are read after any
+    /// framebuffer swap and not halfway through one. A swap made from the
+    /// VI interrupt can still land while the data is copied.
+    pub fn capture(&self, buffer: &mut [u8]) -> Result<usize, CaptureError> {
+        let registers = ViRegisters::read(self);
+        if framebuffer_size(&registers).is_none() || registers.output_size().1 == 0 {
+            return Err(CaptureError::Blank);
+        }
+
+        self.wait_vblank();
+        let registers = ViRegisters::read(self);
+        let size = framebuffer_size(&registers).ok_or(CaptureError::Blank)?;
+        let origin = registers.origin.raw() & 0x00FF_FFFF;
+
+        // Read through KSEG1 to see what the VI sees rather than the cache.
+        let framebuffer = unsafe {
+            core::slice::from_raw_parts((0xA000_0000 | origin) as usize as *const u8, size)
+        };
+        encode(&registers, framebuffer, buffer)
+    }
+}
+
+/// # Decoded capture
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Capture<'a> {
+    pub registers: ViRegisters,
+    /// Framebuffer data from the origin.
+    pub framebuffer: &'a [u8],
+}
+
+impl<'a> Capture<'a> {
+    /// Decodes a capture.
+    pub fn parse(data: &'a [u8]) -> Result<Self, CaptureError> {
+        let word = |offset: usize| {
+            data.get(offset..offset + 4)
+                .map(|bytes| u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+                .ok_or(CaptureError::InvalidFormat)
+        };
+
+        if data.get(0..4) != Some(&CAPTURE_MAGIC[..]) {
+            return Err(CaptureError::InvalidFormat);
+        }
+
+        let version = word(4)? >> 16;
+        let header_size = (word(4)? & 0xFFFF) as usize;
+        if version != u32::from(CAPTURE_VERSION) || header_size < CAPTURE_HEADER_SIZE {
+            return Err(CaptureError::InvalidFormat);
+        }
+
+        let size = word(64)? as usize;
+        let end = header_size
+            .checked_add(size)
+            .ok_or(CaptureError::InvalidFormat)?;
+        let framebuffer = data
+            .get(header_size..end)
+            .ok_or(CaptureError::InvalidFormat)?;
+
+        Ok(Self {
+            registers: ViRegisters {
+                status: ViStatusReg(word(8)?),
+                origin: ViOriginReg(word(12)?),
+                width: ViWidthReg(word(16)?),
+                intr: ViIntrReg(word(20)?),
+                current: ViCurrentReg(word(24)?),
+                timing: ViTimingReg(word(28)?),
+                v_sync: ViVSyncReg(word(32)?),
+                h_sync: ViHSyncReg(word(36)?),
+                h_sync_leap: ViHSyncLeapReg(word(40)?),
+                h_video: ViHVideoReg(word(44)?),
+                v_video: ViVVideoReg(word(48)?),
+                v_burst: ViVBurstReg(word(52)?),
+                x_scale: ViXScaleReg(word(56)?),
+                y_scale: ViYScaleReg(word(60)?),
+            },
+            framebuffer,
+        })
+    }
+
+    /// Size of the picture as `(width, height)`.
+    pub fn output_size(&self) -> (u32, u32) {
+        self.registers.output_size()
+    }
+
+    /// Renders the captured picture as RGBA8, see [`emulation::render`].
+    pub fn render(
+        &self,
+        options: EmulationOptions,
+        out: &mut [u8],
+    ) -> Result<(u32, u32), EmulationError> {
+        let registers = ViRegisters {
+            origin: ViOriginReg(0),
+            ..self.registers
+        };
+        emulation::render(&registers, self.framebuffer, options, out)
+    }
+}
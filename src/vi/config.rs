@@ -0,0 +1,263 @@
+//! # VI configuration builder
+//!
+//! Derives a [`VideoMode`] from a framebuffer resolution: the inverse scale
+//! factors map the framebuffer onto the display window of the chosen
+//! standard, optionally shrunk by overscan margins and shifted to taste.
+
+use super::{
+    filters::{FilterError, ViFilters},
+    mode::{FieldRegisters, StandardTiming, VideoMode, VideoStandard},
+    AntialiasMode, PixelSize, ViHSyncLeapReg, ViHSyncReg, ViHVideoReg, ViIntrReg, ViTimingReg,
+    ViVBurstReg, ViVSyncReg, ViVVideoReg, ViWidthReg, ViXScaleReg, ViYScaleReg,
+};
+
+/// # Overscan margins
+///
+/// Horizontal margins are in output pixels, vertical margins in lines.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Overscan {
+    pub left: u32,
+    pub right: u32,
+    pub top: u32,
+    pub bottom: u32,
+}
+
+impl Overscan {
+    /// The same margin on every side.
+    pub const fn uniform(horizontal: u32, vertical: u32) -> Self {
+        Self {
+            left: horizontal,
+            right: horizontal,
+            top: vertical,
+            bottom: vertical,
+        }
+    }
+}
+
+/// # Configuration error
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigError {
+    /// The framebuffer width or height is zero or too large for the VI.
+    InvalidResolution,
+
+    /// The pixel size is [`PixelSize::Blank`].
+    InvalidPixelSize,
+
+    /// The margins or position push the window outside the visible area.
+    WindowOutOfRange,
+
+    /// The framebuffer is too large to be downscaled into the window.
+    ScaleOutOfRange,
+
+    /// The pixel size and antialias mode do not work together.
+    InvalidFilters(FilterError),
+}
+
+/// # VI configuration
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ViConfig {
+    standard: VideoStandard,
+    width: u32,
+    height: u32,
+    pixel_size: PixelSize,
+    antialias_mode: AntialiasMode,
+    interlaced: Option<bool>,
+    overscan: Overscan,
+    x_offset: i32,
+    y_offset: i32,
+}
+
+/// Display window of a standard, as `(h_start, h_end, v_start, v_end)` with
+/// the vertical bounds in half-lines.
+const fn display_window(standard: VideoStandard) -> (u32, u32, u32, u32) {
+    match standard {
+        VideoStandard::Pal => (128, 768, 43, 619),
+        VideoStandard::Ntsc | VideoStandard::Mpal | VideoStandard::Pal60 => (108, 748, 35, 515),
+    }
+}
+
+/// Largest 2.10 fixed-point scale factor the scale registers hold.
+const SCALE_MAX: u32 = 0xFFF;
+
+impl ViConfig {
+    /// Starts a configuration for a `width`x`height` framebuffer.
+    ///
+    /// Defaults to 16-bit pixels, the default [`ViFilters`], no overscan and
+    /// interlacing only when the height requires it.
+    pub const fn new(standard: VideoStandard, width: u32, height: u32) -> Self {
+        Self {
+            standard,
+            width,
+            height,
+            pixel_size: PixelSize::SixteenBit,
+            antialias_mode: AntialiasMode::Optimized,
+            interlaced: None,
+            overscan: Overscan::uniform(0, 0),
+            x_offset: 0,
+            y_offset: 0,
+        }
+    }
+
+    /// Sets the framebuffer pixel size.
+    pub const fn pixel_size(mut self, pixel_size: PixelSize) -> Self {
+        self.pixel_size = pixel_size;
+        self
+    }
+
+    /// Sets the antialias mode.
+    pub const fn antialias_mode(mut self, antialias_mode: AntialiasMode) -> Self {
+        self.antialias_mode = antialias_mode;
+        self
+    }
+
+    /// Forces interlacing on or off.
+    pub const fn interlaced(mut self, interlaced: bool) -> Self {
+        self.interlaced = Some(interlaced);
+        self
+    }
+
+    /// Shrinks the display window by `overscan`.
+    pub const fn overscan(mut self, overscan: Overscan) -> Self {
+        self.overscan = overscan;
+        self
+    }
+
+    /// Moves the display window by `x` pixels and `y` lines.
+    pub const fn position(mut self, x: i32, y: i32) -> Self {
+        self.x_offset = x;
+        self.y_offset = y;
+        self
+    }
+
+    /// Computes and validates the register set.
+    pub fn build(&self) -> Result<VideoMode, ConfigError> {
+        let bytes_per_pixel = match self.pixel_size {
+            PixelSize::SixteenBit => 2,
+            PixelSize::ThirtyTwoBit => 4,
+            PixelSize::Blank => return Err(ConfigError::InvalidPixelSize),
+        };
+
+        if self.width == 0 || self.height == 0 || self.width > 0x7FF {
+            return Err(ConfigError::InvalidResolution);
+        }
+
+        // Vertical margins and offsets are in lines, the window in half-lines.
+        let (h_start, h_end, v_start, v_end) = display_window(self.standard);
+        let y_offset = self
+            .y_offset
+            .checked_mul(2)
+            .ok_or(ConfigError::WindowOutOfRange)?;
+        let top = half_lines(self.overscan.top)?;
+        let bottom = half_lines(self.overscan.bottom)?;
+
+        let h_start = offset(add(h_start, self.overscan.left)?, self.x_offset)?;
+        let h_end = offset(h_end, self.x_offset)?
+            .checked_sub(self.overscan.right)
+            .ok_or(ConfigError::WindowOutOfRange)?;
+        let v_start = offset(add(v_start, top)?, y_offset)?;
+        let v_end = offset(v_end, y_offset)?
+            .checked_sub(bottom)
+            .ok_or(ConfigError::WindowOutOfRange)?;
+
+        if h_start >= h_end || v_start < 2 || v_start >= v_end {
+            return Err(ConfigError::WindowOutOfRange);
+        }
+
+        let interlaced = self
+            .interlaced
+            .unwrap_or(self.height > (v_end - v_start) / 2);
+        let timing = StandardTiming::new(self.standard, interlaced);
+
+        if h_end > 0x3FF || v_end > timing.v_sync {
+            return Err(ConfigError::WindowOutOfRange);
+        }
+
+        let window_width = h_end - h_start;
+        let window_lines = (v_end - v_start) / 2;
+
+        // Tall framebuffers are split between the fields by doubling the line
+        // stride; short ones are shown whole in each field.
+        let split = interlaced && self.height > window_lines;
+        let field_height = if split {
+            self.height.div_ceil(2)
+        } else {
+            self.height
+        };
+        let x_scale = scale(self.width, window_width)?;
+        let y_scale = scale(field_height, window_lines)?;
+
+        let (width, origin_offsets, y_offsets) = match (split, interlaced) {
+            (true, _) => (self.width * 2, [0, self.width * bytes_per_pixel], [0, 0]),
+            (false, true) => (self.width, [0, 0], [0, y_scale / 2]),
+            (false, false) => (self.width, [0, 0], [0, 0]),
+        };
+
+        let v_video = ViVVideoReg(0)
+            .with_start_active_video(v_start.into())
+            .with_end_active_video(v_end.into())
+            .raw();
+        let v_video = match interlaced {
+            true => [v_video - 0x0002_0002, v_video],
+            false => [v_video; 2],
+        };
+
+        let status = ViFilters::new(self.pixel_size)
+            .antialias_mode(self.antialias_mode)
+            .build(interlaced)
+            .map_err(ConfigError::InvalidFilters)?;
+
+        let field = |index: usize| FieldRegisters {
+            origin_offset: origin_offsets[index],
+            y_scale: ViYScaleReg(0)
+                .with_inverse_scale_factor(y_scale.into())
+                .with_subpixel_offset(y_offsets[index].into()),
+            v_video: ViVVideoReg(v_video[index]),
+            v_burst: ViVBurstReg(timing.v_burst[index]),
+            intr: ViIntrReg(2),
+        };
+
+        Ok(VideoMode {
+            standard: self.standard,
+            framebuffer_width: self.width,
+            framebuffer_height: self.height,
+            status,
+            width: ViWidthReg(width),
+            timing: ViTimingReg(timing.timing),
+            v_sync: ViVSyncReg(timing.v_sync),
+            h_sync: ViHSyncReg(timing.h_sync),
+            h_sync_leap: ViHSyncLeapReg(timing.h_sync_leap),
+            h_video: ViHVideoReg(0)
+                .with_start_active_video(h_start.into())
+                .with_end_active_video(h_end.into()),
+            x_scale: ViXScaleReg(0).with_inverse_scale_factor(x_scale.into()),
+            fields: [field(0), field(1)],
+        })
+    }
+}
+
+fn half_lines(lines: u32) -> Result<u32, ConfigError> {
+    lines.checked_mul(2).ok_or(ConfigError::WindowOutOfRange)
+}
+
+fn add(value: u32, margin: u32) -> Result<u32, ConfigError> {
+    value
+        .checked_add(margin)
+        .ok_or(ConfigError::WindowOutOfRange)
+}
+
+fn offset(value: u32, offset: i32) -> Result<u32, ConfigError> {
+    value
+        .checked_add_signed(offset)
+        .ok_or(ConfigError::WindowOutOfRange)
+}
+
+/// 2.10 fixed-point ratio of framebuffer to window size, rounded.
+fn scale(source: u32, window: u32) -> Result<u32, ConfigError> {
+    let scaled = source
+        .checked_mul(1 << 10)
+        .ok_or(ConfigError::ScaleOutOfRange)?;
+    match (scaled + window / 2) / window {
+        scale @ 1..=SCALE_MAX => Ok(scale),
+        _ => Err(ConfigError::ScaleOutOfRange),
+    }
+}
@@ -0,0 +1,158 @@
+//! # Text console
+//!
+//! Draws text from the built-in [`font`] into a framebuffer
+//! with the CPU alone, scrolling when the cursor passes the last row. Works
+//! with either pixel size and needs neither the RDP nor an allocator, which
+//! makes it suitable for bring-up and crash output.
+
+use core::fmt;
+
+use crate::font::{self, GLYPH_HEIGHT, GLYPH_WIDTH};
+
+use super::{
+    framebuffer::{Framebuffer, FramebufferError},
+    mode::VideoMode,
+    pixel::Pixel,
+    Vi,
+};
+
+/// # Text console
+pub struct Console<'a, P: Pixel> {
+    framebuffer: Framebuffer<'a, P>,
+    foreground: P,
+    background: P,
+    column: u32,
+    row: u32,
+}
+
+impl<'a, P: Pixel> Console<'a, P> {
+    /// Starts a console at the top left of `framebuffer`.
+    pub fn new(framebuffer: Framebuffer<'a, P>, foreground: P, background: P) -> Self {
+        Self {
+            framebuffer,
+            foreground,
+            background,
+            column: 0,
+            row: 0,
+        }
+    }
+
+    /// Number of text columns.
+    pub fn columns(&self) -> u32 {
+        self.framebuffer.width() / GLYPH_WIDTH
+    }
+
+    /// Number of text rows.
+    pub fn rows(&self) -> u32 {
+        self.framebuffer.height() / GLYPH_HEIGHT
+    }
+
+    /// Cursor position as `(column, row)`.
+    pub fn cursor(&self) -> (u32, u32) {
+        (self.column, self.row)
+    }
+
+    /// Moves the cursor, clamping it to the console.
+    pub fn set_cursor(&mut self, column: u32, row: u32) {
+        self.column = column.min(self.columns().saturating_sub(1));
+        self.row = row.min(self.rows().saturating_sub(1));
+    }
+
+    /// Sets the colours used by subsequent text.
+    pub fn set_colors(&mut self, foreground: P, background: P) {
+        self.foreground = foreground;
+        self.background = background;
+    }
+
+    /// Fills the framebuffer with the background colour and homes the cursor.
+    pub fn clear(&mut self) {
+        self.framebuffer.fill(self.background);
+        self.column = 0;
+        self.row = 0;
+    }
+
+    /// Writes one character, handling `\n` and `\r`.
+    pub fn put(&mut self, c: char) {
+        if self.rows() == 0 || self.columns() == 0 {
+            return;
+        }
+
+        match c {
+            '\n' => self.new_line(),
+            '\r' => self.column = 0,
+            c => {
+                if self.column >= self.columns() {
+                    self.new_line();
+                }
+                self.draw(c);
+                self.column += 1;
+            }
+        }
+    }
+
+    /// Returns the framebuffer.
+    pub fn release(self) -> Framebuffer<'a, P> {
+        self.framebuffer
+    }
+
+    fn new_line(&mut self) {
+        self.column = 0;
+        if self.row + 1 < self.rows() {
+            self.row += 1;
+        } else {
+            self.scroll();
+        }
+    }
+
+    /// Moves every text row up by one and clears the last.
+    fn scroll(&mut self) {
+        let row_bytes = (self.framebuffer.width() * GLYPH_HEIGHT) as usize * P::BYTES;
+        let text_bytes = row_bytes * self.rows() as usize;
+        let bytes = self.framebuffer.as_bytes_mut();
+        bytes.copy_within(row_bytes..text_bytes, 0);
+        for pixel in bytes[text_bytes - row_bytes..text_bytes].chunks_exact_mut(P::BYTES) {
+            self.background.write(pixel);
+        }
+    }
+
+    fn draw(&mut self, c: char) {
+        let left = self.column * GLYPH_WIDTH;
+        let top = self.row * GLYPH_HEIGHT;
+
+        for (y, bits) in font::glyph(c).iter().enumerate() {
+            for x in 0..GLYPH_WIDTH {
+                let color = match bits & (0x80 >> x) != 0 {
+                    true => self.foreground,
+                    false => self.background,
+                };
+                self.framebuffer.set(left + x, top + y as u32, color);
+            }
+        }
+    }
+}
+
+impl<P: Pixel> Console<'static, P> {
+    /// Starts a console over the first `height` lines of the framebuffer the
+    /// VI is scanning out in `mode`.
+    ///
+    /// # Safety
+    ///
+    /// See [`Framebuffer::displayed`].
+    pub unsafe fn displayed(
+        vi: &Vi,
+        mode: &VideoMode,
+        height: u32,
+        foreground: P,
+        background: P,
+    ) -> Result<Self, FramebufferError> {
+        let framebuffer = Framebuffer::displayed(vi, mode, height)?;
+        Ok(Self::new(framebuffer, foreground, background))
+    }
+}
+
+impl<P: Pixel> fmt::Write for Console<'_, P> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        s.chars().for_each(|c| self.put(c));
+        Ok(())
+    }
+}
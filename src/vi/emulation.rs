@@ -0,0 +1,480 @@
+//! # VI output emulation
+//!
+//! Renders what the VI would display for a register set and an RDRAM image,
+//! so rendering code can be tested without hardware. The steps follow the
+//! register fields:
+//!
+//! - `VI_STATUS` pixel size selects 16-bit RGBA5551 or 32-bit RGBA8888
+//!   pixels; blank (and the reserved size) shows black.
+//! - `VI_ORIGIN` is the RDRAM address of the first pixel shown, and
+//!   `VI_WIDTH` the distance between framebuffer lines in pixels.
+//! - `VI_H_VIDEO` and `VI_V_VIDEO` bound the active video window, which is
+//!   the size of the rendered picture: one pixel per VI pixel and one line
+//!   per two half-lines.
+//! - `VI_X_SCALE` and `VI_Y_SCALE` step through the framebuffer by a 2.10
+//!   fixed-point amount per output pixel and line, starting at their
+//!   subpixel offsets.
+//! - The antialias mode either replicates the nearest pixel or resamples
+//!   bilinearly between the four surrounding ones.
+//! - The dither filter (status bit 16) recovers 8-bit precision from 16-bit
+//!   pixels by counting neighbours one step above or below.
+//! - The divot filter replaces output pixels of partial coverage with the
+//!   median of themselves and their horizontal neighbours.
+//! - Gamma correction raises each channel to the power 1/2.
+//!
+//! Coverage is taken from the top three alpha bits of 32-bit pixels and the
+//! alpha bit of 16-bit ones, whose hidden coverage bits are not in RDRAM.
+//! Coverage-based edge antialiasing and gamma dither, which is random, are
+//! not emulated.
+
+use core::ptr::read_volatile;
+
+use super::{
+    mode::VideoMode, Vi, ViCurrentReg, ViHSyncLeapReg, ViHSyncReg, ViHVideoReg, ViIntrReg,
+    ViOriginReg, ViStatusReg, ViTimingReg, ViVBurstReg, ViVSyncReg, ViVVideoReg, ViWidthReg,
+    ViXScaleReg, ViYScaleReg,
+};
+
+/// Widest active video window the horizontal video register can describe.
+const MAX_WIDTH: usize = 0x400;
+
+/// # VI register snapshot
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ViRegisters {
+    pub status: ViStatusReg,
+    pub origin: ViOriginReg,
+    pub width: ViWidthReg,
+    pub intr: ViIntrReg,
+    pub current: ViCurrentReg,
+    pub timing: ViTimingReg,
+    pub v_sync: ViVSyncReg,
+    pub h_sync: ViHSyncReg,
+    pub h_sync_leap: ViHSyncLeapReg,
+    pub h_video: ViHVideoReg,
+    pub v_video: ViVVideoReg,
+    pub v_burst: ViVBurstReg,
+    pub x_scale: ViXScaleReg,
+    pub y_scale: ViYScaleReg,
+}
+
+impl ViRegisters {
+    /// Reads every VI register.
+    pub fn read(vi: &Vi) -> Self {
+        unsafe {
+            Self {
+                status: read_volatile(&vi.vi_status_reg),
+                origin: read_volatile(&vi.vi_origin_reg),
+                width: read_volatile(&vi.vi_width_reg),
+                intr: read_volatile(&vi.vi_intr_reg),
+                current: read_volatile(&vi.vi_current_reg),
+                timing: read_volatile(&vi.vi_timing_reg),
+                v_sync: read_volatile(&vi.vi_v_sync_reg),
+                h_sync: read_volatile(&vi.vi_h_sync_reg),
+                h_sync_leap: read_volatile(&vi.vi_h_sync_leap_reg),
+                h_video: read_volatile(&vi.vi_h_video_reg),
+                v_video: read_volatile(&vi.vi_v_video_reg),
+                v_burst: read_volatile(&vi.vi_v_burst_reg),
+                x_scale: read_volatile(&vi.vi_x_scale_reg),
+                y_scale: read_volatile(&vi.vi_y_scale_reg),
+            }
+        }
+    }
+
+    /// Registers for `field` of `mode` showing the framebuffer at physical
+    /// address `framebuffer`.
+    pub const fn from_mode(mode: &VideoMode, framebuffer: u32, field: usize) -> Self {
+        let registers = &mode.fields[field & 1];
+        Self {
+            status: mode.status,
+            origin: mode.origin(framebuffer, field),
+            width: mode.width,
+            intr: registers.intr,
+            current: ViCurrentReg(0),
+            timing: mode.timing,
+            v_sync: mode.v_sync,
+            h_sync: mode.h_sync,
+            h_sync_leap: mode.h_sync_leap,
+            h_video: mode.h_video,
+            v_video: registers.v_video,
+            v_burst: registers.v_burst,
+            x_scale: mode.x_scale,
+            y_scale: registers.y_scale,
+        }
+    }
+
+    /// Size of the displayed picture as `(width, height)`.
+    pub fn output_size(&self) -> (u32, u32) {
+        let h_start = u32::from(self.h_video.start_active_video());
+        let h_end = u32::from(self.h_video.end_active_video());
+        let v_start = u32::from(self.v_video.start_active_video());
+        let v_end = u32::from(self.v_video.end_active_video());
+        (
+            h_end.saturating_sub(h_start),
+            v_end.saturating_sub(v_start) / 2,
+        )
+    }
+}
+
+/// # Emulation options
+///
+/// Which of the filters enabled in `VI_STATUS` to apply.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EmulationOptions {
+    pub antialias: bool,
+    pub dither_filter: bool,
+    pub divot: bool,
+    pub gamma: bool,
+}
+
+impl EmulationOptions {
+    /// Applies every filter the status register enables.
+    pub const ALL: Self = Self {
+        antialias: true,
+        dither_filter: true,
+        divot: true,
+        gamma: true,
+    };
+
+    /// Shows the framebuffer pixels as they are, nearest sampled.
+    pub const NONE: Self = Self {
+        antialias: false,
+        dither_filter: false,
+        divot: false,
+        gamma: false,
+    };
+}
+
+impl Default for EmulationOptions {
+    fn default() -> Self {
+        Self::ALL
+    }
+}
+
+/// # Emulation error
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EmulationError {
+    /// The output buffer cannot hold the picture.
+    OutputTooSmall,
+}
+
+/// A resolved framebuffer pixel: 8-bit colour and 3-bit coverage.
+#[derive(Clone, Copy)]
+struct Sample {
+    color: [u8; 3],
+    coverage: u8,
+}
+
+const BLACK: Sample = Sample {
+    color: [0; 3],
+    coverage: 7,
+};
+
+struct Source<'a> {
+    rdram: &'a [u8],
+    origin: usize,
+    width: usize,
+    bytes_per_pixel: usize,
+    dither_filter: bool,
+}
+
+impl Source<'_> {
+    fn raw(&self, x: usize, y: usize) -> Option<&[u8]> {
+        let offset = self.origin + (y * self.width + x) * self.bytes_per_pixel;
+        self.rdram.get(offset..offset + self.bytes_per_pixel)
+    }
+
+    fn rgba5551(&self, x: usize, y: usize) -> Option<([u8; 3], bool)> {
+        let raw = self.raw(x, y)?;
+        let value = u16::from_be_bytes([raw[0], raw[1]]);
+        let channel = |shift: u16| (value >> shift) as u8 & 0x1F;
+        Some(([channel(11), channel(6), channel(1)], value & 1 != 0))
+    }
+
+    fn sample(&self, x: usize, y: usize) -> Sample {
+        match self.bytes_per_pixel {
+            4 => match self.raw(x, y) {
+                Some(raw) => Sample {
+                    color: [raw[0], raw[1], raw[2]],
+                    coverage: raw[3] >> 5,
+                },
+                None => BLACK,
+            },
+            _ => match self.rgba5551(x, y) {
+                Some((color, alpha)) => Sample {
+                    color: match self.dither_filter {
+                        true => self.undither(x, y, color),
+                        false => color.map(|channel| (channel << 3) | (channel >> 2)),
+                    },
+                    coverage: if alpha { 7 } else { 0 },
+                },
+                None => BLACK,
+            },
+        }
+    }
+
+    /// Each of the eight neighbours one 5-bit step above or below the centre
+    /// moves it by an eighth of a step, which is one 8-bit step.
+    fn undither(&self, x: usize, y: usize, color: [u8; 3]) -> [u8; 3] {
+        let mut result = color.map(|channel| i32::from((channel << 3) | (channel >> 2)));
+
+        for (dx, dy) in [
+            (-1, -1),
+            (0, -1),
+            (1, -1),
+            (-1, 0),
+            (1, 0),
+            (-1, 1),
+            (0, 1),
+            (1, 1),
+        ] {
+            let neighbour = x
+                .checked_add_signed(dx)
+                .zip(y.checked_add_signed(dy))
+                .and_then(|(x, y)| self.rgba5551(x, y));
+
+            if let Some((neighbour, _)) = neighbour {
+                for channel in 0..3 {
+                    match i32::from(neighbour[channel]) - i32::from(color[channel]) {
+                        1 => result[channel] += 1,
+                        -1 => result[channel] -= 1,
+                        _ => {}
+                    }
+                }
+            }
+        }
+
+        result.map(|channel| channel.clamp(0, 0xFF) as u8)
+    }
+}
+
+/// Renders the picture the VI displays for `registers` into `out`, as RGBA8
+/// rows of the width and height returned by [`ViRegisters::output_size`].
+///
+/// `rdram` is an image of RDRAM from physical address 0. Pixels outside it
+/// are black.
+pub fn render(
+    registers: &ViRegisters,
+    rdram: &[u8],
+    options: EmulationOptions,
+    out: &mut [u8],
+) -> Result<(u32, u32), EmulationError> {
+    let (width, height) = registers.output_size();
+    let len = (width * height) as usize * 4;
+    if out.len() < len {
+        return Err(EmulationError::OutputTooSmall);
+    }
+    if len == 0 {
+        return Ok((width, height));
+    }
+
+    let status = registers.status.raw();
+    let bytes_per_pixel = match status & 0b11 {
+        2 => 2,
+        3 => 4,
+        _ => {
+            for pixel in out[..len].chunks_exact_mut(4) {
+                pixel.copy_from_slice(&[0, 0, 0, 0xFF]);
+            }
+            return Ok((width, height));
+        }
+    };
+
+    let source = Source {
+        rdram,
+        origin: (registers.origin.raw() & 0x00FF_FFFF) as usize,
+        width: u32::from(registers.width.frame_buffer_line_width()) as usize,
+        bytes_per_pixel,
+        dither_filter: options.dither_filter && status & (1 << 16) != 0,
+    };
+
+    let resample = options.antialias && (status >> 8) & 0b11 != 0b11;
+    let divot = options.divot && status & (1 << 4) != 0;
+    let gamma = options.gamma && status & (1 << 3) != 0;
+
+    let x_step = u32::from(registers.x_scale.inverse_scale_factor()) as usize;
+    let x_offset = u32::from(registers.x_scale.subpixel_offset()) as usize;
+    let y_step = u32::from(registers.y_scale.inverse_scale_factor()) as usize;
+    let y_offset = u32::from(registers.y_scale.subpixel_offset()) as usize;
+
+    let mut row = [BLACK; MAX_WIDTH];
+    for (y, line) in out[..len].chunks_exact_mut(width as usize * 4).enumerate() {
+        let sy = y_offset + y * y_step;
+
+        for (x, sample) in row[..width as usize].iter_mut().enumerate() {
+            let sx = x_offset + x * x_step;
+            *sample = match resample {
+                true => bilinear(&source, sx, sy),
+                false => source.sample(sx >> 10, sy >> 10),
+            };
+        }
+
+        for (x, pixel) in line.chunks_exact_mut(4).enumerate() {
+            let mut color = row[x].color;
+
+            if divot && row[x].coverage < 7 && x > 0 && x + 1 < width as usize {
+                for (channel, value) in color.iter_mut().enumerate() {
+                    *value = median(row[x - 1].color[channel], *value, row[x + 1].color[channel]);
+                }
+            }
+
+            if gamma {
+                color = color.map(|channel| isqrt(u32::from(channel) * 0xFF) as u8);
+            }
+
+            pixel.copy_from_slice(&[color[0], color[1], color[2], 0xFF]);
+        }
+    }
+
+    Ok((width, height))
+}
+
+fn bilinear(source: &Source, sx: usize, sy: usize) -> Sample {
+    let (x, y) = (sx >> 10, sy >> 10);
+    let (fx, fy) = ((sx & 0x3FF) as u32, (sy & 0x3FF) as u32);
+    let corners = [
+        (source.sample(x, y), (0x400 - fx) * (0x400 - fy)),
+        (source.sample(x + 1, y), fx * (0x400 - fy)),
+        (source.sample(x, y + 1), (0x400 - fx) * fy),
+        (source.sample(x + 1, y + 1), fx * fy),
+    ];
+
+    let mut color = [0; 3];
+    for (channel, value) in color.iter_mut().enumerate() {
+        let sum: u32 = corners
+            .iter()
+            .map(|(sample, weight)| u32::from(sample.color[channel]) * weight)
+            .sum();
+        *value = ((sum + (1 << 19)) >> 20) as u8;
+    }
+
+    Sample {
+        color,
+        coverage: corners[0].0.coverage,
+    }
+}
+
+fn median(a: u8, b: u8, c: u8) -> u8 {
+    a.max(b).min(a.min(b).max(c))
+}
+
+fn isqrt(value: u32) -> u32 {
+    let mut root = 0;
+    while (root + 1) * (root + 1) <= value {
+        root += 1;
+    }
+    root
+}
+
+/// Writes RGBA8 pixels as a binary PPM, dropping alpha.
+///
+/// Fails with [`std::io::ErrorKind::InvalidInput`] if `rgba` holds fewer than
+/// `width * height` pixels.
+#[cfg(feature = "std")]
+pub fn write_ppm<W: std::io::Write>(
+    mut writer: W,
+    width: u32,
+    height: u32,
+    rgba: &[u8],
+) -> std::io::Result<()> {
+    let rgba = pixels(width, height, rgba)?;
+    write!(writer, "P6\n{} {}\n255\n", width, height)?;
+    for pixel in rgba.chunks_exact(4) {
+        writer.write_all(&pixel[..3])?;
+    }
+    Ok(())
+}
+
+/// Writes RGBA8 pixels as an uncompressed PNG.
+///
+/// Fails with [`std::io::ErrorKind::InvalidInput`] if `rgba` holds fewer than
+/// `width * height` pixels.
+#[cfg(feature = "std")]
+pub fn write_png<W: std::io::Write>(
+    mut writer: W,
+    width: u32,
+    height: u32,
+    rgba: &[u8],
+) -> std::io::Result<()> {
+    use std::vec::Vec;
+
+    let rgba = pixels(width, height, rgba)?;
+
+    // Every row starts with filter type 0.
+    let stride = width as usize * 4;
+    let mut raw = Vec::with_capacity((stride + 1) * height as usize);
+    for line in rgba.chunks_exact(stride.max(1)) {
+        raw.push(0);
+        raw.extend_from_slice(line);
+    }
+
+    // zlib stream of stored deflate blocks.
+    let mut zlib = Vec::with_capacity(raw.len() + raw.len() / 0xFFFF * 5 + 11);
+    zlib.extend_from_slice(&[0x78, 0x01]);
+    let mut blocks = raw.chunks(0xFFFF).peekable();
+    if blocks.peek().is_none() {
+        zlib.extend_from_slice(&[1, 0, 0, 0xFF, 0xFF]);
+    }
+    while let Some(block) = blocks.next() {
+        let len = block.len() as u16;
+        zlib.push(blocks.peek().is_none() as u8);
+        zlib.extend_from_slice(&len.to_le_bytes());
+        zlib.extend_from_slice(&(!len).to_le_bytes());
+        zlib.extend_from_slice(block);
+    }
+    zlib.extend_from_slice(&adler32(&raw).to_be_bytes());
+
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&width.to_be_bytes());
+    header.extend_from_slice(&height.to_be_bytes());
+    header.extend_from_slice(&[8, 6, 0, 0, 0]);
+
+    writer.write_all(b"\x89PNG\r\n\x1A\n")?;
+    write_chunk(&mut writer, b"IHDR", &header)?;
+    write_chunk(&mut writer, b"IDAT", &zlib)?;
+    write_chunk(&mut writer, b"IEND", &[])
+}
+
+/// The first `width * height` pixels of `rgba`.
+#[cfg(feature = "std")]
+fn pixels(width: u32, height: u32, rgba: &[u8]) -> std::io::Result<&[u8]> {
+    (width as usize)
+        .checked_mul(height as usize)
+        .and_then(|pixels| pixels.checked_mul(4))
+        .and_then(|len| rgba.get(..len))
+        .ok_or_else(|| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "fewer pixels than width * height",
+            )
+        })
+}
+
+#[cfg(feature = "std")]
+fn write_chunk<W: std::io::Write>(
+    writer: &mut W,
+    kind: &[u8; 4],
+    data: &[u8],
+) -> std::io::Result<()> {
+    let mut crc = !0u32;
+    for byte in kind.iter().chain(data) {
+        crc ^= u32::from(*byte);
+        for _ in 0..8 {
+            crc = (crc >> 1) ^ (0xEDB8_8320 & (crc & 1).wrapping_neg());
+        }
+    }
+
+    writer.write_all(&(data.len() as u32).to_be_bytes())?;
+    writer.write_all(kind)?;
+    writer.write_all(data)?;
+    writer.write_all(&(!crc).to_be_bytes())
+}
+
+#[cfg(feature = "std")]
+fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for byte in data {
+        a = (a + u32::from(*byte)) % 65521;
+        b = (b + a) % 65521;
+    }
+    (b << 16) | a
+}
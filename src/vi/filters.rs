@@ -0,0 +1,205 @@
+//! # VI filters
+//!
+//! The output filters in `VI_STATUS` only work in certain combinations.
+//! [`ViFilters`] builds status values from the combinations known to work
+//! and checks values from elsewhere against them:
+//!
+//! - Serrated sync must be on exactly when the mode is interlaced, or the
+//!   picture jitters or loses vertical lock.
+//! - The divot filter works on the coverage computed by antialiasing, and
+//!   shows garbage at edges without it.
+//! - The dither filter reconstructs 8-bit colour from dithered 16-bit
+//!   pixels, and corrupts 32-bit ones.
+//! - Full antialiasing fetches extra lines for every line, which exceeds the
+//!   VI's RDRAM bandwidth with 32-bit pixels; libultra never uses it.
+//! - The VBus clock, test mode and kill WE bits are for factory testing and
+//!   can hang the VI or damage the hardware.
+//!
+//! The pixel advance, which delays pixel fetches relative to the video
+//! clock, is 3 in every libultra mode.
+
+use super::{AntialiasMode, PixelSize, ViStatusReg};
+
+/// # Default pixel advance
+pub const DEFAULT_PIXEL_ADVANCE: u8 = 3;
+
+/// # Filter error
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FilterError {
+    /// The pixel size is blank or reserved.
+    InvalidPixelSize,
+
+    /// Serrated sync does not match the interlacing of the mode.
+    SerrateMismatch,
+
+    /// The divot filter is on without a coverage antialias mode.
+    DivotWithoutAntialias,
+
+    /// The dither filter is on with 32-bit pixels.
+    DitherFilterWith32Bit,
+
+    /// Full antialiasing is on with 32-bit pixels.
+    FullAntialiasWith32Bit,
+
+    /// A factory test bit is set.
+    TestBitSet,
+}
+
+/// # VI filter configuration
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ViFilters {
+    pixel_size: PixelSize,
+    antialias_mode: AntialiasMode,
+    gamma: bool,
+    gamma_dither: bool,
+    divot: bool,
+    dither_filter: bool,
+    pixel_advance: u8,
+}
+
+impl ViFilters {
+    /// Starts a configuration for `pixel_size`.
+    ///
+    /// Defaults to the libultra filters: gamma correction, gamma dither for
+    /// 16-bit pixels, and antialiasing with resampling and divot filtering.
+    pub const fn new(pixel_size: PixelSize) -> Self {
+        Self {
+            pixel_size,
+            antialias_mode: AntialiasMode::Optimized,
+            gamma: true,
+            gamma_dither: matches!(pixel_size, PixelSize::SixteenBit),
+            divot: true,
+            dither_filter: false,
+            pixel_advance: DEFAULT_PIXEL_ADVANCE,
+        }
+    }
+
+    /// Sets the antialias mode.
+    ///
+    /// Turning antialiasing off also turns the divot filter off.
+    pub const fn antialias_mode(mut self, antialias_mode: AntialiasMode) -> Self {
+        self.antialias_mode = antialias_mode;
+        if !has_coverage(antialias_mode) {
+            self.divot = false;
+        }
+        self
+    }
+
+    /// Turns gamma correction on or off.
+    pub const fn gamma(mut self, gamma: bool) -> Self {
+        self.gamma = gamma;
+        self
+    }
+
+    /// Turns gamma dither on or off.
+    pub const fn gamma_dither(mut self, gamma_dither: bool) -> Self {
+        self.gamma_dither = gamma_dither;
+        self
+    }
+
+    /// Turns the divot filter on or off.
+    pub const fn divot(mut self, divot: bool) -> Self {
+        self.divot = divot;
+        self
+    }
+
+    /// Turns the dither filter on or off.
+    pub const fn dither_filter(mut self, dither_filter: bool) -> Self {
+        self.dither_filter = dither_filter;
+        self
+    }
+
+    /// Sets the pixel advance, which is masked to four bits.
+    pub const fn pixel_advance(mut self, pixel_advance: u8) -> Self {
+        self.pixel_advance = pixel_advance & 0xF;
+        self
+    }
+
+    /// Builds the status value for a mode with the given interlacing.
+    pub fn build(&self, interlaced: bool) -> Result<ViStatusReg, FilterError> {
+        let status = self.status(interlaced);
+        Self::validate(status, interlaced)?;
+        Ok(status)
+    }
+
+    /// The status value for a mode with the given interlacing, unchecked.
+    ///
+    /// Usable in constants, such as the video mode presets.
+    pub const fn status(&self, interlaced: bool) -> ViStatusReg {
+        let pixel_size = match self.pixel_size {
+            PixelSize::Blank => 0,
+            PixelSize::SixteenBit => 2,
+            PixelSize::ThirtyTwoBit => 3,
+        };
+        let antialias_mode = match self.antialias_mode {
+            AntialiasMode::Full => 0,
+            AntialiasMode::Optimized => 1,
+            AntialiasMode::ResampleOnly => 2,
+            AntialiasMode::None => 3,
+        };
+
+        ViStatusReg(
+            pixel_size
+                | (self.gamma_dither as u32) << 2
+                | (self.gamma as u32) << 3
+                | (self.divot as u32) << 4
+                | (interlaced as u32) << 6
+                | antialias_mode << 8
+                | (self.pixel_advance as u32) << 12
+                | (self.dither_filter as u32) << 16,
+        )
+    }
+
+    /// Reads back the filters of a status value, checking it for a mode with
+    /// the given interlacing.
+    pub fn from_status(status: ViStatusReg, interlaced: bool) -> Result<Self, FilterError> {
+        Self::validate(status, interlaced)?;
+
+        Ok(Self {
+            pixel_size: status.pixel_size(),
+            antialias_mode: status.antialias_mode(),
+            gamma: status.gamma_enable(),
+            gamma_dither: status.gamma_dither_enable(),
+            divot: status.divot_enable(),
+            dither_filter: status.dither_filter_enable(),
+            pixel_advance: status.pixel_advance().into(),
+        })
+    }
+
+    /// Checks a status value for a mode with the given interlacing.
+    pub fn validate(status: ViStatusReg, interlaced: bool) -> Result<(), FilterError> {
+        let thirty_two_bit = match status.raw() & 0b11 {
+            2 => false,
+            3 => true,
+            _ => return Err(FilterError::InvalidPixelSize),
+        };
+
+        if status.vbus_clock_enable() || status.test_mode() || status.kill_we() {
+            return Err(FilterError::TestBitSet);
+        }
+
+        if status.serrate() != interlaced {
+            return Err(FilterError::SerrateMismatch);
+        }
+
+        let antialias_mode = status.antialias_mode();
+        if status.divot_enable() && !has_coverage(antialias_mode) {
+            return Err(FilterError::DivotWithoutAntialias);
+        }
+
+        if thirty_two_bit && status.dither_filter_enable() {
+            return Err(FilterError::DitherFilterWith32Bit);
+        }
+
+        if thirty_two_bit && antialias_mode == AntialiasMode::Full {
+            return Err(FilterError::FullAntialiasWith32Bit);
+        }
+
+        Ok(())
+    }
+}
+
+/// Whether `mode` computes the coverage the divot filter needs.
+const fn has_coverage(mode: AntialiasMode) -> bool {
+    matches!(mode, AntialiasMode::Full | AntialiasMode::Optimized)
+}
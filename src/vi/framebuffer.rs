@@ -0,0 +1,165 @@
+//! # Typed framebuffer
+//!
+//! [`Framebuffer`] views a block of memory as rows of one [`Pixel`] type. Its
+//! constructors check the pixel type against the VI's pixel size, so drawing
+//! code cannot write 16-bit pixels into a buffer the VI reads as 32-bit.
+
+use core::marker::PhantomData;
+use core::ptr::read_volatile;
+
+use super::{mode::VideoMode, pixel::Pixel, PixelSize, Vi, ViStatusReg};
+
+/// # Framebuffer error
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FramebufferError {
+    /// The pixel type does not match the VI's pixel size.
+    PixelSizeMismatch,
+
+    /// The memory block is smaller than the framebuffer.
+    TooSmall,
+
+    /// The framebuffer's size in bytes does not fit in a `usize`.
+    TooLarge,
+}
+
+/// # Framebuffer
+pub struct Framebuffer<'a, P: Pixel> {
+    data: &'a mut [u8],
+    width: u32,
+    height: u32,
+    _pixel: PhantomData<P>,
+}
+
+impl<'a, P: Pixel> Framebuffer<'a, P> {
+    /// Views `data` as `height` lines of `width` pixels, for a VI showing
+    /// pixels of `size`.
+    pub fn new(
+        data: &'a mut [u8],
+        size: PixelSize,
+        width: u32,
+        height: u32,
+    ) -> Result<Self, FramebufferError> {
+        if size != P::SIZE {
+            return Err(FramebufferError::PixelSizeMismatch);
+        }
+        if data.len() < Self::len(width, height)? {
+            return Err(FramebufferError::TooSmall);
+        }
+
+        Ok(Self {
+            data,
+            width,
+            height,
+            _pixel: PhantomData,
+        })
+    }
+
+    /// Views `data` as a framebuffer for `mode`.
+    pub fn for_mode(data: &'a mut [u8], mode: &VideoMode) -> Result<Self, FramebufferError> {
+        Self::new(
+            data,
+            pixel_size(mode.status)?,
+            mode.framebuffer_width(),
+            mode.framebuffer_height(),
+        )
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// The pixel at `(x, y)`, or `None` if out of bounds.
+    pub fn get(&self, x: u32, y: u32) -> Option<P> {
+        let offset = self.offset(x, y)?;
+        Some(P::read(&self.data[offset..]))
+    }
+
+    /// Sets the pixel at `(x, y)`, ignoring coordinates out of bounds.
+    pub fn set(&mut self, x: u32, y: u32, pixel: P) {
+        if let Some(offset) = self.offset(x, y) {
+            pixel.write(&mut self.data[offset..]);
+        }
+    }
+
+    /// Sets every pixel.
+    pub fn fill(&mut self, pixel: P) {
+        // Checked against the memory block in `new`.
+        let len = self.width as usize * self.height as usize * P::BYTES;
+        for bytes in self.data[..len].chunks_exact_mut(P::BYTES) {
+            pixel.write(bytes);
+        }
+    }
+
+    /// Raw big-endian contents.
+    pub fn as_bytes(&self) -> &[u8] {
+        self.data
+    }
+
+    /// Mutable raw big-endian contents.
+    pub fn as_bytes_mut(&mut self) -> &mut [u8] {
+        self.data
+    }
+
+    fn offset(&self, x: u32, y: u32) -> Option<usize> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        (y as usize)
+            .checked_mul(self.width as usize)?
+            .checked_add(x as usize)?
+            .checked_mul(P::BYTES)
+    }
+
+    /// Bytes in `height` lines of `width` pixels.
+    fn len(width: u32, height: u32) -> Result<usize, FramebufferError> {
+        (width as usize)
+            .checked_mul(height as usize)
+            .and_then(|pixels| pixels.checked_mul(P::BYTES))
+            .ok_or(FramebufferError::TooLarge)
+    }
+}
+
+impl<P: Pixel> Framebuffer<'static, P> {
+    /// Views the first `height` lines of the framebuffer the VI is scanning
+    /// out in `mode`, through KSEG1.
+    ///
+    /// The dimensions come from `mode`, as the VI width register holds the
+    /// line stride, which is twice the width in high resolution interlaced
+    /// modes. The origin of the field being scanned out is moved back to the
+    /// start of the framebuffer, and `height` is clamped to the mode's.
+    ///
+    /// # Safety
+    ///
+    /// The VI must be displaying a framebuffer in valid RDRAM with `mode`,
+    /// and nothing else may access that memory while the view is live.
+    pub unsafe fn displayed(
+        vi: &Vi,
+        mode: &VideoMode,
+        height: u32,
+    ) -> Result<Self, FramebufferError> {
+        let size = pixel_size(read_volatile(&vi.vi_status_reg))?;
+        let width = mode.framebuffer_width();
+        let height = height.min(mode.framebuffer_height());
+
+        let offset = mode.fields[vi.current_field()].origin_offset;
+        let origin = read_volatile(&vi.vi_origin_reg).raw().wrapping_sub(offset) & 0x00FF_FFFF;
+
+        let len = Self::len(width, height)?;
+        let data = core::slice::from_raw_parts_mut((0xA000_0000 | origin) as usize as *mut u8, len);
+        Self::new(data, size, width, height)
+    }
+}
+
+/// Pixel size of `status`, decoded from the raw bits as converting the
+/// reserved size 1 panics. No pixel type matches a blank or reserved size.
+fn pixel_size(status: ViStatusReg) -> Result<PixelSize, FramebufferError> {
+    match status.raw() & 0b11 {
+        2 => Ok(PixelSize::SixteenBit),
+        3 => Ok(PixelSize::ThirtyTwoBit),
+        _ => Err(FramebufferError::PixelSizeMismatch),
+    }
+}
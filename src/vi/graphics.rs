@@ -0,0 +1,102 @@
+//! # embedded-graphics support
+//!
+//! [`Rgba5551`] and [`Rgba8888`] are embedded-graphics colours, and a
+//! [`Framebuffer`] of either is a `DrawTarget`. Colours from the
+//! embedded-graphics `Rgb555` and `Rgb888` types convert losslessly and are
+//! opaque.
+
+use core::convert::Infallible;
+
+use embedded_graphics_core::{
+    draw_target::DrawTarget,
+    geometry::{Dimensions, OriginDimensions, Size},
+    pixelcolor::{
+        raw::{RawU16, RawU32},
+        Rgb555, Rgb888, RgbColor,
+    },
+    primitives::Rectangle,
+    Pixel as EgPixel,
+};
+
+use super::{
+    framebuffer::Framebuffer,
+    pixel::{Pixel, Rgba5551, Rgba8888},
+};
+
+impl embedded_graphics_core::pixelcolor::PixelColor for Rgba5551 {
+    type Raw = RawU16;
+}
+
+impl embedded_graphics_core::pixelcolor::PixelColor for Rgba8888 {
+    type Raw = RawU32;
+}
+
+impl From<Rgb555> for Rgba5551 {
+    fn from(value: Rgb555) -> Self {
+        Self::new(value.r(), value.g(), value.b(), true)
+    }
+}
+
+impl From<Rgba5551> for Rgb555 {
+    fn from(value: Rgba5551) -> Self {
+        Rgb555::new(value.r(), value.g(), value.b())
+    }
+}
+
+impl From<Rgb888> for Rgba8888 {
+    fn from(value: Rgb888) -> Self {
+        Self::from_rgb8(value.r(), value.g(), value.b())
+    }
+}
+
+impl From<Rgba8888> for Rgb888 {
+    fn from(value: Rgba8888) -> Self {
+        Rgb888::new(value.r(), value.g(), value.b())
+    }
+}
+
+impl<P> OriginDimensions for Framebuffer<'_, P>
+where
+    P: Pixel + embedded_graphics_core::pixelcolor::PixelColor,
+{
+    fn size(&self) -> Size {
+        Size::new(self.width(), self.height())
+    }
+}
+
+impl<P> DrawTarget for Framebuffer<'_, P>
+where
+    P: Pixel + embedded_graphics_core::pixelcolor::PixelColor,
+{
+    type Color = P;
+    type Error = Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = EgPixel<Self::Color>>,
+    {
+        for EgPixel(point, color) in pixels {
+            if let (Ok(x), Ok(y)) = (u32::try_from(point.x), u32::try_from(point.y)) {
+                self.set(x, y, color);
+            }
+        }
+        Ok(())
+    }
+
+    fn fill_solid(&mut self, area: &Rectangle, color: Self::Color) -> Result<(), Self::Error> {
+        let area = area.intersection(&self.bounding_box());
+        if let Some(bottom_right) = area.bottom_right() {
+            for y in area.top_left.y..=bottom_right.y {
+                for x in area.top_left.x..=bottom_right.x {
+                    self.set(x as u32, y as u32, color);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn clear(&mut self, color: Self::Color) -> Result<(), Self::Error> {
+        self.fill(color);
+        Ok(())
+    }
+}
@@ -0,0 +1,357 @@
+//! # Video modes
+//!
+//! Register presets equivalent to libultra's `osViMode*` tables. Each mode is
+//! named after its libultra counterpart:
+//!
+//! - `L`/`H`: 320 or 640 pixels per line.
+//! - `P`/`A`: point sampled, or antialiased with divot filtering.
+//! - `N`/`F`: non-interlaced, or interlaced.
+//! - `1`/`2`: 16-bit or 32-bit pixels.
+//!
+//! Interlaced modes have separate register sets for the even and odd fields.
+
+use core::ptr::{read_volatile, write_volatile};
+
+use crate::boot::TvType;
+
+use super::{
+    filters::ViFilters, AntialiasMode, PixelSize, Vi, ViHSyncLeapReg, ViHSyncReg, ViHVideoReg,
+    ViIntrReg, ViOriginReg, ViStatusReg, ViTimingReg, ViVBurstReg, ViVSyncReg, ViVVideoReg,
+    ViWidthReg, ViXScaleReg, ViYScaleReg,
+};
+
+/// # Video standard
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VideoStandard {
+    Ntsc,
+    Pal,
+    Mpal,
+    /// PAL colour encoding with 525-line, 60 Hz timing.
+    Pal60,
+}
+
+impl VideoStandard {
+    /// VI clock in Hz, which also drives the AI.
+    pub const fn clock_hz(self) -> u32 {
+        match self {
+            VideoStandard::Ntsc => 48_681_812,
+            VideoStandard::Pal | VideoStandard::Pal60 => 49_656_530,
+            VideoStandard::Mpal => 48_628_316,
+        }
+    }
+}
+
+impl From<TvType> for VideoStandard {
+    fn from(value: TvType) -> Self {
+        match value {
+            TvType::Ntsc => VideoStandard::Ntsc,
+            TvType::Pal => VideoStandard::Pal,
+            TvType::Mpal => VideoStandard::Mpal,
+        }
+    }
+}
+
+/// # Per-field registers
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FieldRegisters {
+    /// Byte offset of the field's first line from the framebuffer start.
+    pub origin_offset: u32,
+    pub y_scale: ViYScaleReg,
+    pub v_video: ViVVideoReg,
+    pub v_burst: ViVBurstReg,
+    pub intr: ViIntrReg,
+}
+
+/// Sync and burst timing shared by every mode of a standard.
+pub(super) struct StandardTiming {
+    pub timing: u32,
+    pub v_sync: u32,
+    pub h_sync: u32,
+    pub h_sync_leap: u32,
+    pub h_video: u32,
+    pub v_burst: [u32; 2],
+}
+
+impl StandardTiming {
+    pub const fn new(standard: VideoStandard, interlaced: bool) -> Self {
+        let (timing, h_sync, h_sync_leap, h_video) = match standard {
+            VideoStandard::Ntsc => (0x03E5_2239, 0x0000_0C15, 0x0C15_0C15, 0x006C_02EC),
+            VideoStandard::Pal | VideoStandard::Pal60 => {
+                (0x0404_233A, 0x0015_0C69, 0x0C6F_0C6E, 0x0080_0300)
+            }
+            VideoStandard::Mpal => (0x0465_1E39, 0x0004_0C11, 0x0C19_0C1A, 0x006C_02EC),
+        };
+
+        let (v_sync, v_burst) = match (standard, interlaced) {
+            (VideoStandard::Pal, false) => (0x271, [0x0009_026B; 2]),
+            (VideoStandard::Pal, true) => (0x270, [0x0009_026B, 0x000D_0269]),
+            (_, false) => (0x20D, [0x000E_0204; 2]),
+            (_, true) => (0x20C, [0x000E_0204; 2]),
+        };
+
+        Self {
+            timing,
+            v_sync,
+            h_sync,
+            h_sync_leap,
+            h_video,
+            v_burst,
+        }
+    }
+}
+
+impl FieldRegisters {
+    const fn new(origin_offset: u32, y_offset: u32, v_video: u32, v_burst: u32) -> Self {
+        Self {
+            origin_offset,
+            y_scale: ViYScaleReg(0x400 | (y_offset << 16)),
+            v_video: ViVVideoReg(v_video),
+            v_burst: ViVBurstReg(v_burst),
+            intr: ViIntrReg(2),
+        }
+    }
+}
+
+/// # Video mode
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VideoMode {
+    pub standard: VideoStandard,
+    pub(crate) framebuffer_width: u32,
+    pub(crate) framebuffer_height: u32,
+    pub status: ViStatusReg,
+    pub width: ViWidthReg,
+    pub timing: ViTimingReg,
+    pub v_sync: ViVSyncReg,
+    pub h_sync: ViHSyncReg,
+    pub h_sync_leap: ViHSyncLeapReg,
+    pub h_video: ViHVideoReg,
+    pub x_scale: ViXScaleReg,
+    pub fields: [FieldRegisters; 2],
+}
+
+impl VideoMode {
+    /// Builds a preset from its libultra name components.
+    pub const fn preset(
+        standard: VideoStandard,
+        high_resolution: bool,
+        antialiased: bool,
+        interlaced: bool,
+        thirty_two_bit: bool,
+    ) -> Self {
+        let timing = StandardTiming::new(standard, interlaced);
+        let v_video = match standard {
+            VideoStandard::Pal => 0x005F_0239,
+            _ => 0x0025_01FF,
+        };
+
+        // The even field starts one line earlier.
+        let v_video = match interlaced {
+            true => [v_video - 0x0002_0002, v_video],
+            false => [v_video; 2],
+        };
+        let v_burst = timing.v_burst;
+
+        let bytes_per_pixel = if thirty_two_bit { 4 } else { 2 };
+        let pixels = if high_resolution { 640 } else { 320 };
+
+        // The libultra filters, with point sampling for the non-antialiased
+        // modes.
+        let pixel_size = match thirty_two_bit {
+            true => PixelSize::ThirtyTwoBit,
+            false => PixelSize::SixteenBit,
+        };
+        let filters = match antialiased {
+            true => ViFilters::new(pixel_size),
+            false => ViFilters::new(pixel_size).antialias_mode(AntialiasMode::None),
+        };
+
+        // High resolution interlaced modes show alternate framebuffer lines
+        // in each field; low resolution ones show every line twice, with the
+        // odd field shifted down half a line. Like libultra's tables, every
+        // field starts one line into its framebuffer, or two for the odd field
+        // of high resolution interlaced modes.
+        let line = pixels * bytes_per_pixel;
+        let (width, height, origin_offsets, y_offsets) = match (high_resolution, interlaced) {
+            (true, true) => (pixels * 2, 480, [line, line * 2], [0, 0]),
+            (false, true) => (pixels, 240, [line, line], [0, 0x200]),
+            (_, false) => (pixels, 240, [line, line], [0, 0]),
+        };
+
+        let x_scale = if high_resolution { 0x400 } else { 0x200 };
+
+        Self {
+            standard,
+            framebuffer_width: pixels,
+            framebuffer_height: height,
+            status: filters.status(interlaced),
+            width: ViWidthReg(width),
+            timing: ViTimingReg(timing.timing),
+            v_sync: ViVSyncReg(timing.v_sync),
+            h_sync: ViHSyncReg(timing.h_sync),
+            h_sync_leap: ViHSyncLeapReg(timing.h_sync_leap),
+            h_video: ViHVideoReg(timing.h_video),
+            x_scale: ViXScaleReg(x_scale),
+            fields: [
+                FieldRegisters::new(origin_offsets[0], y_offsets[0], v_video[0], v_burst[0]),
+                FieldRegisters::new(origin_offsets[1], y_offsets[1], v_video[1], v_burst[1]),
+            ],
+        }
+    }
+
+    /// Framebuffer width in pixels.
+    pub const fn framebuffer_width(&self) -> u32 {
+        self.framebuffer_width
+    }
+
+    /// Framebuffer height in lines.
+    pub const fn framebuffer_height(&self) -> u32 {
+        self.framebuffer_height
+    }
+
+    /// Whether the mode is interlaced.
+    pub const fn is_interlaced(&self) -> bool {
+        self.v_sync.0 & 1 == 0
+    }
+
+    /// Origin register value for `field` of a framebuffer at `framebuffer`.
+    pub const fn origin(&self, framebuffer: u32, field: usize) -> ViOriginReg {
+        ViOriginReg((framebuffer + self.fields[field & 1].origin_offset) & 0x00FF_FFFF)
+    }
+}
+
+macro_rules! presets {
+    [$($standard:ident => $table:ident {
+        $($name:ident => $high:literal, $aa:literal, $interlaced:literal, $wide:literal;)*
+    })*] => {
+        $(
+            $(
+                #[doc = concat!("# `", stringify!($name), "`")]
+                pub const $name: VideoMode = VideoMode::preset(
+                    VideoStandard::$standard,
+                    $high,
+                    $aa,
+                    $interlaced,
+                    $wide,
+                );
+            )*
+
+            #[doc = concat!("# ", stringify!($standard), " presets")]
+            ///
+            /// In libultra order (`LPN1` through `HPF2`).
+            pub const $table: [VideoMode; 14] = [$($name,)*];
+        )*
+    };
+}
+
+presets! [
+    Ntsc => NTSC {
+        NTSC_LPN1 => false, false, false, false;
+        NTSC_LPF1 => false, false, true, false;
+        NTSC_LAN1 => false, true, false, false;
+        NTSC_LAF1 => false, true, true, false;
+        NTSC_LPN2 => false, false, false, true;
+        NTSC_LPF2 => false, false, true, true;
+        NTSC_LAN2 => false, true, false, true;
+        NTSC_LAF2 => false, true, true, true;
+        NTSC_HPN1 => true, false, false, false;
+        NTSC_HPF1 => true, false, true, false;
+        NTSC_HAN1 => true, true, false, false;
+        NTSC_HAF1 => true, true, true, false;
+        NTSC_HPN2 => true, false, false, true;
+        NTSC_HPF2 => true, false, true, true;
+    }
+    Pal => PAL {
+        PAL_LPN1 => false, false, false, false;
+        PAL_LPF1 => false, false, true, false;
+        PAL_LAN1 => false, true, false, false;
+        PAL_LAF1 => false, true, true, false;
+        PAL_LPN2 => false, false, false, true;
+        PAL_LPF2 => false, false, true, true;
+        PAL_LAN2 => false, true, false, true;
+        PAL_LAF2 => false, true, true, true;
+        PAL_HPN1 => true, false, false, false;
+        PAL_HPF1 => true, false, true, false;
+        PAL_HAN1 => true, true, false, false;
+        PAL_HAF1 => true, true, true, false;
+        PAL_HPN2 => true, false, false, true;
+        PAL_HPF2 => true, false, true, true;
+    }
+    Mpal => MPAL {
+        MPAL_LPN1 => false, false, false, false;
+        MPAL_LPF1 => false, false, true, false;
+        MPAL_LAN1 => false, true, false, false;
+        MPAL_LAF1 => false, true, true, false;
+        MPAL_LPN2 => false, false, false, true;
+        MPAL_LPF2 => false, false, true, true;
+        MPAL_LAN2 => false, true, false, true;
+        MPAL_LAF2 => false, true, true, true;
+        MPAL_HPN1 => true, false, false, false;
+        MPAL_HPF1 => true, false, true, false;
+        MPAL_HAN1 => true, true, false, false;
+        MPAL_HAF1 => true, true, true, false;
+        MPAL_HPN2 => true, false, false, true;
+        MPAL_HPF2 => true, false, true, true;
+    }
+    Pal60 => PAL60 {
+        PAL60_LPN1 => false, false, false, false;
+        PAL60_LPF1 => false, false, true, false;
+        PAL60_LAN1 => false, true, false, false;
+        PAL60_LAF1 => false, true, true, false;
+        PAL60_LPN2 => false, false, false, true;
+        PAL60_LPF2 => false, false, true, true;
+        PAL60_LAN2 => false, true, false, true;
+        PAL60_LAF2 => false, true, true, true;
+        PAL60_HPN1 => true, false, false, false;
+        PAL60_HPF1 => true, false, true, false;
+        PAL60_HAN1 => true, true, false, false;
+        PAL60_HAF1 => true, true, true, false;
+        PAL60_HPN2 => true, false, false, true;
+        PAL60_HPF2 => true, false, true, true;
+    }
+];
+
+/// Returns the preset table for `standard`.
+pub const fn presets(standard: VideoStandard) -> &'static [VideoMode; 14] {
+    match standard {
+        VideoStandard::Ntsc => &NTSC,
+        VideoStandard::Pal => &PAL,
+        VideoStandard::Mpal => &MPAL,
+        VideoStandard::Pal60 => &PAL60,
+    }
+}
+
+impl Vi {
+    /// Switches to `mode`, showing the framebuffer at physical address
+    /// `framebuffer`.
+    ///
+    /// The framebuffer is passed in rather than read back from the origin
+    /// register, which holds the previous mode's field offset.
+    ///
+    /// Output is blanked while the timing registers change and re-enabled at
+    /// the start of the next field, so the TV never sees a partial mode.
+    pub fn set_mode(&mut self, mode: &VideoMode, framebuffer: u32) {
+        unsafe {
+            // Stop fetching pixels and blank the active area.
+            write_volatile(&mut self.vi_status_reg, ViStatusReg(0));
+            write_volatile(&mut self.vi_h_video_reg, ViHVideoReg(0));
+
+            write_volatile(&mut self.vi_timing_reg, mode.timing);
+            write_volatile(&mut self.vi_v_sync_reg, mode.v_sync);
+            write_volatile(&mut self.vi_h_sync_reg, mode.h_sync);
+            write_volatile(&mut self.vi_h_sync_leap_reg, mode.h_sync_leap);
+            write_volatile(&mut self.vi_v_burst_reg, mode.fields[0].v_burst);
+            write_volatile(&mut self.vi_v_video_reg, mode.fields[0].v_video);
+            write_volatile(&mut self.vi_x_scale_reg, mode.x_scale);
+            write_volatile(&mut self.vi_y_scale_reg, mode.fields[0].y_scale);
+            write_volatile(&mut self.vi_width_reg, mode.width);
+            write_volatile(&mut self.vi_origin_reg, mode.origin(framebuffer, 0));
+            write_volatile(&mut self.vi_intr_reg, mode.fields[0].intr);
+
+            // Unblank at the start of a field.
+            while read_volatile(&self.vi_current_reg).0 & 0x3FE != 0 {}
+
+            write_volatile(&mut self.vi_status_reg, mode.status);
+            write_volatile(&mut self.vi_h_video_reg, mode.h_video);
+        }
+    }
+}
@@ -0,0 +1,321 @@
+//! # Test patterns
+//!
+//! Calibration patterns for tuning the video window position and scaling on
+//! a given TV or capture card, and a [`ModeSelector`] to step through the
+//! presets of a standard while nudging the window.
+
+use super::{
+    framebuffer::Framebuffer,
+    mode::{presets, VideoMode, VideoStandard},
+    pixel::{Pixel, Rgba8888},
+    timing::{self, TimingRegisters, TimingReport},
+    ViHVideoReg, ViVVideoReg,
+};
+
+/// # Test pattern
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TestPattern {
+    /// SMPTE colour bars: 75% bars, the reverse blue bars, and a bottom row
+    /// with -I, white, +Q and a PLUGE for setting black level.
+    ColorBars,
+
+    /// White grid lines every `spacing` pixels, with the outermost lines on
+    /// the framebuffer edges.
+    Crosshatch { spacing: u32 },
+
+    /// Framebuffer edge, action safe (90%) and title safe (80%) outlines
+    /// around a centre cross.
+    SafeArea,
+
+    /// Grey, red, green and blue ramps from black to full intensity, for
+    /// checking gamma.
+    Ramps,
+}
+
+const fn gray(level: u8) -> Rgba8888 {
+    Rgba8888::from_rgb8(level, level, level)
+}
+
+const BARS: [Rgba8888; 7] = [
+    gray(0xBF),
+    Rgba8888::from_rgb8(0xBF, 0xBF, 0x00),
+    Rgba8888::from_rgb8(0x00, 0xBF, 0xBF),
+    Rgba8888::from_rgb8(0x00, 0xBF, 0x00),
+    Rgba8888::from_rgb8(0xBF, 0x00, 0xBF),
+    Rgba8888::from_rgb8(0xBF, 0x00, 0x00),
+    Rgba8888::from_rgb8(0x00, 0x00, 0xBF),
+];
+
+const REVERSE_BARS: [Rgba8888; 7] = [
+    BARS[6],
+    Rgba8888::BLACK,
+    BARS[4],
+    Rgba8888::BLACK,
+    BARS[2],
+    Rgba8888::BLACK,
+    BARS[0],
+];
+
+const MINUS_I: Rgba8888 = Rgba8888::from_rgb8(0x00, 0x21, 0x4C);
+const PLUS_Q: Rgba8888 = Rgba8888::from_rgb8(0x32, 0x00, 0x6A);
+
+/// Draws `pattern` over the whole of `framebuffer`.
+pub fn draw<P: Pixel + From<Rgba8888>>(framebuffer: &mut Framebuffer<P>, pattern: TestPattern) {
+    let (width, height) = (framebuffer.width(), framebuffer.height());
+    framebuffer.fill(P::from(Rgba8888::BLACK));
+
+    match pattern {
+        TestPattern::ColorBars => {
+            let bars_end = height * 2 / 3;
+            let reverse_end = height * 3 / 4;
+            let column = |index: u32| width * index / 7;
+
+            for (index, (bar, reverse)) in BARS.iter().zip(REVERSE_BARS.iter()).enumerate() {
+                let (left, right) = (column(index as u32), column(index as u32 + 1));
+                fill(framebuffer, left, 0, right, bars_end, *bar);
+                fill(framebuffer, left, bars_end, right, reverse_end, *reverse);
+            }
+
+            // The bottom row splits the first five bars' width into four,
+            // then three PLUGE stripes under the sixth bar.
+            let quarter = |index: u32| column(5) * index / 4;
+            let bottom = [MINUS_I, Rgba8888::WHITE, PLUS_Q];
+            for (index, color) in bottom.iter().enumerate() {
+                let index = index as u32;
+                fill(
+                    framebuffer,
+                    quarter(index),
+                    reverse_end,
+                    quarter(index + 1),
+                    height,
+                    *color,
+                );
+            }
+
+            let stripe = |index: u32| column(5) + (column(6) - column(5)) * index / 3;
+            fill(
+                framebuffer,
+                stripe(1),
+                reverse_end,
+                stripe(2),
+                height,
+                gray(0x05),
+            );
+            fill(
+                framebuffer,
+                stripe(2),
+                reverse_end,
+                stripe(3),
+                height,
+                gray(0x0A),
+            );
+        }
+
+        TestPattern::Crosshatch { spacing } => {
+            let spacing = spacing.max(1);
+            let white = P::from(Rgba8888::WHITE);
+            for y in 0..height {
+                for x in 0..width {
+                    let on_x = x % spacing == 0 || x + 1 == width;
+                    let on_y = y % spacing == 0 || y + 1 == height;
+                    if on_x || on_y {
+                        framebuffer.set(x, y, white);
+                    }
+                }
+            }
+        }
+
+        TestPattern::SafeArea => {
+            outline(framebuffer, 0, 0, width, height, Rgba8888::WHITE);
+            let (x, y) = (width / 20, height / 20);
+            outline(framebuffer, x, y, width - x, height - y, Rgba8888::GREEN);
+            let (x, y) = (width / 10, height / 10);
+            outline(framebuffer, x, y, width - x, height - y, Rgba8888::RED);
+
+            let (center_x, center_y) = (width / 2, height / 2);
+            let arm = width.min(height) / 16;
+            let cross = Rgba8888::WHITE;
+            let left = center_x.saturating_sub(arm);
+            let top = center_y.saturating_sub(arm);
+            fill(
+                framebuffer,
+                left,
+                center_y,
+                center_x + arm + 1,
+                center_y + 1,
+                cross,
+            );
+            fill(
+                framebuffer,
+                center_x,
+                top,
+                center_x + 1,
+                center_y + arm + 1,
+                cross,
+            );
+        }
+
+        TestPattern::Ramps => {
+            let channels = [(1, 1, 1), (1, 0, 0), (0, 1, 0), (0, 0, 1)];
+            for (band, (r, g, b)) in channels.iter().enumerate() {
+                let (top, bottom) = (height * band as u32 / 4, height * (band as u32 + 1) / 4);
+                for x in 0..width {
+                    let level = (x * 0xFF / width.saturating_sub(1).max(1)) as u8;
+                    let color = Rgba8888::from_rgb8(level * r, level * g, level * b);
+                    fill(framebuffer, x, top, x + 1, bottom, color);
+                }
+            }
+        }
+    }
+}
+
+/// Fills the rectangle from `(left, top)` up to `(right, bottom)`.
+fn fill<P: Pixel + From<Rgba8888>>(
+    framebuffer: &mut Framebuffer<P>,
+    left: u32,
+    top: u32,
+    right: u32,
+    bottom: u32,
+    color: Rgba8888,
+) {
+    let color = P::from(color);
+    for y in top..bottom {
+        for x in left..right {
+            framebuffer.set(x, y, color);
+        }
+    }
+}
+
+/// Draws a one pixel outline just inside the rectangle.
+fn outline<P: Pixel + From<Rgba8888>>(
+    framebuffer: &mut Framebuffer<P>,
+    left: u32,
+    top: u32,
+    right: u32,
+    bottom: u32,
+    color: Rgba8888,
+) {
+    if left >= right || top >= bottom {
+        return;
+    }
+
+    fill(framebuffer, left, top, right, top + 1, color);
+    fill(framebuffer, left, bottom - 1, right, bottom, color);
+    fill(framebuffer, left, top, left + 1, bottom, color);
+    fill(framebuffer, right - 1, top, right, bottom, color);
+}
+
+/// # Preset names
+///
+/// libultra suffixes of the presets, in the order of [`presets`].
+pub const PRESET_NAMES: [&str; 14] = [
+    "LPN1", "LPF1", "LAN1", "LAF1", "LPN2", "LPF2", "LAN2", "LAF2", "HPN1", "HPF1", "HAN1", "HAF1",
+    "HPN2", "HPF2",
+];
+
+/// Largest horizontal window offset, in pixels.
+const MAX_X_OFFSET: i32 = 0x3FF;
+
+/// Largest vertical window offset, in lines.
+const MAX_Y_OFFSET: i32 = 0x3FF / 2;
+
+/// # Video mode selector
+///
+/// Steps through the presets of a standard and moves their video window, to
+/// find the position that centres the picture on a particular display.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ModeSelector {
+    standard: VideoStandard,
+    index: usize,
+    x_offset: i32,
+    y_offset: i32,
+}
+
+impl ModeSelector {
+    /// Starts at the first preset of `standard`, unmoved.
+    pub const fn new(standard: VideoStandard) -> Self {
+        Self {
+            standard,
+            index: 0,
+            x_offset: 0,
+            y_offset: 0,
+        }
+    }
+
+    /// Selects the next preset, wrapping around.
+    pub fn next(&mut self) {
+        self.index = (self.index + 1) % PRESET_NAMES.len();
+    }
+
+    /// Selects the previous preset, wrapping around.
+    pub fn previous(&mut self) {
+        self.index = (self.index + PRESET_NAMES.len() - 1) % PRESET_NAMES.len();
+    }
+
+    /// Moves the video window by `x` pixels and `y` lines.
+    ///
+    /// The offset is clamped to what can move a window anywhere within the
+    /// register ranges.
+    pub fn nudge(&mut self, x: i32, y: i32) {
+        self.x_offset = self
+            .x_offset
+            .saturating_add(x)
+            .clamp(-MAX_X_OFFSET, MAX_X_OFFSET);
+        self.y_offset = self
+            .y_offset
+            .saturating_add(y)
+            .clamp(-MAX_Y_OFFSET, MAX_Y_OFFSET);
+    }
+
+    /// Offset of the video window as `(x, y)` in pixels and lines.
+    pub fn offset(&self) -> (i32, i32) {
+        (self.x_offset, self.y_offset)
+    }
+
+    /// libultra name of the selected preset, without the standard.
+    pub fn name(&self) -> &'static str {
+        PRESET_NAMES[self.index]
+    }
+
+    /// The selected preset with its video window moved.
+    ///
+    /// The window is clamped to the register ranges; use
+    /// [`ModeSelector::analyze`] to check that it still fits the signal.
+    pub fn mode(&self) -> VideoMode {
+        let mut mode = presets(self.standard)[self.index];
+
+        let h_start = shift(u32::from(mode.h_video.start_active_video()), self.x_offset);
+        let h_end = shift(u32::from(mode.h_video.end_active_video()), self.x_offset);
+        mode.h_video = ViHVideoReg(0)
+            .with_start_active_video(h_start.into())
+            .with_end_active_video(h_end.into());
+
+        for field in mode.fields.iter_mut() {
+            let v_start = shift(
+                u32::from(field.v_video.start_active_video()),
+                self.y_offset * 2,
+            );
+            let v_end = shift(
+                u32::from(field.v_video.end_active_video()),
+                self.y_offset * 2,
+            );
+            field.v_video = ViVVideoReg(0)
+                .with_start_active_video(v_start.into())
+                .with_end_active_video(v_end.into());
+        }
+
+        mode
+    }
+
+    /// Timing analysis of both fields of [`ModeSelector::mode`].
+    pub fn analyze(&self) -> [TimingReport; 2] {
+        let mode = self.mode();
+        [0, 1]
+            .map(|field| timing::analyze(self.standard, &TimingRegisters::from_mode(&mode, field)))
+    }
+}
+
+/// Adds `offset` to a 10-bit register position, clamping it.
+fn shift(value: u32, offset: i32) -> u32 {
+    value.saturating_add_signed(offset).min(0x3FF)
+}
@@ -0,0 +1,161 @@
+//! # Pixel formats
+//!
+//! Colour types for the two framebuffer pixel sizes. Both are stored
+//! big-endian in RDRAM, with red in the most significant bits and alpha (or
+//! coverage, for antialiasing) in the least significant ones.
+
+use super::PixelSize;
+
+/// # Framebuffer pixel
+pub trait Pixel: Copy {
+    /// Pixel size the VI must be set to.
+    const SIZE: PixelSize;
+
+    /// Bytes per pixel.
+    const BYTES: usize;
+
+    /// Decodes a pixel from the first [`Pixel::BYTES`] bytes of `bytes`.
+    fn read(bytes: &[u8]) -> Self;
+
+    /// Encodes the pixel into the first [`Pixel::BYTES`] bytes of `bytes`.
+    fn write(self, bytes: &mut [u8]);
+}
+
+/// # 16-bit colour
+///
+/// Five bits per colour channel and one alpha bit.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[repr(transparent)]
+pub struct Rgba5551(pub u16);
+
+impl Rgba5551 {
+    pub const BLACK: Self = Self::new(0, 0, 0, true);
+    pub const WHITE: Self = Self::new(31, 31, 31, true);
+    pub const RED: Self = Self::new(31, 0, 0, true);
+    pub const GREEN: Self = Self::new(0, 31, 0, true);
+    pub const BLUE: Self = Self::new(0, 0, 31, true);
+    pub const TRANSPARENT: Self = Self::new(0, 0, 0, false);
+
+    /// Builds a colour from 5-bit channels, which are masked.
+    pub const fn new(r: u8, g: u8, b: u8, a: bool) -> Self {
+        Self(
+            ((r as u16 & 0x1F) << 11)
+                | ((g as u16 & 0x1F) << 6)
+                | ((b as u16 & 0x1F) << 1)
+                | a as u16,
+        )
+    }
+
+    /// Builds an opaque colour from 8-bit channels, dropping the low bits.
+    pub const fn from_rgb8(r: u8, g: u8, b: u8) -> Self {
+        Self::new(r >> 3, g >> 3, b >> 3, true)
+    }
+
+    pub const fn r(self) -> u8 {
+        (self.0 >> 11) as u8 & 0x1F
+    }
+
+    pub const fn g(self) -> u8 {
+        (self.0 >> 6) as u8 & 0x1F
+    }
+
+    pub const fn b(self) -> u8 {
+        (self.0 >> 1) as u8 & 0x1F
+    }
+
+    pub const fn a(self) -> bool {
+        self.0 & 1 != 0
+    }
+}
+
+impl Pixel for Rgba5551 {
+    const SIZE: PixelSize = PixelSize::SixteenBit;
+    const BYTES: usize = 2;
+
+    fn read(bytes: &[u8]) -> Self {
+        Self(u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn write(self, bytes: &mut [u8]) {
+        bytes[..2].copy_from_slice(&self.0.to_be_bytes());
+    }
+}
+
+impl From<Rgba8888> for Rgba5551 {
+    fn from(value: Rgba8888) -> Self {
+        Self::new(
+            value.r() >> 3,
+            value.g() >> 3,
+            value.b() >> 3,
+            value.a() >= 0x80,
+        )
+    }
+}
+
+/// # 32-bit colour
+///
+/// Eight bits per channel.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[repr(transparent)]
+pub struct Rgba8888(pub u32);
+
+impl Rgba8888 {
+    pub const BLACK: Self = Self::new(0, 0, 0, 0xFF);
+    pub const WHITE: Self = Self::new(0xFF, 0xFF, 0xFF, 0xFF);
+    pub const RED: Self = Self::new(0xFF, 0, 0, 0xFF);
+    pub const GREEN: Self = Self::new(0, 0xFF, 0, 0xFF);
+    pub const BLUE: Self = Self::new(0, 0, 0xFF, 0xFF);
+    pub const TRANSPARENT: Self = Self::new(0, 0, 0, 0);
+
+    pub const fn new(r: u8, g: u8, b: u8, a: u8) -> Self {
+        Self(u32::from_be_bytes([r, g, b, a]))
+    }
+
+    /// Builds an opaque colour.
+    pub const fn from_rgb8(r: u8, g: u8, b: u8) -> Self {
+        Self::new(r, g, b, 0xFF)
+    }
+
+    pub const fn r(self) -> u8 {
+        (self.0 >> 24) as u8
+    }
+
+    pub const fn g(self) -> u8 {
+        (self.0 >> 16) as u8
+    }
+
+    pub const fn b(self) -> u8 {
+        (self.0 >> 8) as u8
+    }
+
+    pub const fn a(self) -> u8 {
+        self.0 as u8
+    }
+}
+
+impl Pixel for Rgba8888 {
+    const SIZE: PixelSize = PixelSize::ThirtyTwoBit;
+    const BYTES: usize = 4;
+
+    fn read(bytes: &[u8]) -> Self {
+        Self(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn write(self, bytes: &mut [u8]) {
+        bytes[..4].copy_from_slice(&self.0.to_be_bytes());
+    }
+}
+
+impl From<Rgba5551> for Rgba8888 {
+    /// Widens each channel by replicating its high bits, so full intensity
+    /// maps to `0xFF`.
+    fn from(value: Rgba5551) -> Self {
+        let widen = |channel: u8| (channel << 3) | (channel >> 2);
+        Self::new(
+            widen(value.r()),
+            widen(value.g()),
+            widen(value.b()),
+            if value.a() { 0xFF } else { 0 },
+        )
+    }
+}
@@ -0,0 +1,230 @@
+//! # Raster position
+//!
+//! The VI counts half-lines from the start of each field in `VI_CURRENT`.
+//! Progressive modes step it by two; interlaced modes put the field in bit 0.
+//! Lines here are counted from the start of the field, so line `n` is
+//! half-line `2n` regardless of the field. The counter is 10 bits wide, so
+//! lines run up to [`MAX_LINE`], and a field only reaches the lines before
+//! half of `VI_V_SYNC`.
+//!
+//! [`RasterScheduler`] moves the VI interrupt through a list of lines every
+//! field, for mid-frame register changes. The VI has a single interrupt
+//! line, so alongside a [`FrameBuffers`](super::buffers::FrameBuffers) the
+//! scheduler only supplies the lines, and the framebuffer manager programs
+//! them between its own vertical blank interrupts.
+
+use core::{
+    cell::RefCell,
+    ptr::{read_volatile, write_volatile},
+};
+
+use critical_section::Mutex;
+
+use super::{Vi, ViCurrentReg, ViIntrReg};
+
+/// # Last line
+///
+/// The last line the 10-bit half-line counter can reach.
+pub const MAX_LINE: u32 = 0x1FF;
+
+/// # Raster error
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RasterError {
+    /// The line is past [`MAX_LINE`], or past the end of the field.
+    LineOutOfRange,
+}
+
+impl Vi {
+    /// Half-line being scanned out, with the field in bit 0.
+    pub fn current_half_line(&self) -> u32 {
+        unsafe { read_volatile(&self.vi_current_reg).0 & 0x3FF }
+    }
+
+    /// Line being scanned out, from the start of the field.
+    pub fn current_line(&self) -> u32 {
+        line(self.current_half_line())
+    }
+
+    /// Field being scanned out: always 0 in progressive modes.
+    pub fn current_field(&self) -> usize {
+        field(self.current_half_line())
+    }
+
+    /// Waits for the start of the next vertical blank.
+    ///
+    /// If the active area is empty this never returns.
+    pub fn wait_vblank(&self) {
+        let end = unsafe { read_volatile(&self.vi_v_video_reg).0 & 0x3FE };
+        while self.current_half_line() & 0x3FE >= end {}
+        while self.current_half_line() & 0x3FE < end {}
+    }
+
+    /// Waits until line `line` is being scanned out.
+    ///
+    /// Fails without waiting if the fields of the current mode never reach
+    /// the line.
+    pub fn wait_line(&self, line: u32) -> Result<(), RasterError> {
+        let v_sync = unsafe { read_volatile(&self.vi_v_sync_reg).0 & 0x3FF };
+        check_line(line, v_sync)?;
+        while self.current_line() != line {}
+        Ok(())
+    }
+
+    /// Acknowledges the VI interrupt.
+    pub fn acknowledge_interrupt(&mut self) {
+        unsafe { write_volatile(&mut self.vi_current_reg, ViCurrentReg(0)) };
+    }
+}
+
+/// # Raster interrupt scheduler
+///
+/// Fires the VI interrupt at each of `N` lines in turn, every field. Shared
+/// between the main loop and the VI interrupt handler, so its position is
+/// only touched inside critical sections.
+pub struct RasterScheduler<const N: usize> {
+    lines: [u32; N],
+    next: Mutex<RefCell<usize>>,
+}
+
+impl<const N: usize> RasterScheduler<N> {
+    /// Schedules interrupts at `lines`, which are sorted into scan order.
+    ///
+    /// Fails if a line is past [`MAX_LINE`].
+    pub fn new(mut lines: [u32; N]) -> Result<Self, RasterError> {
+        if lines.iter().any(|&line| line > MAX_LINE) {
+            return Err(RasterError::LineOutOfRange);
+        }
+
+        lines.sort_unstable();
+        Ok(Self {
+            lines,
+            next: Mutex::new(RefCell::new(0)),
+        })
+    }
+
+    /// The scheduled lines, in scan order.
+    pub fn lines(&self) -> &[u32; N] {
+        &self.lines
+    }
+
+    /// Programs the interrupt for the first line.
+    pub fn start(&self, vi: &mut Vi) {
+        critical_section::with(|cs| {
+            *self.next.borrow_ref_mut(cs) = 0;
+            self.program(vi, 0);
+        });
+    }
+
+    /// Handles the VI interrupt.
+    ///
+    /// Acknowledges the interrupt, programs the next line and returns the
+    /// index of the line that fired, or `None` if nothing is scheduled.
+    pub fn on_interrupt(&self, vi: &mut Vi) -> Option<usize> {
+        vi.acknowledge_interrupt();
+        critical_section::with(|cs| {
+            let fired = self.advance(cs)?;
+            self.program(vi, *self.next.borrow_ref(cs));
+            Some(fired)
+        })
+    }
+
+    /// Moves to the next line, wrapping around to the first one of the next
+    /// field, and returns the index of the line that fired.
+    fn advance(&self, cs: critical_section::CriticalSection) -> Option<usize> {
+        if N == 0 {
+            return None;
+        }
+
+        let mut next = self.next.borrow_ref_mut(cs);
+        let fired = *next;
+        *next = (fired + 1) % N;
+        Some(fired)
+    }
+
+    fn program(&self, vi: &mut Vi, next: usize) {
+        if let Some(&line) = self.lines.get(next) {
+            let intr = ViIntrReg(0).with_intr_half_line((line << 1).into());
+            unsafe { write_volatile(&mut vi.vi_intr_reg, intr) };
+        }
+    }
+}
+
+/// Line of `half_line`, from the start of its field.
+fn line(half_line: u32) -> u32 {
+    half_line >> 1
+}
+
+/// Field of `half_line`.
+fn field(half_line: u32) -> usize {
+    (half_line & 1) as usize
+}
+
+/// Checks that the fields of a mode with `v_sync` half-lines per frame reach
+/// `line`.
+fn check_line(line: u32, v_sync: u32) -> Result<(), RasterError> {
+    match line <= MAX_LINE && line << 1 < v_sync {
+        true => Ok(()),
+        false => Err(RasterError::LineOutOfRange),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_half_lines() {
+        assert_eq!((line(0), field(0)), (0, 0));
+        assert_eq!((line(0x20C), field(0x20C)), (0x106, 0));
+        assert_eq!((line(0x20D), field(0x20D)), (0x106, 1));
+        assert_eq!((line(0x3FF), field(0x3FF)), (MAX_LINE, 1));
+    }
+
+    #[test]
+    fn checks_lines_against_the_field() {
+        // NTSC progressive and interlaced, and PAL progressive.
+        assert_eq!(check_line(262, 0x20D), Ok(()));
+        assert_eq!(check_line(263, 0x20D), Err(RasterError::LineOutOfRange));
+        assert_eq!(check_line(261, 0x20C), Ok(()));
+        assert_eq!(check_line(262, 0x20C), Err(RasterError::LineOutOfRange));
+        assert_eq!(check_line(312, 0x271), Ok(()));
+        assert_eq!(
+            check_line(MAX_LINE + 1, u32::MAX),
+            Err(RasterError::LineOutOfRange)
+        );
+    }
+
+    #[test]
+    fn sorts_lines_into_scan_order() {
+        let raster = RasterScheduler::new([200, 10, 100]).unwrap();
+        assert_eq!(raster.lines(), &[10, 100, 200]);
+
+        assert!(RasterScheduler::new([MAX_LINE]).is_ok());
+        assert!(matches!(
+            RasterScheduler::new([10, MAX_LINE + 1]),
+            Err(RasterError::LineOutOfRange)
+        ));
+    }
+
+    #[test]
+    fn wraps_around_every_field() {
+        let raster = RasterScheduler::new([200, 10, 100]).unwrap();
+        let fired: [Option<usize>; 7] =
+            core::array::from_fn(|_| critical_section::with(|cs| raster.advance(cs)));
+        assert_eq!(
+            fired,
+            [
+                Some(0),
+                Some(1),
+                Some(2),
+                Some(0),
+                Some(1),
+                Some(2),
+                Some(0)
+            ]
+        );
+
+        let empty = RasterScheduler::new([]).unwrap();
+        assert_eq!(critical_section::with(|cs| empty.advance(cs)), None);
+    }
+}
@@ -0,0 +1,256 @@
+//! # VI timing analysis
+//!
+//! Derives the line and field rates of a set of timing registers and checks
+//! that the active video window, sync pulses and colour burst fit together.
+//! A mode that passes these checks is not guaranteed to display, but one
+//! that fails them will roll, tear or lose colour on most TVs.
+//!
+//! Horizontal positions are in pixels of four VI clocks, line durations in
+//! VI clocks and vertical positions in half-lines.
+
+use super::{
+    mode::{VideoMode, VideoStandard},
+    ViHSyncLeapReg, ViHSyncReg, ViHVideoReg, ViTimingReg, ViVBurstReg, ViVSyncReg, ViVVideoReg,
+};
+
+/// Allowed deviation from the nominal line rate, in percent.
+const LINE_RATE_TOLERANCE: f32 = 2.0;
+
+/// Allowed deviation from the nominal field rate, in percent.
+const FIELD_RATE_TOLERANCE: f32 = 5.0;
+
+/// # Timing registers
+///
+/// The registers that shape the output signal of one field.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimingRegisters {
+    pub timing: ViTimingReg,
+    pub v_sync: ViVSyncReg,
+    pub h_sync: ViHSyncReg,
+    pub h_sync_leap: ViHSyncLeapReg,
+    pub h_video: ViHVideoReg,
+    pub v_video: ViVVideoReg,
+    pub v_burst: ViVBurstReg,
+}
+
+impl TimingRegisters {
+    /// Registers of `field` of `mode`.
+    pub const fn from_mode(mode: &VideoMode, field: usize) -> Self {
+        let field = &mode.fields[field & 1];
+        Self {
+            timing: mode.timing,
+            v_sync: mode.v_sync,
+            h_sync: mode.h_sync,
+            h_sync_leap: mode.h_sync_leap,
+            h_video: mode.h_video,
+            v_video: field.v_video,
+            v_burst: field.v_burst,
+        }
+    }
+}
+
+/// # Timing violation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Violation {
+    /// The line rate is too far from the standard's for a TV to lock to.
+    LineRateOutOfRange,
+
+    /// The field rate is too far from the standard's for a TV to lock to.
+    FieldRateOutOfRange,
+
+    /// A leap period differs from the line duration by more than the line
+    /// rate tolerance.
+    LeapOutOfRange,
+
+    /// The horizontal sync pulse runs into the colour burst.
+    SyncOverlapsBurst,
+
+    /// The colour burst runs into the active video window.
+    BurstOverlapsVideo,
+
+    /// The horizontal video window is empty or extends past the line.
+    HVideoOutsideLine,
+
+    /// The vertical video window is empty or extends past the field.
+    VVideoOutsideField,
+
+    /// The vertical video window starts during vertical sync.
+    VideoOverlapsVSync,
+
+    /// The colour burst lines start during vertical sync or extend past
+    /// the field.
+    VBurstOutsideField,
+
+    /// Some active lines have no colour burst.
+    VideoWithoutBurst,
+}
+
+impl Violation {
+    /// Every violation, in the order [`Violations::iter`] yields them.
+    pub const ALL: [Violation; 10] = [
+        Violation::LineRateOutOfRange,
+        Violation::FieldRateOutOfRange,
+        Violation::LeapOutOfRange,
+        Violation::SyncOverlapsBurst,
+        Violation::BurstOverlapsVideo,
+        Violation::HVideoOutsideLine,
+        Violation::VVideoOutsideField,
+        Violation::VideoOverlapsVSync,
+        Violation::VBurstOutsideField,
+        Violation::VideoWithoutBurst,
+    ];
+}
+
+/// # Set of timing violations
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Violations(u16);
+
+impl Violations {
+    /// Whether no violation was found.
+    pub const fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    /// Whether `violation` was found.
+    pub const fn contains(&self, violation: Violation) -> bool {
+        self.0 & (1 << violation as u8) != 0
+    }
+
+    /// The violations found.
+    pub fn iter(&self) -> impl Iterator<Item = Violation> + '_ {
+        Violation::ALL
+            .into_iter()
+            .filter(|violation| self.contains(*violation))
+    }
+
+    fn insert_if(&mut self, violation: Violation, condition: bool) {
+        if condition {
+            self.0 |= 1 << violation as u8;
+        }
+    }
+}
+
+/// # Timing report
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TimingReport {
+    /// Lines per second.
+    pub line_rate_hz: f32,
+    /// Fields per second.
+    pub field_rate_hz: f32,
+    /// Complete pictures per second: half the field rate when interlaced.
+    pub frame_rate_hz: f32,
+    pub half_lines_per_field: u32,
+    pub interlaced: bool,
+    /// Width of the active video window in pixels.
+    pub active_width: u32,
+    /// Height of the active video window in lines.
+    pub active_lines: u32,
+    pub violations: Violations,
+}
+
+impl TimingReport {
+    /// Whether no violation was found.
+    pub const fn is_valid(&self) -> bool {
+        self.violations.is_empty()
+    }
+}
+
+/// Analyzes `registers` as output with the VI clock of `standard`.
+///
+/// The rates assume every line lasts the nominal line duration; the leap
+/// periods only shift a few sync lines and are checked separately.
+pub fn analyze(standard: VideoStandard, registers: &TimingRegisters) -> TimingReport {
+    let clock = standard.clock_hz() as f32;
+
+    let line_clocks = u32::from(registers.h_sync.line_duration()) + 1;
+    let leap_clocks = [
+        u32::from(registers.h_sync_leap.h_sync_period_0()) + 1,
+        u32::from(registers.h_sync_leap.h_sync_period_1()) + 1,
+    ];
+    let last_half_line = u32::from(registers.v_sync.half_lines_per_field());
+    let half_lines = last_half_line + 1;
+    let interlaced = half_lines % 2 == 1;
+
+    let line_rate_hz = clock / line_clocks as f32;
+    let field_rate_hz = 2.0 * line_rate_hz / half_lines as f32;
+    let frame_rate_hz = if interlaced {
+        field_rate_hz / 2.0
+    } else {
+        field_rate_hz
+    };
+
+    let (nominal_line_rate, nominal_field_rate) = match standard {
+        VideoStandard::Pal => (15_625.0, 50.0),
+        VideoStandard::Ntsc | VideoStandard::Mpal | VideoStandard::Pal60 => (15_734.264, 59.94),
+    };
+
+    let h_sync_width = u32::from(registers.timing.horizontal_sync_width());
+    let burst_start = u32::from(registers.timing.color_burst_offset());
+    let burst_end = burst_start + u32::from(registers.timing.color_burst_width());
+    let v_sync_width = u32::from(registers.timing.vertical_sync_width());
+
+    let h_start = u32::from(registers.h_video.start_active_video());
+    let h_end = u32::from(registers.h_video.end_active_video());
+    let v_start = u32::from(registers.v_video.start_active_video());
+    let v_end = u32::from(registers.v_video.end_active_video());
+    let v_burst_start = u32::from(registers.v_burst.start_color_burst());
+    let v_burst_end = u32::from(registers.v_burst.end_color_burst());
+
+    let mut violations = Violations::default();
+    violations.insert_if(
+        Violation::LineRateOutOfRange,
+        deviation(line_rate_hz, nominal_line_rate) > LINE_RATE_TOLERANCE,
+    );
+    violations.insert_if(
+        Violation::FieldRateOutOfRange,
+        deviation(field_rate_hz, nominal_field_rate) > FIELD_RATE_TOLERANCE,
+    );
+    violations.insert_if(
+        Violation::LeapOutOfRange,
+        leap_clocks
+            .iter()
+            .any(|&leap| deviation(leap as f32, line_clocks as f32) > LINE_RATE_TOLERANCE),
+    );
+    violations.insert_if(Violation::SyncOverlapsBurst, h_sync_width >= burst_start);
+    violations.insert_if(Violation::BurstOverlapsVideo, burst_end > h_start);
+    violations.insert_if(
+        Violation::HVideoOutsideLine,
+        h_start >= h_end || h_end * 4 > line_clocks,
+    );
+    violations.insert_if(
+        Violation::VVideoOutsideField,
+        v_start >= v_end || v_end > last_half_line,
+    );
+    violations.insert_if(Violation::VideoOverlapsVSync, v_start <= v_sync_width);
+    violations.insert_if(
+        Violation::VBurstOutsideField,
+        v_burst_start <= v_sync_width || v_burst_end > last_half_line,
+    );
+    violations.insert_if(
+        Violation::VideoWithoutBurst,
+        v_start < v_end && (v_burst_start > v_start || v_burst_end < v_end),
+    );
+
+    TimingReport {
+        line_rate_hz,
+        field_rate_hz,
+        frame_rate_hz,
+        half_lines_per_field: half_lines,
+        interlaced,
+        active_width: h_end.saturating_sub(h_start),
+        active_lines: v_end.saturating_sub(v_start) / 2,
+        violations,
+    }
+}
+
+/// Relative difference from `nominal`, in percent.
+fn deviation(value: f32, nominal: f32) -> f32 {
+    let difference = value - nominal;
+    let difference = if difference < 0.0 {
+        -difference
+    } else {
+        difference
+    };
+    difference * 100.0 / nominal
+}
@@ -0,0 +1,53 @@
+use nintendo64_pac::{
+    ai::{AudioRate, FrequencyError},
+    vi::mode::VideoStandard,
+};
+
+fn rate(hz: u32, standard: VideoStandard) -> (u32, u32, u32) {
+    let rate = AudioRate::new(hz, standard).unwrap();
+    (rate.dac_rate, rate.bitrate, rate.frequency)
+}
+
+#[test]
+fn ntsc_rates() {
+    assert_eq!(rate(32000, VideoStandard::Ntsc), (1521, 16, 32006));
+    assert_eq!(rate(44100, VideoStandard::Ntsc), (1104, 16, 44095));
+    assert_eq!(rate(48000, VideoStandard::Ntsc), (1014, 15, 48009));
+}
+
+#[test]
+fn pal_rates() {
+    assert_eq!(rate(32000, VideoStandard::Pal), (1552, 16, 31995));
+    assert_eq!(rate(44100, VideoStandard::Pal), (1126, 16, 44099));
+    assert_eq!(rate(48000, VideoStandard::Pal), (1035, 15, 47977));
+}
+
+#[test]
+fn mpal_rates() {
+    assert_eq!(rate(32000, VideoStandard::Mpal), (1520, 16, 31992));
+    assert_eq!(rate(44100, VideoStandard::Mpal), (1103, 16, 44087));
+    assert_eq!(rate(48000, VideoStandard::Mpal), (1013, 15, 48004));
+}
+
+#[test]
+fn register_values() {
+    let rate = AudioRate::new(44100, VideoStandard::Ntsc).unwrap();
+    assert_eq!(rate.dacrate_reg().raw(), 1103);
+    assert_eq!(rate.bitrate_reg().raw(), 15);
+}
+
+#[test]
+fn out_of_range() {
+    assert_eq!(
+        AudioRate::new(400_000, VideoStandard::Ntsc),
+        Err(FrequencyError::TooHigh)
+    );
+    assert_eq!(
+        AudioRate::new(1000, VideoStandard::Ntsc),
+        Err(FrequencyError::TooLow)
+    );
+    assert_eq!(
+        AudioRate::new(0, VideoStandard::Ntsc),
+        Err(FrequencyError::TooLow)
+    );
+}
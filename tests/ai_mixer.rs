@@ -0,0 +1,154 @@
+use nintendo64_pac::ai::mixer::{Mixer, Playback, Sound, UNITY_PITCH, UNITY_VOLUME};
+
+const RAMP: [i16; 4] = [0, 1000, 2000, 3000];
+
+fn mix<const V: usize>(mixer: &mut Mixer<V>, frames: usize) -> Vec<(i16, i16)> {
+    let mut out = vec![0; frames * 2];
+    mixer.mix(&mut out);
+    out.chunks(2).map(|frame| (frame[0], frame[1])).collect()
+}
+
+fn left(frames: &[(i16, i16)]) -> Vec<i16> {
+    frames.iter().map(|&(left, _)| left).collect()
+}
+
+#[test]
+fn one_shot_ends() {
+    let mut mixer = Mixer::<2>::new(32000);
+    let id = mixer
+        .play(Sound::new(&RAMP, 32000), Playback::DEFAULT)
+        .unwrap();
+
+    assert_eq!(left(&mix(&mut mixer, 6)), [0, 1000, 2000, 3000, 0, 0]);
+    assert!(!mixer.is_playing(id));
+    assert_eq!(mixer.active(), 0);
+}
+
+#[test]
+fn looping_wraps_to_loop_start() {
+    let mut mixer = Mixer::<1>::new(32000);
+    mixer.play(Sound::new(&RAMP, 32000).looping(2), Playback::DEFAULT);
+
+    assert_eq!(
+        left(&mix(&mut mixer, 8)),
+        [0, 1000, 2000, 3000, 2000, 3000, 2000, 3000]
+    );
+}
+
+#[test]
+fn pitch_resamples() {
+    let mut mixer = Mixer::<1>::new(32000);
+    let playback = Playback {
+        pitch: UNITY_PITCH / 2,
+        ..Playback::DEFAULT
+    };
+    mixer.play(Sound::new(&RAMP, 32000), playback);
+    assert_eq!(
+        left(&mix(&mut mixer, 8)),
+        [0, 500, 1000, 1500, 2000, 2500, 3000, 3000]
+    );
+
+    // A sound at twice the output rate skips every other sample.
+    mixer.play(Sound::new(&RAMP, 64000), Playback::DEFAULT);
+    assert_eq!(left(&mix(&mut mixer, 3)), [0, 2000, 0]);
+}
+
+#[test]
+fn volume_and_pan() {
+    let mut mixer = Mixer::<1>::new(32000);
+    let playback = Playback {
+        volume: UNITY_VOLUME / 2,
+        pan: -128,
+        pitch: UNITY_PITCH,
+    };
+    let id = mixer.play(Sound::new(&[2000; 4], 32000), playback).unwrap();
+    assert_eq!(mix(&mut mixer, 1), [(1000, 0)]);
+
+    mixer.set_playback(id, Playback::DEFAULT);
+    assert_eq!(mix(&mut mixer, 1), [(2000, 2000)]);
+}
+
+#[test]
+fn voices_sum_and_saturate() {
+    let mut mixer = Mixer::<2>::new(32000);
+    mixer.play(Sound::new(&[30000; 2], 32000), Playback::DEFAULT);
+    mixer.play(Sound::new(&[-20000, 30000], 32000), Playback::DEFAULT);
+    assert_eq!(left(&mix(&mut mixer, 2)), [10000, i16::MAX]);
+
+    assert!(mixer
+        .play(Sound::new(&RAMP, 32000), Playback::DEFAULT)
+        .is_some());
+    assert!(mixer
+        .play(Sound::new(&RAMP, 32000), Playback::DEFAULT)
+        .is_some());
+    assert!(mixer
+        .play(Sound::new(&RAMP, 32000), Playback::DEFAULT)
+        .is_none());
+}
+
+#[test]
+fn stale_handles_are_ignored() {
+    let mut mixer = Mixer::<1>::new(32000);
+    let first = mixer
+        .play(Sound::new(&RAMP, 32000), Playback::DEFAULT)
+        .unwrap();
+    mixer.stop(first);
+    let second = mixer
+        .play(Sound::new(&RAMP, 32000), Playback::DEFAULT)
+        .unwrap();
+
+    mixer.stop(first);
+    assert!(mixer.is_playing(second));
+    assert!(mixer.playback(first).is_none());
+}
+
+#[test]
+fn mixes_big_endian_bytes() {
+    let mut mixer = Mixer::<1>::new(32000);
+    mixer.play(Sound::new(&[0x1234; 1], 32000), Playback::DEFAULT);
+
+    let mut out = [0; 8];
+    mixer.mix_bytes(&mut out);
+    assert_eq!(out, [0x12, 0x34, 0x12, 0x34, 0, 0, 0, 0]);
+}
+
+#[test]
+fn pan_silences_other_side_at_both_ends() {
+    let mut mixer = Mixer::<1>::new(32000);
+    for (pan, expected) in [(-128, (2000, 0)), (127, (0, 2000)), (0, (2000, 2000))] {
+        let playback = Playback {
+            pan,
+            ..Playback::DEFAULT
+        };
+        mixer.play(Sound::new(&[2000; 1], 32000), playback);
+        assert_eq!(mix(&mut mixer, 1), [expected], "pan {}", pan);
+    }
+
+    let playback = Playback {
+        pan: 64,
+        ..Playback::DEFAULT
+    };
+    mixer.play(Sound::new(&[2000; 1], 32000), playback);
+    assert_eq!(mix(&mut mixer, 1), [(984, 2000)]);
+}
+
+#[test]
+fn stationary_sounds_end() {
+    let mut mixer = Mixer::<1>::new(32000);
+    let id = mixer
+        .play(Sound::new(&RAMP, 0).looping(0), Playback::DEFAULT)
+        .unwrap();
+    assert!(!mixer.is_playing(id));
+    assert_eq!(mixer.active(), 0);
+
+    let id = mixer
+        .play(Sound::new(&RAMP, 32000).looping(0), Playback::DEFAULT)
+        .unwrap();
+    let playback = Playback {
+        pitch: 0,
+        ..Playback::DEFAULT
+    };
+    mixer.set_playback(id, playback);
+    assert!(!mixer.is_playing(id));
+    assert_eq!(mix(&mut mixer, 1), [(0, 0)]);
+}
@@ -0,0 +1,68 @@
+use nintendo64_pac::{
+    ai::{dma_address, AiDmaError, AiRevision, AI_DMA_BOUNDARY},
+    mi::MiVersionReg,
+};
+
+#[test]
+fn detects_revision() {
+    assert_eq!(
+        AiRevision::from_version(MiVersionReg(0x0101_0101)),
+        AiRevision::V1
+    );
+    assert_eq!(
+        AiRevision::from_version(MiVersionReg(0x0202_0102)),
+        AiRevision::V2
+    );
+}
+
+#[test]
+fn encodes_length() {
+    assert_eq!(
+        AiRevision::V1.len_reg(0x1000, 0x7FF8).unwrap().raw(),
+        0x7FF8
+    );
+    assert_eq!(
+        AiRevision::V2.len_reg(0x1000, 0x3FFF8).unwrap().raw(),
+        0x3FFF8
+    );
+}
+
+#[test]
+fn rejects_invalid_transfers() {
+    let v1 = AiRevision::V1;
+    assert_eq!(
+        v1.len_reg(0x1004, 0x100).map(|len| len.raw()),
+        Err(AiDmaError::MisalignedAddress)
+    );
+    assert_eq!(
+        v1.len_reg(0x1000, 0x104).map(|len| len.raw()),
+        Err(AiDmaError::MisalignedLength)
+    );
+    assert_eq!(
+        v1.len_reg(0x1000, 0).map(|len| len.raw()),
+        Err(AiDmaError::Empty)
+    );
+    assert_eq!(
+        v1.len_reg(0x1000, 0x8000).map(|len| len.raw()),
+        Err(AiDmaError::TooLong)
+    );
+    assert!(AiRevision::V2.len_reg(0x1000, 0x8000).is_ok());
+}
+
+#[test]
+fn splits_long_buffers() {
+    let chunks: Vec<_> = AiRevision::V1.chunks(0x1000, 0x10000).collect();
+    assert_eq!(
+        chunks,
+        [(0x1000, 0x7FF8), (0x8FF8, 0x7FF8), (0x10FF0, 0x10)]
+    );
+    assert_eq!(AiRevision::V2.chunks(0x1000, 0x10000).count(), 1);
+}
+
+#[test]
+fn works_around_boundary_bug() {
+    assert_eq!(dma_address(0x8000, None), 0x8000);
+    assert_eq!(dma_address(0x8000, Some(0x7FF8)), 0x8000);
+    assert_eq!(dma_address(0x8000, Some(0x8000)), 0x8000 - AI_DMA_BOUNDARY);
+    assert_eq!(dma_address(0x1_0000, Some(0x6000)), 0xE000);
+}
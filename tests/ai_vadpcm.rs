@@ -0,0 +1,231 @@
+use nintendo64_pac::ai::vadpcm::{Codebook, Decoder, Loop, Stream, VadpcmError, FRAME_SAMPLES};
+
+/// Order 2 codebook whose only predictor passes residuals through.
+const ZERO: [i16; 16] = [0; 16];
+
+/// Order 2 codebook whose only predictor repeats the previous sample, so
+/// each sample is the previous one plus the residual.
+const INTEGRATOR: [i16; 16] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 2048, 2048, 2048, 2048, 2048, 2048, 2048, 2048,
+];
+
+fn decode(codebook: &[i16], predictors: usize, frames: &[u8]) -> Vec<i16> {
+    let codebook = Codebook::new(2, predictors, codebook).unwrap();
+    let mut decoder = Decoder::new(codebook);
+    let mut out = vec![0; frames.len() / 9 * FRAME_SAMPLES];
+    assert_eq!(decoder.decode(frames, &mut out), Ok(out.len()));
+    out
+}
+
+#[test]
+fn residuals_are_scaled_and_sign_extended() {
+    let frame = [0x20, 0x01, 0x23, 0x45, 0x67, 0x89, 0xAB, 0xCD, 0xEF];
+    assert_eq!(
+        decode(&ZERO, 1, &frame),
+        [0, 4, 8, 12, 16, 20, 24, 28, -32, -28, -24, -20, -16, -12, -8, -4]
+    );
+}
+
+#[test]
+fn predictor_carries_state_across_frames() {
+    let frames = [
+        [0x00, 0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x11],
+        [0x30, 0xFF, 0xFF, 0xFF, 0xFF, 0x00, 0x00, 0x00, 0x00],
+    ]
+    .concat();
+
+    let mut expected: Vec<i16> = (1..=16).collect();
+    expected.extend((1..=8).map(|step| 16 - step * 8));
+    expected.extend([-48; 8]);
+    assert_eq!(decode(&INTEGRATOR, 1, &frames), expected);
+}
+
+#[test]
+fn header_selects_predictor() {
+    let codebook = [ZERO, INTEGRATOR].concat();
+    let frame = [0x01, 0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x11];
+    assert_eq!(decode(&codebook, 2, &frame), (1..=16).collect::<Vec<i16>>());
+
+    let codebook = Codebook::new(2, 1, &INTEGRATOR).unwrap();
+    let mut out = [0; FRAME_SAMPLES];
+    assert_eq!(
+        Decoder::new(codebook).decode_frame(&frame, &mut out),
+        Err(VadpcmError::InvalidPredictor)
+    );
+}
+
+#[test]
+fn output_saturates() {
+    let frame = [0xC0, 0x77, 0x77, 0x77, 0x77, 0x77, 0x77, 0x77, 0x77];
+    let out = decode(&INTEGRATOR, 1, &frame);
+    assert_eq!(out[..3], [28672, i16::MAX, i16::MAX]);
+}
+
+#[test]
+fn full_scale_residuals_saturate_without_overflow() {
+    let frame = [0xF0, 0x77, 0x77, 0x77, 0x77, 0x77, 0x77, 0x77, 0x77];
+    assert_eq!(decode(&INTEGRATOR, 1, &frame), [i16::MAX; 16]);
+
+    let frame = [0xF0, 0x88, 0x88, 0x88, 0x88, 0x88, 0x88, 0x88, 0x88];
+    assert_eq!(decode(&INTEGRATOR, 1, &frame), [i16::MIN; 16]);
+
+    let frame = [0xF0, 0x78, 0x78, 0x78, 0x78, 0x78, 0x78, 0x78, 0x78];
+    assert_eq!(
+        decode(&INTEGRATOR, 1, &frame),
+        [i16::MAX, i16::MIN].repeat(8)
+    );
+}
+
+/// Order 2 codebook of two predictors designed for a tone, with frames
+/// using both, and the samples the SDK's `vadpcm_dec` decodes them to.
+const TONE_CODEBOOK: [i16; 32] = [
+    -1848, -3452, -4779, -5811, -6539, -6970, -7115, -7000, 3825, 5296, 6439, 7246, 7723, 7885,
+    7756, 7371, -1417, -1936, -1666, -936, -127, 475, 736, 678, 2799, 2407, 1353, 183, -686, -1064,
+    -979, -602,
+];
+const TONE_FRAMES: [u8; 36] = [
+    0x80, 0x07, 0x30, 0x10, 0x10, 0x01, 0xF1, 0xF0, 0x00, 0x50, 0xEE, 0xF1, 0x02, 0x23, 0x34, 0x44,
+    0x35, 0x23, 0x90, 0x0F, 0x7E, 0xAC, 0x15, 0x42, 0xCB, 0xC1, 0x55, 0x91, 0x0E, 0xBB, 0xC0, 0x00,
+    0xDB, 0xBD, 0x12, 0x20,
+];
+const TONE_SAMPLES: [i16; 64] = [
+    0, 1792, 4114, 6068, 7876, 9233, 10392, 11078, 11312, 11386, 10802, 10148, 8959, 7568, 6053,
+    4472, 2826, 1178, -382, -1747, -2914, -3805, -4410, -4709, -4720, -4437, -3900, -3149, -2269,
+    -1234, -194, 848, 1758, 2007, 5746, 7896, 6490, 2948, 158, 197, 2273, 5092, 5411, 2950, -1422,
+    -4806, -5131, -2687, -123, 668, -1561, -5158, -8014, -7385, -4544, -1102, 101, -1659, -4896,
+    -7080, -5774, -1969, 2331, 4545,
+];
+
+#[test]
+fn matches_reference_decoder() {
+    assert_eq!(decode(&TONE_CODEBOOK, 2, &TONE_FRAMES), TONE_SAMPLES);
+}
+
+#[test]
+fn loop_state_restarts_prediction() {
+    let codebook = Codebook::new(2, 1, &INTEGRATOR).unwrap();
+    let frame = [0x00, 0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x11];
+    let mut decoder = Decoder::new(codebook);
+    let mut out = [0; FRAME_SAMPLES];
+
+    decoder.decode_frame(&frame, &mut out).unwrap();
+    let state = *decoder.state();
+    decoder.decode_frame(&frame, &mut out).unwrap();
+    assert_eq!(out[0], 17);
+
+    decoder.set_state(state);
+    decoder.decode_frame(&frame, &mut out).unwrap();
+    assert_eq!(out[0], 17);
+
+    decoder.reset();
+    decoder.decode_frame(&frame, &mut out).unwrap();
+    assert_eq!(out[0], 1);
+}
+
+#[test]
+fn decodes_stereo_frames() {
+    let codebook = Codebook::new(2, 1, &ZERO).unwrap();
+    let frame = [0x00, 0x12, 0, 0, 0, 0, 0, 0, 0];
+    let mut out = [0; FRAME_SAMPLES * 2];
+    assert_eq!(
+        Decoder::new(codebook).decode_stereo(&frame, &mut out),
+        Ok(16)
+    );
+    assert_eq!(out[..4], [1, 1, 2, 2]);
+}
+
+#[test]
+fn rejects_invalid_codebooks() {
+    assert_eq!(Codebook::new(0, 1, &[]), Err(VadpcmError::InvalidOrder));
+    assert_eq!(
+        Codebook::new(2, 0, &[]),
+        Err(VadpcmError::InvalidPredictorCount)
+    );
+    assert_eq!(
+        Codebook::new(2, 1, &ZERO[..8]),
+        Err(VadpcmError::InvalidCodebookSize)
+    );
+}
+
+/// Two frames of ramps, passed through by the [`ZERO`] codebook.
+const RAMP: [u8; 18] = [
+    0x00, 0x01, 0x23, 0x45, 0x67, 0x00, 0x00, 0x00, 0x00, 0x10, 0x45, 0x67, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00,
+];
+
+fn stream(codebook: &[i16], frames: &[u8], looping: Option<Loop>, length: usize) -> Vec<i16> {
+    let codebook = Codebook::new(2, 1, codebook).unwrap();
+    let mut stream = Stream::new(codebook, frames, looping).unwrap();
+    let mut out = vec![0; length];
+    let written = stream.read(&mut out).unwrap();
+    out.truncate(written);
+    out
+}
+
+#[test]
+fn stream_plays_loop_count_times() {
+    let looping = Loop {
+        start: 16,
+        end: 20,
+        count: 2,
+        state: [0; FRAME_SAMPLES],
+    };
+
+    let first = decode(&ZERO, 1, &RAMP);
+    let mut expected = first[..20].to_vec();
+    expected.extend_from_slice(&first[16..20]);
+    expected.extend_from_slice(&first[16..]);
+    assert_eq!(stream(&ZERO, &RAMP, Some(looping), 100), expected);
+
+    assert_eq!(stream(&ZERO, &RAMP, None, 100), first);
+}
+
+#[test]
+fn stream_restores_loop_state() {
+    let frames = [[0x00, 0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x11]; 2].concat();
+    let looping = Loop {
+        start: 16,
+        end: 32,
+        count: 1,
+        state: [0; FRAME_SAMPLES],
+    };
+
+    let mut expected: Vec<i16> = (1..=32).collect();
+    expected.extend(1..=16);
+    assert_eq!(stream(&INTEGRATOR, &frames, Some(looping), 100), expected);
+}
+
+#[test]
+fn stream_loops_forever() {
+    let codebook = Codebook::new(2, 1, &ZERO).unwrap();
+    let looping = Loop {
+        start: 0,
+        end: 8,
+        count: u32::MAX,
+        state: [0; FRAME_SAMPLES],
+    };
+    let mut stream = Stream::new(codebook, &RAMP, Some(looping)).unwrap();
+
+    let mut out = [0; 20];
+    assert_eq!(stream.read(&mut out), Ok(20));
+    assert_eq!(out[..10], [0, 1, 2, 3, 4, 5, 6, 7, 0, 1]);
+    assert_eq!(stream.position(), 4);
+    assert_eq!(stream.remaining(), u32::MAX);
+}
+
+#[test]
+fn stream_rejects_invalid_loops() {
+    let codebook = Codebook::new(2, 1, &ZERO).unwrap();
+    for (start, end) in [(8, 20), (16, 16), (0, 33)] {
+        let looping = Loop {
+            start,
+            end,
+            count: 1,
+            state: [0; FRAME_SAMPLES],
+        };
+        assert_eq!(
+            Stream::new(codebook, &RAMP, Some(looping)),
+            Err(VadpcmError::InvalidLoop)
+        );
+    }
+}
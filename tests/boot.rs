@@ -0,0 +1,81 @@
+use nintendo64_pac::boot::{BootInfo, Cic, ResetType, RomType, TvType, BOOT_INFO_SIZE};
+
+/// Block left by a 6102 IPL3 on an NTSC console without an Expansion Pak.
+fn ntsc_6102() -> [u8; BOOT_INFO_SIZE] {
+    let words: [u32; 7] = [
+        0x0000_0001,
+        0x0000_0000,
+        0xB000_0000,
+        0x0000_0000,
+        0x0000_003F,
+        0x0000_0000,
+        0x0040_0000,
+    ];
+
+    let mut raw = [0; BOOT_INFO_SIZE];
+    for (index, word) in words.iter().enumerate() {
+        raw[index * 4..index * 4 + 4].copy_from_slice(&word.to_be_bytes());
+    }
+    raw
+}
+
+#[test]
+fn parses_ipl3_block() {
+    let info = BootInfo::parse(&ntsc_6102()).unwrap();
+    assert_eq!(info.tv_type, TvType::Ntsc);
+    assert_eq!(info.rom_type, RomType::Cartridge);
+    assert_eq!(info.rom_base, 0xB000_0000);
+    assert_eq!(info.reset_type, ResetType::Cold);
+    assert_eq!(info.cic, Cic::Cic6102);
+    assert_eq!(info.memory_size, 0x0040_0000);
+    assert!(!info.has_expansion_pak());
+    assert_eq!(info.app_nmi_buffer, [0; 64]);
+}
+
+#[test]
+fn parses_pal_nmi_with_expansion_pak() {
+    let mut raw = ntsc_6102();
+    raw[3] = 0;
+    raw[15] = 1;
+    raw[19] = 0x91;
+    raw[24..28].copy_from_slice(&0x0080_0000u32.to_be_bytes());
+    raw[0x1C] = 0xAB;
+    raw[BOOT_INFO_SIZE - 1] = 0xCD;
+
+    let info = BootInfo::parse(&raw).unwrap();
+    assert_eq!(info.tv_type, TvType::Pal);
+    assert_eq!(info.reset_type, ResetType::Nmi);
+    assert_eq!(info.cic, Cic::Cic6105);
+    assert!(info.has_expansion_pak());
+    assert_eq!(info.app_nmi_buffer[0], 0xAB);
+    assert_eq!(info.app_nmi_buffer[63], 0xCD);
+}
+
+#[test]
+fn identifies_cic_from_seed() {
+    assert_eq!(Cic::from(0x78), Cic::Cic6103);
+    assert_eq!(Cic::from(0x85), Cic::Cic6106);
+    assert_eq!(Cic::from(0xDD), Cic::Cic8303);
+    assert_eq!(Cic::from(0x12), Cic::Unknown(0x12));
+}
+
+#[test]
+fn rejects_invalid_block() {
+    let mut raw = ntsc_6102();
+    raw[3] = 3;
+    assert_eq!(BootInfo::parse(&raw), None);
+
+    let mut raw = ntsc_6102();
+    raw[7] = 2;
+    assert_eq!(BootInfo::parse(&raw), None);
+
+    let mut raw = ntsc_6102();
+    raw[15] = 2;
+    assert_eq!(BootInfo::parse(&raw), None);
+
+    let mut raw = ntsc_6102();
+    raw[24..28].copy_from_slice(&0x0042_0000u32.to_be_bytes());
+    assert_eq!(BootInfo::parse(&raw), None);
+
+    assert_eq!(BootInfo::parse(&[0xFF; BOOT_INFO_SIZE]), None);
+}
@@ -0,0 +1,57 @@
+use embedded_graphics::{
+    mono_font::{ascii::FONT_6X10, MonoTextStyle},
+    pixelcolor::Rgb888,
+    prelude::*,
+    primitives::{PrimitiveStyle, Rectangle},
+    text::{Baseline, Text},
+};
+use nintendo64_pac::vi::{
+    framebuffer::Framebuffer,
+    mode::{NTSC_LPN1, NTSC_LPN2},
+    pixel::{Rgba5551, Rgba8888},
+};
+
+#[test]
+fn draws_rectangle_into_16_bit_framebuffer() {
+    let mut memory = vec![0; 320 * 240 * 2];
+    let mut framebuffer = Framebuffer::<Rgba5551>::for_mode(&mut memory, &NTSC_LPN1).unwrap();
+    assert_eq!(framebuffer.size(), Size::new(320, 240));
+
+    framebuffer.clear(Rgba5551::BLACK).unwrap();
+    Rectangle::new(Point::new(10, 20), Size::new(4, 3))
+        .into_styled(PrimitiveStyle::with_fill(Rgba5551::RED))
+        .draw(&mut framebuffer)
+        .unwrap();
+
+    assert_eq!(framebuffer.get(10, 20), Some(Rgba5551::RED));
+    assert_eq!(framebuffer.get(13, 22), Some(Rgba5551::RED));
+    assert_eq!(framebuffer.get(14, 22), Some(Rgba5551::BLACK));
+    assert_eq!(framebuffer.get(10, 23), Some(Rgba5551::BLACK));
+
+    let offset = (20 * 320 + 10) * 2;
+    assert_eq!(&memory[offset..offset + 2], &[0xF8, 0x01]);
+}
+
+#[test]
+fn draws_text_into_32_bit_framebuffer() {
+    let mut memory = vec![0; 320 * 240 * 4];
+    let mut framebuffer = Framebuffer::<Rgba8888>::for_mode(&mut memory, &NTSC_LPN2).unwrap();
+
+    let style = MonoTextStyle::new(&FONT_6X10, Rgba8888::from(Rgb888::WHITE));
+    Text::with_baseline("N64", Point::new(-2, 0), style, Baseline::Top)
+        .draw(&mut framebuffer)
+        .unwrap();
+
+    let lit = (0..10)
+        .flat_map(|y| (0..16).map(move |x| (x, y)))
+        .filter(|&(x, y)| framebuffer.get(x, y) == Some(Rgba8888::WHITE))
+        .count();
+    assert!(lit > 0);
+    assert_eq!(framebuffer.get(100, 100), Some(Rgba8888(0)));
+}
+
+#[test]
+fn rejects_mismatched_pixel_size() {
+    let mut memory = vec![0; 320 * 240 * 4];
+    assert!(Framebuffer::<Rgba8888>::for_mode(&mut memory, &NTSC_LPN1).is_err());
+}
@@ -0,0 +1,7 @@
+use nintendo64_pac::hardware::Hardware;
+
+#[test]
+fn takes_hardware_once() {
+    assert!(Hardware::take().is_some());
+    assert!(Hardware::take().is_none());
+}
@@ -0,0 +1,88 @@
+use nintendo64_pac::tlb::{CacheAlgorithm, Entry, EntryHi, EntryLo, MapError, PageMask, PageSize};
+
+#[test]
+fn encodes_entry_lo() {
+    let entry_lo = EntryLo::new(0x0012_3000, CacheAlgorithm::Cacheable, true);
+    assert_eq!(entry_lo.raw(), (0x123 << 6) | (3 << 3) | 0b111);
+
+    let entry_lo = EntryLo::new(0x0040_0000, CacheAlgorithm::Uncached, false);
+    assert_eq!(entry_lo.raw(), (0x400 << 6) | (2 << 3) | 0b011);
+}
+
+#[test]
+fn encodes_entry_hi() {
+    assert_eq!(EntryHi::new(0x0040_3FFF, 7).raw(), 0x0040_2007);
+}
+
+#[test]
+fn decodes_every_cache_algorithm() {
+    for value in 0..8 {
+        let entry_lo = EntryLo(value << 3);
+        assert_eq!(u8::from(entry_lo.cache_algorithm()), value as u8);
+    }
+    assert_eq!(
+        Entry::invalid(0).entry_lo0.cache_algorithm(),
+        CacheAlgorithm::Reserved0
+    );
+}
+
+#[test]
+fn encodes_page_mask() {
+    for size in PageSize::ALL {
+        let page_mask = PageMask::new(size);
+        assert_eq!(page_mask.raw(), u32::from(size.mask()) << 13);
+        assert_eq!(page_mask.size(), Some(size));
+    }
+    assert_eq!(PageMask::new(PageSize::SixteenMegabytes).raw(), 0x01FF_E000);
+    assert_eq!(PageMask(0x0000_2000).size(), None);
+}
+
+#[test]
+fn maps_single_page_as_global() {
+    let entry = Entry::map(0x0040_0000, 0x0010_0000, 4).unwrap();
+    assert_eq!(entry.page_mask.size(), Some(PageSize::FourKilobytes));
+    assert_eq!(entry.entry_hi.raw(), 0x0040_0000);
+    assert!(entry.entry_lo0.valid() && entry.entry_lo0.global());
+    assert!(!entry.entry_lo1.valid() && entry.entry_lo1.global());
+}
+
+#[test]
+fn maps_page_pair() {
+    let entry = Entry::map(0x0080_0000, 0x0020_0000, 2048).unwrap();
+    assert_eq!(entry.page_mask.size(), Some(PageSize::OneMegabyte));
+    assert_eq!(entry.entry_lo0.raw() >> 6, 0x200);
+    assert_eq!(entry.entry_lo1.raw() >> 6, 0x300);
+    assert!(entry.entry_lo1.valid() && entry.entry_lo1.dirty());
+}
+
+#[test]
+fn rejects_bad_mappings() {
+    assert_eq!(Entry::map(0, 0, 0), Err(MapError::InvalidLength));
+    assert_eq!(Entry::map(0, 0, 6), Err(MapError::InvalidLength));
+    assert_eq!(Entry::map(0, 0, 0x10_0000), Err(MapError::InvalidLength));
+    assert_eq!(
+        Entry::map(0x8000_0000, 0, 4),
+        Err(MapError::UnmappedSegment)
+    );
+    assert_eq!(Entry::map(0x1000, 0, 8), Err(MapError::Misaligned));
+    assert_eq!(Entry::map(0, 0x1000, 16), Err(MapError::Misaligned));
+    assert_eq!(Entry::map(0, 0x007F_F000, 8), Err(MapError::OutOfRange));
+}
+
+#[test]
+fn guards_page() {
+    let entry = Entry::guard(0x0010_1000).unwrap();
+    assert_eq!(entry.entry_hi.raw(), 0x0010_0000);
+    assert!(!entry.entry_lo0.valid() && !entry.entry_lo1.valid());
+    assert!(entry.entry_lo0.global() && entry.entry_lo1.global());
+
+    assert_eq!(Entry::guard(0x0010_0800), Err(MapError::Misaligned));
+    assert_eq!(Entry::guard(0xA000_0000), Err(MapError::UnmappedSegment));
+}
+
+#[test]
+fn parks_invalid_entries_in_kseg0() {
+    assert_eq!(Entry::invalid(0).entry_hi.raw(), 0x8000_0000);
+    assert_eq!(Entry::invalid(3).entry_hi.raw(), 0x8000_6000);
+    assert!(!Entry::invalid(3).entry_lo0.valid());
+}
@@ -0,0 +1,187 @@
+use nintendo64_pac::ai::vadpcm::{
+    aifc::{self, AudioFile, FileError, Pcm},
+    encoder::{self, EncoderOptions},
+    wav, Decoder, VadpcmError,
+};
+
+fn tone(len: usize) -> Pcm {
+    let samples = (0..len)
+        .map(|index| {
+            let t = index as f64 / 32000.0;
+            let value = (t * 440.0 * std::f64::consts::TAU).sin() * 8000.0
+                + (t * 1250.0 * std::f64::consts::TAU).sin() * 3000.0;
+            value as i16
+        })
+        .collect();
+    Pcm {
+        rate: 32000,
+        samples,
+    }
+}
+
+fn decode(sound: &aifc::VadpcmSound) -> Vec<i16> {
+    let mut decoder = Decoder::new(sound.codebook().unwrap());
+    let mut out = vec![0; sound.data.len() / 9 * 16];
+    decoder.decode(&sound.data, &mut out).unwrap();
+    out.truncate(sound.samples as usize);
+    out
+}
+
+fn snr(reference: &[i16], decoded: &[i16]) -> f64 {
+    let signal: f64 = reference.iter().map(|&s| f64::from(s).powi(2)).sum();
+    let noise: f64 = reference
+        .iter()
+        .zip(decoded)
+        .map(|(&a, &b)| (f64::from(a) - f64::from(b)).powi(2))
+        .sum();
+    10.0 * (signal / noise).log10()
+}
+
+#[test]
+fn encodes_with_low_noise() {
+    let pcm = tone(4000);
+    let sound = encoder::encode(&pcm, &EncoderOptions::new()).unwrap();
+
+    assert_eq!(sound.samples, 4000);
+    assert_eq!(sound.data.len(), 250 * 9);
+    assert_eq!(sound.coefficients.len(), 2 * 4 * 8);
+    assert!(snr(&pcm.samples, &decode(&sound)) > 25.0);
+}
+
+#[test]
+fn records_loop_state() {
+    let pcm = tone(1024);
+    let options = EncoderOptions::new().looping(192, 1000, u32::MAX);
+    let sound = encoder::encode(&pcm, &options).unwrap();
+    let decoded = decode(&sound);
+
+    let start = &sound.loops[0];
+    assert_eq!((start.start, start.end, start.count), (192, 1000, u32::MAX));
+    assert_eq!(start.state[..], decoded[176..192]);
+
+    let options = EncoderOptions::new().looping(0, 1024, 1);
+    let sound = encoder::encode(&pcm, &options).unwrap();
+    assert_eq!(sound.loops[0].state, [0; 16]);
+}
+
+#[test]
+fn rejects_invalid_loops() {
+    let pcm = tone(1024);
+    for (start, end) in [
+        (1000, 2000),
+        (512, 512),
+        (512, 256),
+        (200, 1000),
+        (17, 1024),
+    ] {
+        let options = EncoderOptions::new().looping(start, end, 1);
+        assert_eq!(
+            encoder::encode(&pcm, &options),
+            Err(VadpcmError::InvalidLoop),
+            "{}..{}",
+            start,
+            end
+        );
+    }
+}
+
+#[test]
+fn rejects_invalid_options() {
+    let pcm = tone(64);
+    assert_eq!(
+        encoder::encode(&pcm, &EncoderOptions::new().order(0)),
+        Err(VadpcmError::InvalidOrder)
+    );
+    assert_eq!(
+        encoder::encode(&pcm, &EncoderOptions::new().predictors(17)),
+        Err(VadpcmError::InvalidPredictorCount)
+    );
+}
+
+#[test]
+fn aifc_round_trip() {
+    let options = EncoderOptions::new().looping(32, 500, 3);
+    let sound = encoder::encode(&tone(500), &options).unwrap();
+    let file = aifc::write_aifc(&sound);
+
+    assert_eq!(&file[0..4], b"FORM");
+    assert_eq!(&file[8..12], b"AIFC");
+    assert_eq!(aifc::read(&file), Ok(AudioFile::Vadpcm(sound)));
+}
+
+#[test]
+fn aiff_round_trip() {
+    let pcm = tone(300);
+    let file = aifc::write_aiff(&pcm);
+    assert_eq!(&file[8..12], b"AIFF");
+    assert_eq!(aifc::read(&file), Ok(AudioFile::Pcm(pcm)));
+}
+
+#[test]
+fn extended_floats() {
+    let bytes = [0x40, 0x0E, 0xAC, 0x44, 0, 0, 0, 0, 0, 0];
+    assert_eq!(aifc::u32_to_extended(44100), bytes);
+    assert_eq!(aifc::extended_to_u32(bytes), 44100);
+    assert_eq!(aifc::extended_to_u32(aifc::u32_to_extended(1)), 1);
+    assert_eq!(aifc::extended_to_u32(aifc::u32_to_extended(0)), 0);
+}
+
+#[test]
+fn reads_stereo_wav() {
+    let mut file = Vec::new();
+    file.extend_from_slice(b"RIFF");
+    file.extend_from_slice(&44u32.to_le_bytes());
+    file.extend_from_slice(b"WAVEfmt ");
+    file.extend_from_slice(&16u32.to_le_bytes());
+    file.extend_from_slice(&[1, 0, 2, 0]);
+    file.extend_from_slice(&22050u32.to_le_bytes());
+    file.extend_from_slice(&(22050u32 * 4).to_le_bytes());
+    file.extend_from_slice(&[4, 0, 16, 0]);
+    file.extend_from_slice(b"data");
+    file.extend_from_slice(&8u32.to_le_bytes());
+    for sample in [100i16, 300, -1000, -2000] {
+        file.extend_from_slice(&sample.to_le_bytes());
+    }
+
+    let pcm = wav::read(&file).unwrap();
+    assert_eq!(pcm.rate, 22050);
+    assert_eq!(pcm.samples, [200, -1500]);
+
+    file[20] = 3;
+    assert_eq!(wav::read(&file), Err(FileError::UnsupportedFormat));
+    assert_eq!(wav::read(b"RIFF"), Err(FileError::InvalidFormat));
+}
+
+#[test]
+fn reads_extensible_wav() {
+    const SUBTYPE_PCM: [u8; 16] = [
+        0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x10, 0x00, 0x80, 0x00, 0x00, 0xAA, 0x00, 0x38, 0x9B,
+        0x71,
+    ];
+
+    let mut file = Vec::new();
+    file.extend_from_slice(b"RIFF");
+    file.extend_from_slice(&64u32.to_le_bytes());
+    file.extend_from_slice(b"WAVEfmt ");
+    file.extend_from_slice(&40u32.to_le_bytes());
+    file.extend_from_slice(&[0xFE, 0xFF, 1, 0]);
+    file.extend_from_slice(&44100u32.to_le_bytes());
+    file.extend_from_slice(&(44100u32 * 2).to_le_bytes());
+    file.extend_from_slice(&[2, 0, 16, 0]);
+    // Extension size, valid bits and channel mask.
+    file.extend_from_slice(&[22, 0, 16, 0, 4, 0, 0, 0]);
+    file.extend_from_slice(&SUBTYPE_PCM);
+    file.extend_from_slice(b"data");
+    file.extend_from_slice(&4u32.to_le_bytes());
+    for sample in [1234i16, -5678] {
+        file.extend_from_slice(&sample.to_le_bytes());
+    }
+
+    let pcm = wav::read(&file).unwrap();
+    assert_eq!(pcm.rate, 44100);
+    assert_eq!(pcm.samples, [1234, -5678]);
+
+    // IEEE float subformat.
+    file[44] = 3;
+    assert_eq!(wav::read(&file), Err(FileError::UnsupportedFormat));
+}
@@ -0,0 +1,80 @@
+use nintendo64_pac::vi::{
+    capture::{encode, framebuffer_size, Capture, CaptureError, CAPTURE_HEADER_SIZE},
+    emulation::{render, EmulationOptions, ViRegisters},
+    mode::{NTSC_LPN1, NTSC_LPN2},
+};
+
+const ORIGIN: usize = 0x1000;
+
+/// RDRAM up to `len` bytes past the origin of `registers`, which is a line
+/// past the framebuffer start.
+fn rdram(registers: &ViRegisters, len: usize) -> (usize, Vec<u8>) {
+    let origin = registers.origin.raw() as usize;
+    let rdram = (0..origin + len).map(|index| (index * 7) as u8).collect();
+    (origin, rdram)
+}
+
+#[test]
+fn round_trips() {
+    for mode in [NTSC_LPN1, NTSC_LPN2] {
+        let registers = ViRegisters::from_mode(&mode, ORIGIN as u32, 0);
+        let size = framebuffer_size(&registers).unwrap();
+        let (origin, rdram) = rdram(&registers, size);
+
+        let mut buffer = vec![0; CAPTURE_HEADER_SIZE + size];
+        let len = encode(&registers, &rdram[origin..], &mut buffer).unwrap();
+        assert_eq!(len, buffer.len());
+
+        let capture = Capture::parse(&buffer).unwrap();
+        assert_eq!(capture.registers, registers);
+        assert_eq!(capture.framebuffer, &rdram[origin..]);
+
+        let (width, height) = capture.output_size();
+        let mut expected = vec![0; (width * height) as usize * 4];
+        let mut rendered = expected.clone();
+        render(&registers, &rdram, EmulationOptions::ALL, &mut expected).unwrap();
+        capture
+            .render(EmulationOptions::ALL, &mut rendered)
+            .unwrap();
+        assert_eq!(rendered, expected);
+    }
+}
+
+#[test]
+fn rejects_small_buffer() {
+    let registers = ViRegisters::from_mode(&NTSC_LPN1, ORIGIN as u32, 0);
+    let size = framebuffer_size(&registers).unwrap();
+    let (origin, rdram) = rdram(&registers, size);
+    let mut buffer = vec![0; CAPTURE_HEADER_SIZE + size - 1];
+    assert_eq!(
+        encode(&registers, &rdram[origin..], &mut buffer),
+        Err(CaptureError::BufferTooSmall)
+    );
+}
+
+#[test]
+fn rejects_invalid_captures() {
+    let registers = ViRegisters::from_mode(&NTSC_LPN1, ORIGIN as u32, 0);
+    let mut buffer = vec![0; CAPTURE_HEADER_SIZE + 16];
+    encode(&registers, &[0; 16], &mut buffer).unwrap();
+    assert!(Capture::parse(&buffer).is_ok());
+
+    let truncated = &buffer[..buffer.len() - 1];
+    assert_eq!(Capture::parse(truncated), Err(CaptureError::InvalidFormat));
+
+    let mut bad_magic = buffer.clone();
+    bad_magic[0] = b'X';
+    assert_eq!(Capture::parse(&bad_magic), Err(CaptureError::InvalidFormat));
+
+    let mut bad_version = buffer.clone();
+    bad_version[5] = 2;
+    assert_eq!(
+        Capture::parse(&bad_version),
+        Err(CaptureError::InvalidFormat)
+    );
+
+    let mut huge = buffer.clone();
+    huge[6..8].copy_from_slice(&u16::MAX.to_be_bytes());
+    huge[64..68].copy_from_slice(&u32::MAX.to_be_bytes());
+    assert_eq!(Capture::parse(&huge), Err(CaptureError::InvalidFormat));
+}
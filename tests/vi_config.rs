@@ -0,0 +1,104 @@
+use nintendo64_pac::vi::{
+    config::{ConfigError, Overscan, ViConfig},
+    filters::FilterError,
+    mode::VideoStandard,
+    AntialiasMode, PixelSize,
+};
+
+#[test]
+fn builds_low_resolution_mode() {
+    let mode = ViConfig::new(VideoStandard::Ntsc, 320, 240)
+        .build()
+        .unwrap();
+    assert!(!mode.is_interlaced());
+    assert_eq!(mode.framebuffer_width(), 320);
+    assert_eq!(mode.framebuffer_height(), 240);
+    assert_eq!(mode.width.0, 320);
+    assert_eq!(mode.x_scale.raw(), 0x200);
+    assert_eq!(mode.h_video.raw(), 0x006C_02EC);
+    assert_eq!(mode.fields[0].v_video.raw(), 0x0023_0203);
+    assert_eq!(mode.fields[0].y_scale.raw(), 0x400);
+}
+
+#[test]
+fn splits_tall_framebuffer_between_fields() {
+    let mode = ViConfig::new(VideoStandard::Ntsc, 640, 480)
+        .build()
+        .unwrap();
+    assert!(mode.is_interlaced());
+    assert_eq!(mode.framebuffer_width(), 640);
+    assert_eq!(mode.framebuffer_height(), 480);
+    assert_eq!(mode.width.0, 1280);
+    assert_eq!(mode.fields[0].origin_offset, 0);
+    assert_eq!(mode.fields[1].origin_offset, 1280);
+    assert_eq!(mode.fields[0].y_scale.raw(), 0x400);
+    assert_eq!(mode.fields[0].v_video.raw(), 0x0021_0201);
+    assert_eq!(mode.fields[1].v_video.raw(), 0x0023_0203);
+}
+
+#[test]
+fn applies_overscan_and_position() {
+    let mode = ViConfig::new(VideoStandard::Pal, 320, 240)
+        .overscan(Overscan::uniform(16, 8))
+        .position(-4, 2)
+        .build()
+        .unwrap();
+    assert_eq!(mode.h_video.raw(), ((128 + 16 - 4) << 16) | (768 - 16 - 4));
+    assert_eq!(
+        mode.fields[0].v_video.raw(),
+        ((43 + 16 + 4) << 16) | (619 - 16 + 4)
+    );
+}
+
+#[test]
+fn rejects_invalid_resolution() {
+    let error = Err(ConfigError::InvalidResolution);
+    assert_eq!(ViConfig::new(VideoStandard::Ntsc, 0, 240).build(), error);
+    assert_eq!(ViConfig::new(VideoStandard::Ntsc, 320, 0).build(), error);
+    assert_eq!(
+        ViConfig::new(VideoStandard::Ntsc, 0x800, 240).build(),
+        error
+    );
+
+    let blank = ViConfig::new(VideoStandard::Ntsc, 320, 240).pixel_size(PixelSize::Blank);
+    assert_eq!(blank.build(), Err(ConfigError::InvalidPixelSize));
+}
+
+#[test]
+fn rejects_window_out_of_range() {
+    let config = ViConfig::new(VideoStandard::Ntsc, 320, 240);
+    let error = Err(ConfigError::WindowOutOfRange);
+    assert_eq!(config.position(0, i32::MAX).build(), error);
+    assert_eq!(config.position(0, i32::MIN).build(), error);
+    assert_eq!(config.position(i32::MIN, 0).build(), error);
+    assert_eq!(config.position(400, 0).build(), error);
+    assert_eq!(
+        config.overscan(Overscan::uniform(0, u32::MAX)).build(),
+        error
+    );
+    assert_eq!(
+        config.overscan(Overscan::uniform(u32::MAX, 0)).build(),
+        error
+    );
+    assert_eq!(config.overscan(Overscan::uniform(320, 0)).build(), error);
+    assert_eq!(config.overscan(Overscan::uniform(0, 120)).build(), error);
+}
+
+#[test]
+fn rejects_scale_out_of_range() {
+    let config = ViConfig::new(VideoStandard::Ntsc, 320, u32::MAX).interlaced(false);
+    assert_eq!(config.build(), Err(ConfigError::ScaleOutOfRange));
+}
+
+#[test]
+fn rejects_invalid_filters() {
+    let config = ViConfig::new(VideoStandard::Ntsc, 320, 240)
+        .pixel_size(PixelSize::ThirtyTwoBit)
+        .antialias_mode(AntialiasMode::Full);
+    assert_eq!(
+        config.build(),
+        Err(ConfigError::InvalidFilters(
+            FilterError::FullAntialiasWith32Bit
+        ))
+    );
+}
@@ -0,0 +1,130 @@
+use nintendo64_pac::{
+    exception::SCREEN_LINES,
+    font::{self, GLYPH_HEIGHT, GLYPH_WIDTH},
+    vi::{
+        console::Console,
+        framebuffer::Framebuffer,
+        mode::{presets, VideoStandard},
+        pixel::{Pixel, Rgba5551},
+        PixelSize,
+    },
+};
+
+const SENTINEL: u8 = 0xA5;
+
+fn console(memory: &mut [u8], height: u32) -> Console<'_, Rgba5551> {
+    let framebuffer = Framebuffer::new(memory, PixelSize::SixteenBit, 320, height).unwrap();
+    let mut console = Console::new(framebuffer, Rgba5551::WHITE, Rgba5551::BLACK);
+    console.clear();
+    console
+}
+
+/// Whether the text cell at `(column, row)` shows `c`.
+fn shows(framebuffer: &Framebuffer<Rgba5551>, column: u32, row: u32, c: char) -> bool {
+    font::glyph(c).iter().enumerate().all(|(y, bits)| {
+        (0..GLYPH_WIDTH).all(|x| {
+            let expected = match bits & (0x80 >> x) != 0 {
+                true => Rgba5551::WHITE,
+                false => Rgba5551::BLACK,
+            };
+            let pixel = framebuffer.get(column * GLYPH_WIDTH + x, row * GLYPH_HEIGHT + y as u32);
+            pixel == Some(expected)
+        })
+    })
+}
+
+#[test]
+fn wraps_long_lines() {
+    let mut memory = vec![0; 320 * 240 * Rgba5551::BYTES];
+    let mut console = console(&mut memory, 240);
+    assert_eq!(console.columns(), 40);
+
+    for _ in 0..40 {
+        console.put('a');
+    }
+    assert_eq!(console.cursor(), (40, 0));
+    console.put('b');
+    assert_eq!(console.cursor(), (1, 1));
+
+    let framebuffer = console.release();
+    assert!(shows(&framebuffer, 39, 0, 'a'));
+    assert!(shows(&framebuffer, 0, 1, 'b'));
+}
+
+#[test]
+fn handles_control_characters() {
+    let mut memory = vec![0; 320 * 240 * Rgba5551::BYTES];
+    let mut console = console(&mut memory, 240);
+
+    console.put('a');
+    console.put('b');
+    console.put('\r');
+    console.put('c');
+    console.put('\n');
+    assert_eq!(console.cursor(), (0, 1));
+
+    let framebuffer = console.release();
+    assert!(shows(&framebuffer, 0, 0, 'c'));
+    assert!(shows(&framebuffer, 1, 0, 'b'));
+}
+
+#[test]
+fn scrolls_past_last_row() {
+    let mut memory = vec![0; 320 * 240 * Rgba5551::BYTES];
+    let mut console = console(&mut memory, 240);
+    let rows = console.rows();
+    assert_eq!(rows, 30);
+
+    for row in 0..=rows {
+        if row > 0 {
+            console.put('\n');
+        }
+        console.put(char::from(b'A' + (row % 26) as u8));
+    }
+    assert_eq!(console.cursor(), (1, rows - 1));
+
+    let framebuffer = console.release();
+    assert!(shows(&framebuffer, 0, 0, 'B'));
+    assert!(shows(&framebuffer, 0, rows - 2, 'D'));
+    assert!(shows(&framebuffer, 0, rows - 1, 'E'));
+    assert!(shows(&framebuffer, 1, rows - 1, ' '));
+}
+
+#[test]
+fn keeps_to_screen_lines() {
+    let mut memory = vec![SENTINEL; 320 * 240 * Rgba5551::BYTES];
+    let mut console = console(&mut memory, SCREEN_LINES);
+    assert_eq!(console.rows(), SCREEN_LINES / GLYPH_HEIGHT);
+
+    for _ in 0..100 {
+        console.put('x');
+        console.put('\n');
+    }
+
+    let text_bytes = (320 * SCREEN_LINES) as usize * Rgba5551::BYTES;
+    assert!(memory[text_bytes..].iter().all(|&byte| byte == SENTINEL));
+    assert!(memory[..text_bytes].iter().all(|&byte| byte != SENTINEL));
+}
+
+#[test]
+fn screen_lines_fit_every_mode() {
+    for standard in [
+        VideoStandard::Ntsc,
+        VideoStandard::Pal,
+        VideoStandard::Mpal,
+        VideoStandard::Pal60,
+    ] {
+        for mode in presets(standard) {
+            assert!(SCREEN_LINES <= mode.framebuffer_height());
+        }
+    }
+}
+
+#[test]
+fn clamps_cursor() {
+    let mut memory = vec![0; 320 * 240 * Rgba5551::BYTES];
+    let mut console = console(&mut memory, SCREEN_LINES);
+
+    console.set_cursor(100, 100);
+    assert_eq!(console.cursor(), (39, 27));
+}
@@ -0,0 +1,94 @@
+#![cfg(feature = "std")]
+
+use std::io::ErrorKind;
+
+use nintendo64_pac::vi::{
+    emulation::{render, write_png, write_ppm, EmulationError, EmulationOptions, ViRegisters},
+    mode::NTSC_LPN1,
+};
+
+const ORIGIN: usize = 0x1000;
+
+const RED: u16 = 0xF801;
+const BLUE: u16 = 0x003F;
+
+/// RDRAM holding a 320x240 16-bit framebuffer at [`ORIGIN`], red on the left
+/// half and blue on the right.
+fn rdram() -> Vec<u8> {
+    let mut rdram = vec![0; ORIGIN + 320 * 240 * 2];
+    for (index, pixel) in rdram[ORIGIN..].chunks_exact_mut(2).enumerate() {
+        let color = if index % 320 < 160 { RED } else { BLUE };
+        pixel.copy_from_slice(&color.to_be_bytes());
+    }
+    rdram
+}
+
+#[test]
+fn scans_out_known_framebuffer() {
+    let registers = ViRegisters::from_mode(&NTSC_LPN1, ORIGIN as u32, 0);
+    let (width, height) = registers.output_size();
+    let mut out = vec![0; (width * height) as usize * 4];
+
+    let size = render(&registers, &rdram(), EmulationOptions::NONE, &mut out).unwrap();
+    assert_eq!(size, (640, 237));
+
+    for (index, pixel) in out.chunks_exact(4).enumerate() {
+        let expected = match index % 640 < 320 {
+            true => [0xFF, 0, 0, 0xFF],
+            false => [0, 0, 0xFF, 0xFF],
+        };
+        assert_eq!(pixel, expected, "pixel {}", index);
+    }
+}
+
+#[test]
+fn rejects_small_output() {
+    let registers = ViRegisters::from_mode(&NTSC_LPN1, ORIGIN as u32, 0);
+    let mut out = vec![0; 640 * 4];
+    assert_eq!(
+        render(&registers, &rdram(), EmulationOptions::NONE, &mut out),
+        Err(EmulationError::OutputTooSmall)
+    );
+}
+
+#[test]
+fn writes_ppm() {
+    let rgba = [1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16];
+    let mut ppm = Vec::new();
+    write_ppm(&mut ppm, 2, 2, &rgba).unwrap();
+    assert_eq!(
+        ppm,
+        b"P6\n2 2\n255\n\x01\x02\x03\x05\x06\x07\x09\x0A\x0B\x0D\x0E\x0F"
+    );
+}
+
+#[test]
+fn writes_png() {
+    let rgba = [1, 2, 3, 4, 5, 6, 7, 8];
+    let mut png = Vec::new();
+    write_png(&mut png, 1, 2, &rgba).unwrap();
+
+    assert_eq!(&png[..8], b"\x89PNG\r\n\x1A\n");
+    assert_eq!(&png[8..16], b"\0\0\0\x0DIHDR");
+    assert_eq!(&png[16..29], &[0, 0, 0, 1, 0, 0, 0, 2, 8, 6, 0, 0, 0]);
+
+    // One stored deflate block of both filtered rows.
+    let idat = &png[33..];
+    assert_eq!(&idat[..8], b"\0\0\0\x15IDAT");
+    assert_eq!(&idat[8..15], &[0x78, 0x01, 1, 10, 0, 0xF5, 0xFF]);
+    assert_eq!(&idat[15..25], &[0, 1, 2, 3, 4, 0, 5, 6, 7, 8]);
+    assert_eq!(&idat[25..29], &[0x00, 0x8C, 0x00, 0x25]);
+
+    assert_eq!(&png[png.len() - 12..], b"\0\0\0\0IEND\xAE\x42\x60\x82");
+}
+
+#[test]
+fn rejects_short_pixels() {
+    let rgba = [0; 15];
+    let error = write_ppm(Vec::new(), 2, 2, &rgba).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::InvalidInput);
+    let error = write_png(Vec::new(), 2, 2, &rgba).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::InvalidInput);
+    let error = write_png(Vec::new(), u32::MAX, u32::MAX, &rgba).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::InvalidInput);
+}
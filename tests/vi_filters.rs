@@ -0,0 +1,77 @@
+use nintendo64_pac::vi::{
+    filters::{FilterError, ViFilters},
+    mode::{presets, VideoStandard, NTSC_LAN2, NTSC_LPF1, NTSC_LPN1},
+    AntialiasMode, PixelSize, ViStatusReg,
+};
+
+#[test]
+fn builds_every_preset() {
+    for standard in [
+        VideoStandard::Ntsc,
+        VideoStandard::Pal,
+        VideoStandard::Mpal,
+        VideoStandard::Pal60,
+    ] {
+        for mode in presets(standard) {
+            let filters = ViFilters::from_status(mode.status, mode.is_interlaced()).unwrap();
+            assert_eq!(filters.build(mode.is_interlaced()), Ok(mode.status));
+
+            let thirty_two_bit = mode.status.pixel_size() == PixelSize::ThirtyTwoBit;
+            assert_eq!(mode.status.gamma_dither_enable(), !thirty_two_bit);
+        }
+    }
+}
+
+#[test]
+fn keeps_preset_status() {
+    assert_eq!(NTSC_LPN1.status.raw(), 0x0000_330E);
+    assert_eq!(NTSC_LPF1.status.raw(), 0x0000_334E);
+    assert_eq!(NTSC_LAN2.status.raw(), 0x0000_311B);
+}
+
+#[test]
+fn status_matches_build() {
+    let filters = ViFilters::new(PixelSize::SixteenBit)
+        .antialias_mode(AntialiasMode::ResampleOnly)
+        .dither_filter(true)
+        .pixel_advance(5);
+    let status = filters.build(true).unwrap();
+    assert_eq!(status, filters.status(true));
+    assert_eq!(ViFilters::from_status(status, true), Ok(filters));
+}
+
+#[test]
+fn rejects_invalid_combinations() {
+    let sixteen_bit = ViFilters::new(PixelSize::SixteenBit);
+    let thirty_two_bit = ViFilters::new(PixelSize::ThirtyTwoBit);
+
+    assert_eq!(
+        ViFilters::new(PixelSize::Blank).build(false),
+        Err(FilterError::InvalidPixelSize)
+    );
+    assert_eq!(
+        ViFilters::validate(sixteen_bit.status(true), false),
+        Err(FilterError::SerrateMismatch)
+    );
+    assert_eq!(
+        sixteen_bit
+            .antialias_mode(AntialiasMode::None)
+            .divot(true)
+            .build(false),
+        Err(FilterError::DivotWithoutAntialias)
+    );
+    assert_eq!(
+        thirty_two_bit.dither_filter(true).build(false),
+        Err(FilterError::DitherFilterWith32Bit)
+    );
+    assert_eq!(
+        thirty_two_bit
+            .antialias_mode(AntialiasMode::Full)
+            .build(false),
+        Err(FilterError::FullAntialiasWith32Bit)
+    );
+    assert_eq!(
+        ViFilters::validate(ViStatusReg(sixteen_bit.status(false).raw() | 0x80), false),
+        Err(FilterError::TestBitSet)
+    );
+}
@@ -0,0 +1,79 @@
+use nintendo64_pac::vi::{
+    framebuffer::{Framebuffer, FramebufferError},
+    mode::{NTSC_HPF1, NTSC_LPN1, NTSC_LPN2},
+    pixel::{Rgba5551, Rgba8888},
+    PixelSize, ViStatusReg,
+};
+
+#[test]
+fn checks_pixel_size() {
+    let mut memory = vec![0; 320 * 240 * 4];
+    assert!(matches!(
+        Framebuffer::<Rgba8888>::new(&mut memory, PixelSize::SixteenBit, 320, 240),
+        Err(FramebufferError::PixelSizeMismatch)
+    ));
+    assert!(matches!(
+        Framebuffer::<Rgba5551>::new(&mut memory, PixelSize::Blank, 320, 240),
+        Err(FramebufferError::PixelSizeMismatch)
+    ));
+    assert!(Framebuffer::<Rgba8888>::new(&mut memory, PixelSize::ThirtyTwoBit, 320, 240).is_ok());
+}
+
+#[test]
+fn checks_memory_size() {
+    let mut memory = vec![0; 320 * 240 * 2 - 1];
+    assert!(matches!(
+        Framebuffer::<Rgba5551>::for_mode(&mut memory, &NTSC_LPN1),
+        Err(FramebufferError::TooSmall)
+    ));
+}
+
+#[test]
+fn rejects_reserved_pixel_size() {
+    let mut mode = NTSC_LPN1;
+    mode.status = ViStatusReg((mode.status.raw() & !0b11) | 1);
+    let mut memory = vec![0; 320 * 240 * 2];
+    assert!(matches!(
+        Framebuffer::<Rgba5551>::for_mode(&mut memory, &mode),
+        Err(FramebufferError::PixelSizeMismatch)
+    ));
+}
+
+#[test]
+fn checks_size_overflow() {
+    let mut memory = vec![0; 16];
+    // 0x10000 * 0x10000 pixels would wrap to zero bytes in 32 bits.
+    assert!(matches!(
+        Framebuffer::<Rgba5551>::new(&mut memory, PixelSize::SixteenBit, 0x10000, 0x10000),
+        Err(FramebufferError::TooSmall)
+    ));
+    assert!(matches!(
+        Framebuffer::<Rgba8888>::new(&mut memory, PixelSize::ThirtyTwoBit, u32::MAX, u32::MAX),
+        Err(FramebufferError::TooSmall | FramebufferError::TooLarge)
+    ));
+}
+
+#[test]
+fn sizes_from_mode() {
+    let mut memory = vec![0; 640 * 480 * 2];
+    let framebuffer = Framebuffer::<Rgba5551>::for_mode(&mut memory, &NTSC_HPF1).unwrap();
+    assert_eq!((framebuffer.width(), framebuffer.height()), (640, 480));
+
+    let mut memory = vec![0; 320 * 240 * 4];
+    let framebuffer = Framebuffer::<Rgba8888>::for_mode(&mut memory, &NTSC_LPN2).unwrap();
+    assert_eq!((framebuffer.width(), framebuffer.height()), (320, 240));
+}
+
+#[test]
+fn reads_back_pixels() {
+    let mut memory = vec![0; 320 * 240 * 2];
+    let mut framebuffer = Framebuffer::<Rgba5551>::for_mode(&mut memory, &NTSC_LPN1).unwrap();
+    framebuffer.fill(Rgba5551::BLACK);
+    framebuffer.set(319, 239, Rgba5551::WHITE);
+    framebuffer.set(320, 0, Rgba5551::WHITE);
+
+    assert_eq!(framebuffer.get(319, 239), Some(Rgba5551::WHITE));
+    assert_eq!(framebuffer.get(0, 0), Some(Rgba5551::BLACK));
+    assert_eq!(framebuffer.get(320, 0), None);
+    assert_eq!(&memory[memory.len() - 2..], &[0xFF, 0xFF]);
+}
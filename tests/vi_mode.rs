@@ -0,0 +1,84 @@
+use nintendo64_pac::vi::{
+    emulation::ViRegisters,
+    mode::{
+        presets, VideoMode, VideoStandard, NTSC_HAF1, NTSC_HPF1, NTSC_HPF2, NTSC_HPN1, NTSC_LPF1,
+        NTSC_LPN1, NTSC_LPN2, PAL_LPN1,
+    },
+    PixelSize,
+};
+
+const FRAMEBUFFER: u32 = 0x0010_0000;
+
+#[test]
+fn matches_libultra_lpn1() {
+    let mode = NTSC_LPN1;
+    assert_eq!(mode.width.raw(), 320);
+    assert_eq!(mode.timing.raw(), 0x03E5_2239);
+    assert_eq!(mode.v_sync.raw(), 0x20D);
+    assert_eq!(mode.h_sync.raw(), 0x0000_0C15);
+    assert_eq!(mode.h_sync_leap.raw(), 0x0C15_0C15);
+    assert_eq!(mode.h_video.raw(), 0x006C_02EC);
+    assert_eq!(mode.x_scale.raw(), 0x200);
+
+    for field in mode.fields {
+        assert_eq!(field.origin_offset, 0x280);
+        assert_eq!(field.y_scale.raw(), 0x400);
+        assert_eq!(field.v_video.raw(), 0x0025_01FF);
+        assert_eq!(field.v_burst.raw(), 0x000E_0204);
+        assert_eq!(field.intr.raw(), 2);
+    }
+}
+
+#[test]
+fn matches_libultra_origins() {
+    let origins = |mode: &VideoMode| [mode.fields[0].origin_offset, mode.fields[1].origin_offset];
+    assert_eq!(origins(&NTSC_LPN1), [640, 640]);
+    assert_eq!(origins(&NTSC_LPN2), [1280, 1280]);
+    assert_eq!(origins(&NTSC_LPF1), [640, 640]);
+    assert_eq!(origins(&NTSC_HPN1), [1280, 1280]);
+    assert_eq!(origins(&NTSC_HPF1), [1280, 2560]);
+    assert_eq!(origins(&NTSC_HAF1), [1280, 2560]);
+    assert_eq!(origins(&NTSC_HPF2), [2560, 5120]);
+    assert_eq!(origins(&PAL_LPN1), [640, 640]);
+}
+
+#[test]
+fn offsets_every_preset_by_a_line() {
+    for standard in [
+        VideoStandard::Ntsc,
+        VideoStandard::Pal,
+        VideoStandard::Mpal,
+        VideoStandard::Pal60,
+    ] {
+        for mode in presets(standard) {
+            let bytes_per_pixel = match mode.status.pixel_size() {
+                PixelSize::ThirtyTwoBit => 4,
+                _ => 2,
+            };
+            let line = mode.framebuffer_width() * bytes_per_pixel;
+            let lines = match mode.framebuffer_height() {
+                480 => [1, 2],
+                _ => [1, 1],
+            };
+            assert_eq!(mode.fields[0].origin_offset, line * lines[0]);
+            assert_eq!(mode.fields[1].origin_offset, line * lines[1]);
+        }
+    }
+}
+
+#[test]
+fn origin_is_relative_to_framebuffer() {
+    for field in 0..2 {
+        let registers = ViRegisters::from_mode(&NTSC_HAF1, FRAMEBUFFER, field);
+        assert_eq!(
+            registers.origin.raw(),
+            FRAMEBUFFER + NTSC_HAF1.fields[field].origin_offset
+        );
+    }
+
+    // Switching from the odd field of one mode starts the next mode from the
+    // same framebuffer.
+    let odd = NTSC_HAF1.origin(FRAMEBUFFER, 1);
+    assert_eq!(odd.raw() - NTSC_HAF1.fields[1].origin_offset, FRAMEBUFFER);
+    assert_eq!(NTSC_LPN1.origin(FRAMEBUFFER, 0).raw(), FRAMEBUFFER + 640);
+}
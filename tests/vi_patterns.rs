@@ -0,0 +1,185 @@
+use nintendo64_pac::vi::{
+    framebuffer::Framebuffer,
+    mode::{VideoStandard, NTSC_LPN1, NTSC_LPN2},
+    patterns::{draw, ModeSelector, TestPattern, PRESET_NAMES},
+    pixel::{Rgba5551, Rgba8888},
+    timing::Violation,
+};
+
+#[test]
+fn steps_through_presets() {
+    let mut selector = ModeSelector::new(VideoStandard::Ntsc);
+    assert_eq!(selector.name(), "LPN1");
+    assert_eq!(selector.mode(), NTSC_LPN1);
+
+    selector.previous();
+    assert_eq!(selector.name(), "HPF2");
+    selector.next();
+    for _ in 0..4 {
+        selector.next();
+    }
+    assert_eq!(selector.name(), "LPN2");
+    assert_eq!(selector.mode(), NTSC_LPN2);
+
+    for _ in 0..PRESET_NAMES.len() {
+        selector.next();
+    }
+    assert_eq!(selector.name(), "LPN2");
+}
+
+#[test]
+fn moves_window() {
+    let mut selector = ModeSelector::new(VideoStandard::Ntsc);
+    selector.nudge(4, -2);
+    assert_eq!(selector.offset(), (4, -2));
+
+    let mode = selector.mode();
+    let h_start = u32::from(NTSC_LPN1.h_video.start_active_video());
+    let v_start = u32::from(NTSC_LPN1.fields[0].v_video.start_active_video());
+    assert_eq!(u32::from(mode.h_video.start_active_video()), h_start + 4);
+    assert_eq!(
+        u32::from(mode.fields[0].v_video.start_active_video()),
+        v_start - 4
+    );
+    assert!(selector.analyze().iter().all(|report| report.is_valid()));
+}
+
+#[test]
+fn clamps_offset() {
+    let mut selector = ModeSelector::new(VideoStandard::Ntsc);
+    selector.nudge(i32::MAX, i32::MAX);
+    selector.nudge(i32::MAX, i32::MAX);
+    let (x, y) = selector.offset();
+    assert!(x > 0 && y > 0);
+
+    let mode = selector.mode();
+    assert_eq!(u32::from(mode.h_video.start_active_video()), 0x3FF);
+    assert_eq!(u32::from(mode.h_video.end_active_video()), 0x3FF);
+    let reports = selector.analyze();
+    assert!(reports[0].violations.contains(Violation::HVideoOutsideLine));
+
+    selector.nudge(i32::MIN, i32::MIN);
+    selector.nudge(i32::MIN, i32::MIN);
+    assert_eq!(selector.offset(), (-x, -y));
+
+    let mode = selector.mode();
+    assert_eq!(u32::from(mode.h_video.start_active_video()), 0);
+    assert_eq!(u32::from(mode.fields[0].v_video.start_active_video()), 0);
+}
+
+/// Draws `pattern` into 320x240 framebuffers of both pixel sizes and checks
+/// the pixels at each point.
+fn check_pattern(pattern: TestPattern, points: &[(u32, u32, Rgba8888)]) {
+    let mut memory = vec![0; 320 * 240 * 4];
+    let mut framebuffer = Framebuffer::<Rgba8888>::for_mode(&mut memory, &NTSC_LPN2).unwrap();
+    draw(&mut framebuffer, pattern);
+    for &(x, y, color) in points {
+        assert_eq!(
+            framebuffer.get(x, y),
+            Some(color),
+            "{:?} ({}, {})",
+            pattern,
+            x,
+            y
+        );
+    }
+
+    let mut memory = vec![0; 320 * 240 * 2];
+    let mut framebuffer = Framebuffer::<Rgba5551>::for_mode(&mut memory, &NTSC_LPN1).unwrap();
+    draw(&mut framebuffer, pattern);
+    for &(x, y, color) in points {
+        assert_eq!(
+            framebuffer.get(x, y),
+            Some(Rgba5551::from(color)),
+            "{:?} ({}, {})",
+            pattern,
+            x,
+            y
+        );
+    }
+}
+
+#[test]
+fn draws_color_bars() {
+    let gray = |level| Rgba8888::from_rgb8(level, level, level);
+    check_pattern(
+        TestPattern::ColorBars,
+        &[
+            // Bars, 45 or 46 pixels wide, down to line 160.
+            (0, 0, gray(0xBF)),
+            (50, 10, Rgba8888::from_rgb8(0xBF, 0xBF, 0x00)),
+            (100, 10, Rgba8888::from_rgb8(0x00, 0xBF, 0xBF)),
+            (319, 159, Rgba8888::from_rgb8(0x00, 0x00, 0xBF)),
+            // Reverse bars down to line 180.
+            (10, 160, Rgba8888::from_rgb8(0x00, 0x00, 0xBF)),
+            (50, 170, Rgba8888::BLACK),
+            (319, 179, gray(0xBF)),
+            // -I, white and +Q, then the PLUGE under the sixth bar.
+            (10, 200, Rgba8888::from_rgb8(0x00, 0x21, 0x4C)),
+            (80, 200, Rgba8888::WHITE),
+            (120, 239, Rgba8888::from_rgb8(0x32, 0x00, 0x6A)),
+            (200, 200, Rgba8888::BLACK),
+            (235, 200, Rgba8888::BLACK),
+            (250, 200, gray(0x05)),
+            (265, 200, gray(0x0A)),
+            (300, 200, Rgba8888::BLACK),
+        ],
+    );
+}
+
+#[test]
+fn draws_crosshatch() {
+    check_pattern(
+        TestPattern::Crosshatch { spacing: 16 },
+        &[
+            (0, 0, Rgba8888::WHITE),
+            (16, 5, Rgba8888::WHITE),
+            (5, 16, Rgba8888::WHITE),
+            (8, 8, Rgba8888::BLACK),
+            (319, 100, Rgba8888::WHITE),
+            (100, 239, Rgba8888::WHITE),
+            (318, 238, Rgba8888::BLACK),
+        ],
+    );
+}
+
+#[test]
+fn draws_safe_area() {
+    check_pattern(
+        TestPattern::SafeArea,
+        &[
+            (160, 0, Rgba8888::WHITE),
+            (319, 120, Rgba8888::WHITE),
+            (1, 1, Rgba8888::BLACK),
+            // Action safe at 16, 12 and title safe at 32, 24.
+            (16, 120, Rgba8888::GREEN),
+            (160, 227, Rgba8888::GREEN),
+            (32, 120, Rgba8888::RED),
+            (287, 120, Rgba8888::RED),
+            // Centre cross with 15 pixel arms.
+            (160, 120, Rgba8888::WHITE),
+            (145, 120, Rgba8888::WHITE),
+            (144, 120, Rgba8888::BLACK),
+            (160, 135, Rgba8888::WHITE),
+            (160, 136, Rgba8888::BLACK),
+            (100, 100, Rgba8888::BLACK),
+        ],
+    );
+}
+
+#[test]
+fn draws_ramps() {
+    check_pattern(
+        TestPattern::Ramps,
+        &[
+            (0, 0, Rgba8888::BLACK),
+            (160, 30, Rgba8888::from_rgb8(0x7F, 0x7F, 0x7F)),
+            (319, 59, Rgba8888::WHITE),
+            (0, 60, Rgba8888::BLACK),
+            (319, 60, Rgba8888::RED),
+            (319, 150, Rgba8888::GREEN),
+            (160, 150, Rgba8888::from_rgb8(0x00, 0x7F, 0x00)),
+            (319, 239, Rgba8888::BLUE),
+        ],
+    );
+}
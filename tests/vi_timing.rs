@@ -0,0 +1,153 @@
+use nintendo64_pac::vi::{
+    mode::{presets, VideoStandard, NTSC_LPN1, PAL_HPF1},
+    timing::{analyze, TimingRegisters, Violation},
+    ViHSyncLeapReg, ViHSyncReg, ViHVideoReg, ViTimingReg, ViVBurstReg, ViVSyncReg, ViVVideoReg,
+};
+
+fn ntsc() -> TimingRegisters {
+    TimingRegisters::from_mode(&NTSC_LPN1, 0)
+}
+
+fn violations(registers: &TimingRegisters) -> Vec<Violation> {
+    analyze(VideoStandard::Ntsc, registers)
+        .violations
+        .iter()
+        .collect()
+}
+
+#[test]
+fn accepts_every_preset() {
+    for standard in [VideoStandard::Ntsc, VideoStandard::Pal, VideoStandard::Mpal] {
+        for mode in presets(standard) {
+            for field in 0..2 {
+                let report = analyze(standard, &TimingRegisters::from_mode(mode, field));
+                assert!(report.is_valid(), "{:?}", report.violations);
+            }
+        }
+    }
+}
+
+#[test]
+fn reports_ntsc_rates() {
+    let report = analyze(VideoStandard::Ntsc, &ntsc());
+    assert!((report.line_rate_hz - 15_734.0).abs() < 1.0);
+    assert!((report.field_rate_hz - 60.0).abs() < 0.5);
+    assert_eq!(report.frame_rate_hz, report.field_rate_hz);
+    assert_eq!(report.half_lines_per_field, 526);
+    assert!(!report.interlaced);
+    assert_eq!(report.active_width, 640);
+    assert_eq!(report.active_lines, 237);
+}
+
+#[test]
+fn reports_interlaced_pal_rates() {
+    let report = analyze(
+        VideoStandard::Pal,
+        &TimingRegisters::from_mode(&PAL_HPF1, 1),
+    );
+    assert!(report.interlaced);
+    assert_eq!(report.half_lines_per_field, 625);
+    assert!((report.field_rate_hz - 50.0).abs() < 0.5);
+    assert!((report.frame_rate_hz - 25.0).abs() < 0.25);
+}
+
+#[test]
+fn rejects_line_rate() {
+    let registers = TimingRegisters {
+        h_sync: ViHSyncReg(0x0000_0B00),
+        h_sync_leap: ViHSyncLeapReg(0x0B00_0B00),
+        ..ntsc()
+    };
+    assert!(violations(&registers).contains(&Violation::LineRateOutOfRange));
+    assert!(!violations(&registers).contains(&Violation::LeapOutOfRange));
+}
+
+#[test]
+fn rejects_field_rate() {
+    let registers = TimingRegisters {
+        v_sync: ViVSyncReg(0x271),
+        ..ntsc()
+    };
+    assert_eq!(violations(&registers), [Violation::FieldRateOutOfRange]);
+}
+
+#[test]
+fn rejects_leap() {
+    let registers = TimingRegisters {
+        h_sync_leap: ViHSyncLeapReg(0x0C15_0B00),
+        ..ntsc()
+    };
+    assert_eq!(violations(&registers), [Violation::LeapOutOfRange]);
+}
+
+#[test]
+fn rejects_sync_overlapping_burst() {
+    let registers = TimingRegisters {
+        timing: ViTimingReg(0x03E5_2240),
+        ..ntsc()
+    };
+    assert_eq!(violations(&registers), [Violation::SyncOverlapsBurst]);
+}
+
+#[test]
+fn rejects_burst_overlapping_video() {
+    let registers = TimingRegisters {
+        h_video: ViHVideoReg(0x005A_02EC),
+        ..ntsc()
+    };
+    assert_eq!(violations(&registers), [Violation::BurstOverlapsVideo]);
+}
+
+#[test]
+fn rejects_h_video_outside_line() {
+    let past_line = TimingRegisters {
+        h_video: ViHVideoReg(0x006C_0330),
+        ..ntsc()
+    };
+    assert_eq!(violations(&past_line), [Violation::HVideoOutsideLine]);
+
+    let empty = TimingRegisters {
+        h_video: ViHVideoReg(0x006C_006C),
+        ..ntsc()
+    };
+    assert_eq!(violations(&empty), [Violation::HVideoOutsideLine]);
+}
+
+#[test]
+fn rejects_v_video_outside_field() {
+    let registers = TimingRegisters {
+        v_video: ViVVideoReg(0x0025_020E),
+        v_burst: ViVBurstReg(0x000E_020E),
+        ..ntsc()
+    };
+    assert!(violations(&registers).contains(&Violation::VVideoOutsideField));
+    assert!(!violations(&registers).contains(&Violation::VideoWithoutBurst));
+}
+
+#[test]
+fn rejects_video_during_v_sync() {
+    let registers = TimingRegisters {
+        v_video: ViVVideoReg(0x0004_01FF),
+        v_burst: ViVBurstReg(0x0004_0204),
+        ..ntsc()
+    };
+    assert!(violations(&registers).contains(&Violation::VideoOverlapsVSync));
+}
+
+#[test]
+fn rejects_v_burst_outside_field() {
+    let registers = TimingRegisters {
+        v_burst: ViVBurstReg(0x0003_0204),
+        ..ntsc()
+    };
+    assert_eq!(violations(&registers), [Violation::VBurstOutsideField]);
+}
+
+#[test]
+fn rejects_video_without_burst() {
+    let registers = TimingRegisters {
+        v_burst: ViVBurstReg(0x000E_0100),
+        ..ntsc()
+    };
+    assert_eq!(violations(&registers), [Violation::VideoWithoutBurst]);
+}